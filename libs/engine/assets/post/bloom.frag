@@ -0,0 +1,24 @@
+#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+uniform vec2 resolution;
+uniform float threshold;
+uniform float intensity;
+uniform float radius;
+
+void main() {
+    vec4 base = texture(scene, v_tex_coords);
+    vec2 texel = radius / resolution;
+    vec3 glow = vec3(0.0);
+    for (int x = -3; x <= 3; x++) {
+        for (int y = -3; y <= 3; y++) {
+            vec3 neighbour = texture(scene, v_tex_coords + vec2(x, y) * texel).rgb;
+            float brightness = max(neighbour.r, max(neighbour.g, neighbour.b));
+            glow += neighbour * max(brightness - threshold, 0.0);
+        }
+    }
+    color = vec4(base.rgb + glow / 49.0 * intensity, base.a);
+}
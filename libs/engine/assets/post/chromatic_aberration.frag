@@ -0,0 +1,16 @@
+#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+uniform vec2 resolution;
+uniform float amount;
+
+void main() {
+    vec2 offset = (v_tex_coords - vec2(0.5)) * amount / resolution * 2.0;
+    vec4 base = texture(scene, v_tex_coords);
+    float red = texture(scene, v_tex_coords + offset).r;
+    float blue = texture(scene, v_tex_coords - offset).b;
+    color = vec4(red, base.g, blue, base.a);
+}
@@ -0,0 +1,19 @@
+#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+uniform float brightness;
+uniform float contrast;
+uniform float saturation;
+uniform vec4 tint;
+
+void main() {
+    vec4 base = texture(scene, v_tex_coords);
+    vec3 graded = base.rgb * tint.rgb + brightness;
+    graded = (graded - 0.5) * contrast + 0.5;
+    float luminance = dot(graded, vec3(0.299, 0.587, 0.114));
+    graded = mix(vec3(luminance), graded, saturation);
+    color = vec4(clamp(graded, 0.0, 1.0), base.a);
+}
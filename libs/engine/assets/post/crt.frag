@@ -0,0 +1,22 @@
+#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+uniform vec2 resolution;
+uniform float scanline_strength;
+uniform float curvature;
+
+void main() {
+    vec2 centered = v_tex_coords * 2.0 - 1.0;
+    centered *= 1.0 + curvature * dot(centered.yx, centered.yx);
+    vec2 uv = centered * 0.5 + 0.5;
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec4 base = texture(scene, uv);
+    float scanline = sin(uv.y * resolution.y * 3.14159) * 0.5 + 0.5;
+    color = vec4(base.rgb * (1.0 - scanline_strength * scanline), base.a);
+}
@@ -0,0 +1,15 @@
+#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+uniform float strength;
+uniform float radius;
+
+void main() {
+    vec4 base = texture(scene, v_tex_coords);
+    float dist = length(v_tex_coords - vec2(0.5));
+    float darkening = smoothstep(radius, radius + 0.5, dist) * strength;
+    color = vec4(base.rgb * (1.0 - darkening), base.a);
+}
@@ -4,7 +4,11 @@ in vec2 v_tex_coords;
 out vec4 color;
 
 uniform sampler2D tex;
+uniform vec4 tint;
+uniform vec4 flash;
+uniform float opacity;
 
 void main() {
-    color = texture(tex, v_tex_coords);
-}
\ No newline at end of file
+    vec4 texel = texture(tex, v_tex_coords) * tint;
+    color = vec4(min(texel.rgb + flash.rgb * flash.a, 1.0), texel.a * opacity);
+}
@@ -0,0 +1,259 @@
+/// A rectangle on a sprite sheet, in pixels relative to the top-left corner of the image
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpriteRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SpriteRegion {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> SpriteRegion {
+        SpriteRegion {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// How an animation continues after it has shown its last frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Start over at the first frame
+    Loop,
+    /// Play the frames backwards, then forwards again
+    PingPong,
+    /// Stay on the last frame
+    Once,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationFrame {
+    pub region: SpriteRegion,
+    /// The time this frame is shown, in milliseconds
+    pub duration: f32,
+}
+
+/// A sequence of frames on a single sprite sheet.
+/// Load it with `EngineGraphics::load_animation`, and play it with an `AnimationCursor`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: AnimationMode,
+}
+
+impl Animation {
+    pub fn new(mode: AnimationMode) -> Animation {
+        Animation {
+            frames: Vec::new(),
+            mode,
+        }
+    }
+
+    pub fn with_frame(mut self, region: SpriteRegion, duration: f32) -> Animation {
+        self.frames.push(AnimationFrame { region, duration });
+        self
+    }
+}
+
+/// The playback position of an animation. Every entity that plays an animation owns one of these.
+pub struct AnimationCursor {
+    durations: Vec<f32>,
+    mode: AnimationMode,
+    frame: usize,
+    elapsed: f32,
+    reversed: bool,
+    finished: bool,
+    on_finish: Option<Box<dyn FnMut()>>,
+}
+
+impl AnimationCursor {
+    pub fn new(animation: &Animation) -> AnimationCursor {
+        AnimationCursor {
+            durations: animation.frames.iter().map(|f| f.duration).collect(),
+            mode: animation.mode,
+            frame: 0,
+            elapsed: 0f32,
+            reversed: false,
+            finished: false,
+            on_finish: None,
+        }
+    }
+
+    /// Set a callback that is called every time the animation finishes.
+    /// For `Once` this happens when the last frame has been shown, for `Loop` and `PingPong` at the end of every cycle.
+    pub fn on_finish<F: FnMut() + 'static>(mut self, callback: F) -> AnimationCursor {
+        self.on_finish = Some(Box::new(callback));
+        self
+    }
+
+    /// The index of the frame that should currently be drawn
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Whether a `Once` animation has shown its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0f32;
+        self.reversed = false;
+        self.finished = false;
+    }
+
+    /// Advance the animation by `delta_time` milliseconds.
+    /// Returns true if the animation finished during this update.
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        if self.finished || self.durations.is_empty() {
+            return false;
+        }
+        self.elapsed += delta_time;
+
+        let mut has_finished = false;
+        while self.elapsed >= self.durations[self.frame] {
+            // Frames without a duration would make this loop forever
+            if self.durations[self.frame] <= 0f32 {
+                self.elapsed = 0f32;
+            } else {
+                self.elapsed -= self.durations[self.frame];
+            }
+            if self.advance() {
+                has_finished = true;
+                if let Some(callback) = &mut self.on_finish {
+                    callback();
+                }
+                if self.finished {
+                    self.elapsed = 0f32;
+                    break;
+                }
+            }
+            if self.elapsed == 0f32 {
+                break;
+            }
+        }
+        has_finished
+    }
+
+    /// Go to the next frame, returns true if a cycle was completed
+    fn advance(&mut self) -> bool {
+        let last = self.durations.len() - 1;
+        match self.mode {
+            AnimationMode::Once => {
+                if self.frame == last {
+                    self.finished = true;
+                    true
+                } else {
+                    self.frame += 1;
+                    false
+                }
+            }
+            AnimationMode::Loop => {
+                if self.frame == last {
+                    self.frame = 0;
+                    true
+                } else {
+                    self.frame += 1;
+                    false
+                }
+            }
+            AnimationMode::PingPong => {
+                if last == 0 {
+                    return true;
+                }
+                if self.reversed {
+                    self.frame -= 1;
+                    if self.frame == 0 {
+                        self.reversed = false;
+                        return true;
+                    }
+                } else {
+                    self.frame += 1;
+                    if self.frame == last {
+                        self.reversed = true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Animation, AnimationCursor, AnimationMode, SpriteRegion};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn animation(mode: AnimationMode, frames: usize) -> Animation {
+        (0..frames).fold(Animation::new(mode), |animation, index| {
+            animation.with_frame(SpriteRegion::new(index as u32 * 16, 0, 16, 16), 100f32)
+        })
+    }
+
+    /// The frame after every step of `delta_time`
+    fn frames(cursor: &mut AnimationCursor, delta_time: f32, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                cursor.update(delta_time);
+                cursor.frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        let finished = Rc::new(Cell::new(0));
+        let counter = finished.clone();
+        let mut cursor = AnimationCursor::new(&animation(AnimationMode::Loop, 3))
+            .on_finish(move || counter.set(counter.get() + 1));
+        assert_eq!(cursor.frame(), 0);
+        assert_eq!(frames(&mut cursor, 100f32, 7), vec![1, 2, 0, 1, 2, 0, 1]);
+        assert_eq!(finished.get(), 2);
+        assert!(!cursor.is_finished());
+    }
+
+    #[test]
+    fn frames_wait_for_their_duration() {
+        let mut cursor = AnimationCursor::new(&animation(AnimationMode::Loop, 3));
+        assert_eq!(frames(&mut cursor, 40f32, 5), vec![0, 0, 1, 1, 2]);
+        // A long frame time skips frames
+        cursor.restart();
+        assert!(cursor.update(350f32));
+        assert_eq!(cursor.frame(), 0);
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_both_ends() {
+        let mut cursor = AnimationCursor::new(&animation(AnimationMode::PingPong, 3));
+        let mut cycles = 0;
+        let mut played = Vec::new();
+        for _ in 0..8 {
+            if cursor.update(100f32) {
+                cycles += 1;
+            }
+            played.push(cursor.frame());
+        }
+        assert_eq!(played, vec![1, 2, 1, 0, 1, 2, 1, 0]);
+        assert_eq!(cycles, 2);
+    }
+
+    #[test]
+    fn once_stays_on_the_last_frame() {
+        let mut cursor = AnimationCursor::new(&animation(AnimationMode::Once, 3));
+        assert_eq!(frames(&mut cursor, 100f32, 2), vec![1, 2]);
+        assert!(!cursor.is_finished());
+        assert!(cursor.update(100f32));
+        assert!(cursor.is_finished());
+        assert!(!cursor.update(100f32));
+        assert_eq!(cursor.frame(), 2);
+
+        cursor.restart();
+        assert_eq!(cursor.frame(), 0);
+        assert!(!cursor.is_finished());
+    }
+}
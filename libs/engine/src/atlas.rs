@@ -0,0 +1,340 @@
+use glium::texture::{RawImage2d, Texture2d};
+use glium::Display;
+use image::RgbaImage;
+use std::rc::Rc;
+
+use super::Result;
+
+/// The largest size of an atlas page. Images that are larger than this get a page of their own.
+pub const ATLAS_PAGE_SIZE: u32 = 2048;
+/// The amount of pixels between two images on a page. The edge pixels of every image are
+/// extruded into this area, so linear filtering (and the first few mipmap levels) never sample
+/// a neighbouring image.
+pub const ATLAS_PADDING: u32 = 8;
+
+/// A rectangle in texture coordinates, ranging from 0 to 1.
+/// `top` is the edge at the top of the image, which in OpenGL has the larger `v` coordinate.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextureRegion {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl TextureRegion {
+    /// The region covering the entire texture
+    pub fn full() -> TextureRegion {
+        TextureRegion {
+            left: 0f32,
+            top: 1f32,
+            right: 1f32,
+            bottom: 0f32,
+        }
+    }
+}
+
+/// The location of an image after it has been packed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasPlacement {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A packed image, referencing the page texture it ended up on
+#[derive(Clone)]
+pub struct AtlasEntry {
+    pub texture: Rc<Texture2d>,
+    /// The pixel rectangle of the image on the page, without padding
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasEntry {
+    /// Get the texture coordinates of a sub-rectangle of this image, in pixels relative to the
+    /// top-left corner of the original image.
+    pub fn sub_region(&self, x: u32, y: u32, width: u32, height: u32) -> TextureRegion {
+        let page_width = self.texture.get_width() as f32;
+        let page_height = self.texture.get_height().unwrap_or(1) as f32;
+        let x = (self.x + x) as f32;
+        let y = (self.y + y) as f32;
+        TextureRegion {
+            left: x / page_width,
+            right: (x + width as f32) / page_width,
+            // The page is uploaded upside down, see `TextureAtlas::build`
+            top: 1f32 - y / page_height,
+            bottom: 1f32 - (y + height as f32) / page_height,
+        }
+    }
+
+    /// Get the texture coordinates of the entire image
+    pub fn region(&self) -> TextureRegion {
+        self.sub_region(0, 0, self.width, self.height)
+    }
+}
+
+/// Collects images and packs them into as few textures as possible
+#[derive(Default)]
+pub struct TextureAtlas {
+    images: Vec<RgbaImage>,
+}
+
+impl TextureAtlas {
+    /// Add an image to the atlas, returning the index of the entry that `build` will return for it
+    pub fn add(&mut self, image: RgbaImage) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Pack all the added images and upload the resulting pages to the GPU.
+    /// The returned entries are in the same order as the images were added.
+    pub fn build(self, display: &Display) -> Result<Vec<AtlasEntry>> {
+        let sizes = self
+            .images
+            .iter()
+            .map(|i| i.dimensions())
+            .collect::<Vec<_>>();
+        let (placements, page_sizes) = pack(&sizes, ATLAS_PAGE_SIZE, ATLAS_PADDING);
+
+        let mut pages = page_sizes
+            .iter()
+            .map(|&(width, height)| RgbaImage::new(width, height))
+            .collect::<Vec<_>>();
+        for (image, placement) in self.images.iter().zip(placements.iter()) {
+            blit_extruded(
+                &mut pages[placement.page],
+                image,
+                placement.x,
+                placement.y,
+                ATLAS_PADDING,
+            );
+        }
+
+        let mut textures = Vec::with_capacity(pages.len());
+        for page in pages {
+            let dimensions = page.dimensions();
+            let raw = RawImage2d::from_raw_rgba_reversed(&page.into_raw(), dimensions);
+            textures.push(Rc::new(Texture2d::new(display, raw)?));
+        }
+
+        Ok(sizes
+            .into_iter()
+            .zip(placements.into_iter())
+            .map(|((width, height), placement)| AtlasEntry {
+                texture: textures[placement.page].clone(),
+                x: placement.x,
+                y: placement.y,
+                width,
+                height,
+            })
+            .collect())
+    }
+}
+
+/// Pack rectangles of the given sizes onto pages, using a shelf packer.
+/// Returns the placement of every rectangle (in the same order as `sizes`) and the size of every page,
+/// which is only as large as the rectangles on it.
+/// Every rectangle keeps `padding` pixels free on all of its sides.
+pub fn pack(
+    sizes: &[(u32, u32)],
+    page_size: u32,
+    padding: u32,
+) -> (Vec<AtlasPlacement>, Vec<(u32, u32)>) {
+    struct Shelf {
+        y: u32,
+        height: u32,
+        used_width: u32,
+    }
+    struct Page {
+        shelves: Vec<Shelf>,
+        used_height: u32,
+        width: u32,
+        height: u32,
+    }
+
+    // Placing the highest rectangles first keeps the shelves tight
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| sizes[*b].1.cmp(&sizes[*a].1).then(a.cmp(b)));
+
+    let mut pages: Vec<Page> = Vec::new();
+    let mut placements = vec![
+        AtlasPlacement {
+            page: 0,
+            x: 0,
+            y: 0
+        };
+        sizes.len()
+    ];
+
+    'images: for index in order {
+        let (width, height) = sizes[index];
+        let padded_width = width + padding * 2;
+        let padded_height = height + padding * 2;
+
+        for (page_index, page) in pages.iter_mut().enumerate() {
+            for shelf in &mut page.shelves {
+                if shelf.height >= padded_height && shelf.used_width + padded_width <= page.width {
+                    placements[index] = AtlasPlacement {
+                        page: page_index,
+                        x: shelf.used_width + padding,
+                        y: shelf.y + padding,
+                    };
+                    shelf.used_width += padded_width;
+                    continue 'images;
+                }
+            }
+            if page.used_height + padded_height <= page.height && padded_width <= page.width {
+                placements[index] = AtlasPlacement {
+                    page: page_index,
+                    x: padding,
+                    y: page.used_height + padding,
+                };
+                page.shelves.push(Shelf {
+                    y: page.used_height,
+                    height: padded_height,
+                    used_width: padded_width,
+                });
+                page.used_height += padded_height;
+                continue 'images;
+            }
+        }
+
+        // Nothing fits, start a new page. Oversized images get a page that fits them exactly.
+        pages.push(Page {
+            shelves: vec![Shelf {
+                y: 0,
+                height: padded_height,
+                used_width: padded_width,
+            }],
+            used_height: padded_height,
+            width: page_size.max(padded_width),
+            height: page_size.max(padded_height),
+        });
+        placements[index] = AtlasPlacement {
+            page: pages.len() - 1,
+            x: padding,
+            y: padding,
+        };
+    }
+
+    let page_sizes = pages
+        .iter()
+        .map(|page| {
+            let used_width = page.shelves.iter().map(|s| s.used_width).max();
+            (used_width.unwrap_or(0), page.used_height)
+        })
+        .collect();
+    (placements, page_sizes)
+}
+
+/// Copy `image` onto `page` at the given position, repeating the outer pixels of the image `padding` times on every side
+fn blit_extruded(page: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, padding: u32) {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    let padding = padding as i64;
+    for target_y in -padding..(height as i64 + padding) {
+        let source_y = target_y.max(0).min(height as i64 - 1) as u32;
+        for target_x in -padding..(width as i64 + padding) {
+            let source_x = target_x.max(0).min(width as i64 - 1) as u32;
+            let page_x = x as i64 + target_x;
+            let page_y = y as i64 + target_y;
+            if page_x < 0
+                || page_y < 0
+                || page_x >= page.width() as i64
+                || page_y >= page.height() as i64
+            {
+                continue;
+            }
+            let pixel = *image.get_pixel(source_x, source_y);
+            page.put_pixel(page_x as u32, page_y as u32, pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pack;
+
+    /// The padded rectangles of all placements, as (page, left, top, right, bottom)
+    fn padded_rects(
+        sizes: &[(u32, u32)],
+        page_size: u32,
+        padding: u32,
+    ) -> Vec<(usize, u32, u32, u32, u32)> {
+        let (placements, page_sizes) = pack(sizes, page_size, padding);
+        placements
+            .iter()
+            .zip(sizes)
+            .map(|(placement, &(width, height))| {
+                let rect = (
+                    placement.page,
+                    placement.x - padding,
+                    placement.y - padding,
+                    placement.x + width + padding,
+                    placement.y + height + padding,
+                );
+                let (page_width, page_height) = page_sizes[placement.page];
+                assert!(rect.3 <= page_width && rect.4 <= page_height);
+                rect
+            })
+            .collect()
+    }
+
+    #[test]
+    fn placements_do_not_overlap() {
+        let sizes = [
+            (30, 20),
+            (10, 40),
+            (50, 50),
+            (20, 20),
+            (64, 8),
+            (8, 64),
+            (33, 17),
+        ];
+        let rects = padded_rects(&sizes, 128, 2);
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[i + 1..] {
+                let overlap = a.0 == b.0 && a.1 < b.3 && b.1 < a.3 && a.2 < b.4 && b.2 < a.4;
+                assert!(!overlap, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn pages_are_sized_to_their_contents() {
+        let (placements, page_sizes) = pack(&[(16, 16)], 2048, 8);
+        assert_eq!(placements[0].x, 8);
+        assert_eq!(placements[0].y, 8);
+        assert_eq!(page_sizes, vec![(32, 32)]);
+
+        let (_, page_sizes) = pack(&[(16, 16), (16, 8)], 2048, 0);
+        assert_eq!(page_sizes, vec![(32, 16)]);
+    }
+
+    #[test]
+    fn full_pages_continue_on_a_new_page() {
+        let (placements, page_sizes) = pack(&[(60, 70), (60, 70), (60, 70)], 128, 0);
+        assert_eq!(page_sizes.len(), 2);
+        assert_eq!(placements[2].page, 1);
+        assert_eq!(page_sizes[0], (120, 70));
+        assert_eq!(page_sizes[1], (60, 70));
+    }
+
+    #[test]
+    fn oversized_images_get_a_page_of_their_own() {
+        let (placements, page_sizes) = pack(&[(10, 10), (300, 200)], 128, 1);
+        assert_eq!(placements[1].page, 0);
+        assert_eq!(page_sizes[0], (302, 202));
+        assert_eq!(placements[0].page, 1);
+        assert_eq!(page_sizes[1], (12, 12));
+    }
+}
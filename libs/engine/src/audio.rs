@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::error::Error;
+use super::music::OggStream;
+use super::{
+    AudioOutput, Camera, EntityWrapper, Mixer, MusicCommand, MusicDecoder, MusicSource, MusicTrack,
+    MusicVoice, NullOutput, PlayMusic, Playlist, Result, SoundData, TGraphicIndex, VoiceId,
+    WavFileOutput, DEFAULT_SAMPLE_RATE, SFX_BUS,
+};
+
+/// A request to play a loaded sound, e.g. from `EntityEvent::PlaySound`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaySound {
+    pub sound: String,
+    pub bus: String,
+    pub volume: f32,
+    /// 1 plays the sound at its own speed, 2 plays it twice as fast and an octave higher
+    pub pitch: f32,
+    pub looping: bool,
+    /// Turn the music down while the sound plays, for sounds that must not be missed
+    pub ducks_music: bool,
+    /// -1 plays the sound on the left only, 1 on the right only. Positional sounds are panned further.
+    pub pan: f32,
+    /// The world position of a positional sound. When following an entity, this is relative to the entity's position.
+    pub position: Option<(f32, f32)>,
+    /// The id of the entity a positional sound follows, see `EntityState::id`
+    pub follow: Option<u64>,
+}
+
+impl PlaySound {
+    /// Play a sound once on the sfx bus
+    pub fn new(sound: &str) -> PlaySound {
+        PlaySound {
+            sound: sound.to_owned(),
+            bus: SFX_BUS.to_owned(),
+            volume: 1f32,
+            pitch: 1f32,
+            looping: false,
+            ducks_music: false,
+            pan: 0f32,
+            position: None,
+            follow: None,
+        }
+    }
+
+    pub fn on_bus(mut self, bus: &str) -> Self {
+        self.bus = bus.to_owned();
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn ducking_music(mut self) -> Self {
+        self.ducks_music = true;
+        self
+    }
+
+    pub fn with_pan(mut self, pan: f32) -> Self {
+        self.pan = pan;
+        self
+    }
+
+    /// Pan and attenuate the sound by where `x` and `y` are on screen
+    pub fn at(mut self, x: f32, y: f32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    /// Follow the entity with the given id while the sound plays. `at` becomes an offset to the entity's position.
+    pub fn following(mut self, entity_id: u64) -> Self {
+        self.follow = Some(entity_id);
+        self
+    }
+}
+
+/// The loaded sounds and music and the mixer that plays them, with the output the mix goes to
+pub struct Audio {
+    sounds: HashMap<String, Arc<SoundData>>,
+    music: HashMap<String, MusicTrack>,
+    /// The entity positions of the last update, for sounds that follow an entity
+    entity_positions: HashMap<u64, (f32, f32)>,
+    mixer: Arc<Mutex<Mixer>>,
+    output: Box<dyn AudioOutput>,
+    /// Decode the music that plays, or is queued, ahead of the mixer
+    decoders: Vec<MusicDecoder>,
+}
+
+impl Audio {
+    pub fn new(mixer: Mixer, output: Box<dyn AudioOutput>) -> Audio {
+        Audio {
+            sounds: HashMap::new(),
+            music: HashMap::new(),
+            entity_positions: HashMap::new(),
+            mixer: Arc::new(Mutex::new(mixer)),
+            output,
+            decoders: Vec::new(),
+        }
+    }
+
+    /// Mix without playing anything, for machines without sound hardware
+    pub fn null() -> Audio {
+        Audio::new(
+            Mixer::new(DEFAULT_SAMPLE_RATE),
+            Box::new(NullOutput::default()),
+        )
+    }
+
+    /// Write everything that plays to a WAV file. The file is complete once the engine stops.
+    pub fn wav_file<P: AsRef<Path>>(file: P) -> Result<Audio> {
+        let output = WavFileOutput::create(file, DEFAULT_SAMPLE_RATE)?;
+        Ok(Audio::new(
+            Mixer::new(DEFAULT_SAMPLE_RATE),
+            Box::new(output),
+        ))
+    }
+
+    /// Play on the default sound device
+    #[cfg(feature = "device-audio")]
+    pub fn device() -> Result<Audio> {
+        let audio = Audio::new(
+            Mixer::new(::DeviceOutput::sample_rate()?),
+            Box::new(NullOutput::default()),
+        );
+        let output = ::DeviceOutput::open(&audio.mixer)?;
+        Ok(Audio {
+            output: Box::new(output),
+            ..audio
+        })
+    }
+
+    /// The sound device when the engine is built with the `device-audio` feature and a device is found,
+    /// the null output otherwise
+    pub fn default_output() -> Audio {
+        #[cfg(feature = "device-audio")]
+        {
+            match Audio::device() {
+                Ok(audio) => audio,
+                Err(error) => {
+                    // Keep why there is no sound for `take_errors`
+                    let audio = Audio::null();
+                    audio.mixer().report(error);
+                    audio
+                }
+            }
+        }
+        #[cfg(not(feature = "device-audio"))]
+        Audio::null()
+    }
+
+    /// Load a WAV or OGG file, to be played by name
+    pub fn load_sound<P: AsRef<Path>>(&mut self, name: &str, file: P) -> Result<()> {
+        let sound = SoundData::load(file)?;
+        self.add_sound(name, sound);
+        Ok(())
+    }
+
+    pub fn add_sound(&mut self, name: &str, sound: SoundData) {
+        self.sounds.insert(name.to_owned(), Arc::new(sound));
+    }
+
+    pub fn has_sound(&self, name: &str) -> bool {
+        self.sounds.contains_key(name)
+    }
+
+    pub fn play(&mut self, request: &PlaySound) -> Result<VoiceId> {
+        let sound = self
+            .sounds
+            .get(&request.sound)
+            .cloned()
+            .ok_or_else(|| Error::audio(&format!("unknown sound {}", request.sound)))?;
+        let positions = &self.entity_positions;
+        let mut mixer = self.mixer.lock().unwrap();
+        let id = mixer.play(sound, request)?;
+        mixer.move_emitters(|entity| positions.get(&entity).cloned());
+        Ok(id)
+    }
+
+    /// Move the sounds that follow entities, and hear positional sounds from what the camera shows
+    pub fn update_positions<T: TGraphicIndex>(
+        &mut self,
+        entities: &[EntityWrapper<T>],
+        camera: Camera,
+        width: f32,
+        height: f32,
+    ) {
+        self.entity_positions = entities
+            .iter()
+            .filter(|e| e.state.active)
+            .map(|e| (e.state.id, (e.state.x, e.state.y)))
+            .collect();
+        let positions = &self.entity_positions;
+        let mut mixer = self.mixer.lock().unwrap();
+        mixer.set_listener(camera, width, height);
+        mixer.move_emitters(|entity| positions.get(&entity).cloned());
+    }
+
+    /// Register a music track. OGG files are streamed while they play, WAV files are loaded into memory.
+    pub fn load_music<P: AsRef<Path>>(&mut self, name: &str, file: P) -> Result<()> {
+        let file = file.as_ref();
+        let is_ogg = file
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("ogg"))
+            .unwrap_or(false);
+        let source = if is_ogg {
+            // Read the headers now, so a broken file fails here instead of when it plays
+            OggStream::open(file)?;
+            MusicSource::Ogg(file.to_path_buf())
+        } else {
+            MusicSource::Sound(Arc::new(SoundData::load(file)?))
+        };
+        self.music
+            .insert(name.to_owned(), MusicTrack::new(name, source));
+        Ok(())
+    }
+
+    pub fn add_music(&mut self, name: &str, sound: SoundData) {
+        let source = MusicSource::Sound(Arc::new(sound));
+        self.music
+            .insert(name.to_owned(), MusicTrack::new(name, source));
+    }
+
+    /// Set the frames a looping track repeats between. Without an end, it repeats at the end of the track.
+    pub fn set_loop_points(&mut self, name: &str, start: u64, end: Option<u64>) -> Result<()> {
+        if end.map(|end| end <= start).unwrap_or(false) {
+            return Err(Error::audio(&format!(
+                "the loop of {} ends before it starts",
+                name
+            )));
+        }
+        let track = self.music_track_mut(name)?;
+        track.loop_start = start;
+        track.loop_end = end;
+        Ok(())
+    }
+
+    fn music_track_mut(&mut self, name: &str) -> Result<&mut MusicTrack> {
+        self.music
+            .get_mut(name)
+            .ok_or_else(|| Error::audio(&format!("unknown music {}", name)))
+    }
+
+    pub fn has_music(&self, name: &str) -> bool {
+        self.music.contains_key(name)
+    }
+
+    /// Crossfade to a track. When the track is already playing, it continues.
+    pub fn play_music(&mut self, request: &PlayMusic) -> Result<()> {
+        let track = self.music_track_mut(&request.track)?.clone();
+        if self.mixer().plays_music_track(&track.name) {
+            return Ok(());
+        }
+        let voice = self.open_music(&track, request.looping)?;
+        self.mixer().play_music(voice, request.crossfade);
+        Ok(())
+    }
+
+    pub fn play_playlist(&mut self, playlist: &Playlist) -> Result<()> {
+        let tracks = playlist
+            .tracks
+            .iter()
+            .map(|name| self.music_track_mut(name).map(|track| track.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        if tracks.is_empty() {
+            self.stop_music(playlist.crossfade);
+            return Ok(());
+        }
+        let first = self.open_music(&tracks[0], false)?;
+        self.mixer()
+            .play_playlist(first, tracks, playlist.crossfade, playlist.repeat);
+        Ok(())
+    }
+
+    /// Open a track and decode its start before the mixer is locked, so the sound device does not wait for it
+    fn open_music(&mut self, track: &MusicTrack, looping: bool) -> Result<MusicVoice> {
+        let sample_rate = self.mixer().sample_rate();
+        let (voice, decoder) = MusicVoice::open(track, looping, sample_rate)?;
+        self.decoders.push(decoder);
+        Ok(voice)
+    }
+
+    /// Open the next track of the playlist, and decode the music ahead of the mixer
+    fn decode_music(&mut self) {
+        let next = self.mixer().next_music_to_queue();
+        if let Some((track, generation)) = next {
+            match self.open_music(&track, false) {
+                Ok(voice) => self.mixer().queue_music(voice, generation),
+                Err(error) => self.mixer().report(error),
+            }
+        }
+        let mut errors = Vec::new();
+        self.decoders.retain_mut(|decoder| {
+            decoder.fill().unwrap_or_else(|error| {
+                errors.push(error);
+                false
+            })
+        });
+        let mut mixer = self.mixer();
+        for error in errors {
+            mixer.report(error);
+        }
+    }
+
+    pub fn stop_music(&mut self, fade_out: f32) {
+        self.mixer().stop_music(fade_out);
+    }
+
+    /// Carry out a command from `EntityEvent::Music`
+    pub fn music_command(&mut self, command: &MusicCommand) -> Result<()> {
+        match command {
+            MusicCommand::Play(request) => self.play_music(request),
+            MusicCommand::Playlist(playlist) => self.play_playlist(playlist),
+            MusicCommand::Stop(fade_out) => {
+                self.stop_music(*fade_out);
+                Ok(())
+            }
+        }
+    }
+
+    /// The mixer, to change volumes and stop voices. The sound device waits while it is locked.
+    pub fn mixer(&self) -> MutexGuard<'_, Mixer> {
+        self.mixer.lock().unwrap()
+    }
+
+    /// The errors that happened while playing since the last call, see `Mixer::report`
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        self.mixer().take_errors()
+    }
+
+    /// Decode the music ahead, and advance offline outputs by the time that passed in milliseconds
+    pub fn update(&mut self, elapsed: f32) -> Result<()> {
+        self.decode_music();
+        self.output.update(&self.mixer, elapsed)
+    }
+
+    /// Mix the next frames into a buffer of interleaved stereo samples, instead of sending them to the output
+    pub fn render(&mut self, frames: usize) -> Vec<f32> {
+        self.decode_music();
+        let mut samples = vec![0f32; frames * 2];
+        self.mixer().render(&mut samples);
+        samples
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.output.finish()
+    }
+}
@@ -0,0 +1,271 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::error::Error;
+use super::{Mixer, Result};
+
+/// Where the mixed audio goes
+pub trait AudioOutput {
+    /// Called once per frame with the time that passed in milliseconds.
+    /// Offline outputs render this much audio, device outputs are driven by the device and ignore it.
+    fn update(&mut self, mixer: &Arc<Mutex<Mixer>>, elapsed: f32) -> Result<()>;
+
+    /// Called when the engine stops
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Counts the frames that are due for an amount of time, keeping the fraction of a frame for the next update
+#[derive(Default)]
+struct FrameClock {
+    pending: f64,
+}
+
+impl FrameClock {
+    fn render(&mut self, mixer: &Arc<Mutex<Mixer>>, elapsed: f32) -> Vec<f32> {
+        let mut mixer = mixer.lock().unwrap();
+        self.pending += f64::from(elapsed.max(0f32)) * f64::from(mixer.sample_rate()) / 1000f64;
+        let frames = self.pending as usize;
+        self.pending -= frames as f64;
+        let mut samples = vec![0f32; frames * 2];
+        mixer.render(&mut samples);
+        samples
+    }
+}
+
+/// Mixes the audio and throws it away, for machines without sound hardware.
+/// Voices still play and end at the right time.
+#[derive(Default)]
+pub struct NullOutput {
+    clock: FrameClock,
+}
+
+impl AudioOutput for NullOutput {
+    fn update(&mut self, mixer: &Arc<Mutex<Mixer>>, elapsed: f32) -> Result<()> {
+        self.clock.render(mixer, elapsed);
+        Ok(())
+    }
+}
+
+/// Writes the mixed audio to a 16 bit stereo WAV file, to verify the mixing offline
+pub struct WavFileOutput {
+    writer: Option<WavWriter<BufWriter<File>>>,
+    clock: FrameClock,
+}
+
+impl WavFileOutput {
+    pub fn create<P: AsRef<Path>>(file: P, sample_rate: u32) -> Result<WavFileOutput> {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let writer = WavWriter::create(file, spec).map_err(|e| Error::audio(&e.to_string()))?;
+        Ok(WavFileOutput {
+            writer: Some(writer),
+            clock: FrameClock::default(),
+        })
+    }
+}
+
+impl AudioOutput for WavFileOutput {
+    fn update(&mut self, mixer: &Arc<Mutex<Mixer>>, elapsed: f32) -> Result<()> {
+        let samples = self.clock.render(mixer, elapsed);
+        if let Some(writer) = self.writer.as_mut() {
+            for sample in samples {
+                let sample = (sample.max(-1f32).min(1f32) * f32::from(i16::max_value())) as i16;
+                writer
+                    .write_sample(sample)
+                    .map_err(|e| Error::audio(&e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the WAV header, without it the file can not be played
+    fn finish(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer
+                .finalize()
+                .map_err(|e| Error::audio(&e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WavFileOutput {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// Plays the mixed audio on the default sound device
+#[cfg(feature = "device-audio")]
+pub struct DeviceOutput {
+    _stream: ::cpal::Stream,
+}
+
+#[cfg(feature = "device-audio")]
+impl DeviceOutput {
+    fn device() -> Result<::cpal::Device> {
+        use cpal::traits::HostTrait;
+        ::cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| Error::audio("no sound device found"))
+    }
+
+    fn config() -> Result<::cpal::SupportedStreamConfig> {
+        use cpal::traits::DeviceTrait;
+        DeviceOutput::device()?
+            .default_output_config()
+            .map_err(|e| Error::audio(&e.to_string()))
+    }
+
+    /// The sample rate the mixer has to use for `open`
+    pub fn sample_rate() -> Result<u32> {
+        Ok(DeviceOutput::config()?.sample_rate().0)
+    }
+
+    pub fn open(mixer: &Arc<Mutex<Mixer>>) -> Result<DeviceOutput> {
+        use cpal::traits::{DeviceTrait, StreamTrait};
+        use cpal::SampleFormat;
+
+        let device = DeviceOutput::device()?;
+        let supported = DeviceOutput::config()?;
+        let config = supported.config();
+        let channels = config.channels as usize;
+        let errors = mixer.clone();
+        let mixer = mixer.clone();
+        let mut buffer = Vec::new();
+        let on_error = move |error: ::cpal::StreamError| {
+            if let Ok(mut mixer) = errors.lock() {
+                mixer.report(Error::audio(&error.to_string()));
+            }
+        };
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => device.build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &::cpal::OutputCallbackInfo| {
+                    mix_into(&mixer, &mut buffer, data.len() / channels);
+                    write_frames(&buffer, data, channels, |sample| sample);
+                },
+                on_error,
+                None,
+            ),
+            SampleFormat::I16 => device.build_output_stream(
+                &config,
+                move |data: &mut [i16], _: &::cpal::OutputCallbackInfo| {
+                    mix_into(&mixer, &mut buffer, data.len() / channels);
+                    write_frames(&buffer, data, channels, |sample| {
+                        (sample.max(-1f32).min(1f32) * f32::from(i16::max_value())) as i16
+                    });
+                },
+                on_error,
+                None,
+            ),
+            format => {
+                return Err(Error::audio(&format!(
+                    "unsupported sample format {:?}",
+                    format
+                )))
+            }
+        }
+        .map_err(|e| Error::audio(&e.to_string()))?;
+        stream.play().map_err(|e| Error::audio(&e.to_string()))?;
+        Ok(DeviceOutput { _stream: stream })
+    }
+}
+
+#[cfg(feature = "device-audio")]
+fn mix_into(mixer: &Arc<Mutex<Mixer>>, buffer: &mut Vec<f32>, frames: usize) {
+    buffer.resize(frames * 2, 0f32);
+    match mixer.lock() {
+        Ok(mut mixer) => mixer.render(buffer),
+        Err(_) => buffer.iter_mut().for_each(|sample| *sample = 0f32),
+    }
+}
+
+/// Copy stereo frames to a device buffer. Mono devices get the left channel, extra channels stay silent.
+#[cfg(feature = "device-audio")]
+fn write_frames<S: Copy, F: Fn(f32) -> S>(
+    stereo: &[f32],
+    data: &mut [S],
+    channels: usize,
+    convert: F,
+) {
+    let silence = convert(0f32);
+    for (frame, source) in data.chunks_mut(channels).zip(stereo.chunks(2)) {
+        for (channel, sample) in frame.iter_mut().enumerate() {
+            *sample = match channel {
+                0 | 1 => convert(source[channel]),
+                _ => silence,
+            };
+        }
+    }
+}
+
+#[cfg(feature = "device-audio")]
+impl AudioOutput for DeviceOutput {
+    fn update(&mut self, _: &Arc<Mutex<Mixer>>, _: f32) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AudioOutput, FrameClock, WavFileOutput};
+    use hound::WavReader;
+    use std::env;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use {Mixer, PlaySound, SoundData};
+
+    #[test]
+    fn frame_clock_keeps_the_fraction_of_a_frame() {
+        // One frame per millisecond
+        let mixer = Arc::new(Mutex::new(Mixer::new(1000)));
+        let mut clock = FrameClock::default();
+
+        assert_eq!(clock.render(&mixer, 0.25).len(), 0);
+        assert_eq!(clock.render(&mixer, 0.5).len(), 0);
+        assert_eq!(clock.render(&mixer, 0.5).len(), 2);
+        assert_eq!(clock.render(&mixer, 2.5).len(), 4);
+        assert_eq!(clock.render(&mixer, 0.25).len(), 2);
+        assert_eq!(clock.render(&mixer, -5f32).len(), 0);
+    }
+
+    #[test]
+    fn wav_file_has_the_rendered_frames_after_finish() {
+        let file = env::temp_dir().join(format!("engine_wav_output_{}.wav", ::std::process::id()));
+        let mixer = Arc::new(Mutex::new(Mixer::new(1000)));
+        let sound = SoundData::new(vec![0.5; 100], 1, 1000).unwrap();
+        mixer
+            .lock()
+            .unwrap()
+            .play(Arc::new(sound), &PlaySound::new("tone"))
+            .unwrap();
+        let mut output = WavFileOutput::create(&file, 1000).unwrap();
+        output.update(&mixer, 10f32).unwrap();
+        output.update(&mixer, 2.5).unwrap();
+        output.finish().unwrap();
+
+        let mut reader = WavReader::open(&file).unwrap();
+        let spec = reader.spec();
+        assert_eq!(spec.channels, 2);
+        assert_eq!(spec.sample_rate, 1000);
+        assert_eq!(spec.bits_per_sample, 16);
+        assert_eq!(reader.len(), 12 * 2);
+        let samples = reader
+            .samples::<i16>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(samples.iter().all(|sample| *sample == i16::max_value() / 2));
+        drop(reader);
+        fs::remove_file(&file).unwrap();
+    }
+}
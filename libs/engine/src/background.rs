@@ -0,0 +1,250 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{Camera, Color, EngineGraphics, Result, SpriteEffects, TGraphicIndex};
+
+/// A graphic from the graphics registry, repeated over the whole screen
+pub struct BackgroundLayer<T: TGraphicIndex> {
+    pub graphic: T,
+    /// The scrolling speed, in pixels per millisecond
+    pub velocity: (f32, f32),
+    /// How much the layer moves along with the camera.
+    /// 0 keeps the layer fixed to the screen, 1 moves it like the world itself.
+    pub parallax: f32,
+    pub effects: SpriteEffects,
+
+    scroll: (f32, f32),
+}
+
+impl<T: TGraphicIndex> BackgroundLayer<T> {
+    pub fn new(graphic: T) -> BackgroundLayer<T> {
+        BackgroundLayer {
+            graphic,
+            velocity: (0f32, 0f32),
+            parallax: 0f32,
+            effects: SpriteEffects::default(),
+            scroll: (0f32, 0f32),
+        }
+    }
+
+    pub fn with_velocity(mut self, x: f32, y: f32) -> BackgroundLayer<T> {
+        self.velocity = (x, y);
+        self
+    }
+
+    pub fn with_parallax(mut self, parallax: f32) -> BackgroundLayer<T> {
+        self.parallax = parallax;
+        self
+    }
+
+    /// Tint or fade the layer, e.g. to make distant layers darker
+    pub fn with_effects(mut self, effects: SpriteEffects) -> BackgroundLayer<T> {
+        self.effects = effects;
+        self
+    }
+
+    /// Scroll by the velocity, wrapped to the size of the graphic so the offset stays precise
+    fn scroll(&mut self, delta_time: f32, size: (f32, f32)) {
+        self.scroll = (
+            wrap(self.scroll.0 + self.velocity.0 * delta_time, size.0),
+            wrap(self.scroll.1 + self.velocity.1 * delta_time, size.1),
+        );
+    }
+
+    fn offset(&self, camera: &Camera) -> (f32, f32) {
+        (
+            self.scroll.0 - camera.x * self.parallax,
+            self.scroll.1 - camera.y * self.parallax,
+        )
+    }
+}
+
+struct Star {
+    /// Position relative to the screen size, from 0 to 1
+    x: f32,
+    y: f32,
+    /// From 0 (infinitely far away) to 1 (as close as the nearest stars)
+    depth: f32,
+}
+
+/// Randomly placed stars that wrap around the screen. Closer stars are larger, brighter and faster.
+pub struct Starfield {
+    /// The scrolling speed of the closest stars, in pixels per millisecond
+    pub velocity: (f32, f32),
+    /// How much the closest stars move along with the camera, see `BackgroundLayer::parallax`
+    pub parallax: f32,
+    pub color: Color,
+    /// The size of the closest stars, in pixels
+    pub star_size: f32,
+
+    stars: Vec<Star>,
+}
+
+impl Starfield {
+    /// Generate `count` stars. The same seed always generates the same stars.
+    pub fn new(count: usize, seed: u64) -> Starfield {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let stars = (0..count)
+            .map(|_| Star {
+                x: rng.gen(),
+                y: rng.gen(),
+                depth: rng.gen_range(0.1f32, 1f32),
+            })
+            .collect();
+        Starfield {
+            velocity: (0f32, 0f32),
+            parallax: 0.5f32,
+            color: (1.0, 1.0, 1.0, 1.0),
+            star_size: 3f32,
+            stars,
+        }
+    }
+
+    pub fn with_velocity(mut self, x: f32, y: f32) -> Starfield {
+        self.velocity = (x, y);
+        self
+    }
+
+    pub fn with_parallax(mut self, parallax: f32) -> Starfield {
+        self.parallax = parallax;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Starfield {
+        self.color = color;
+        self
+    }
+
+    pub fn with_star_size(mut self, star_size: f32) -> Starfield {
+        self.star_size = star_size;
+        self
+    }
+
+    /// Move the stars by the velocity, wrapping them around the screen
+    fn scroll(&mut self, delta_time: f32, screen: (f32, f32)) {
+        for star in &mut self.stars {
+            star.x = wrap(
+                star.x + self.velocity.0 * star.depth * delta_time / screen.0,
+                1f32,
+            );
+            star.y = wrap(
+                star.y + self.velocity.1 * star.depth * delta_time / screen.1,
+                1f32,
+            );
+        }
+    }
+
+    fn draw<T: TGraphicIndex>(&self, graphics: &mut EngineGraphics<T>) -> Result<()> {
+        let (width, height) = (graphics.width, graphics.height);
+        let camera = graphics.camera;
+        let offset = (-camera.x * self.parallax, -camera.y * self.parallax);
+        for star in &self.stars {
+            let size = (self.star_size * star.depth).max(1f32);
+            let x = (star.x * width + offset.0 * star.depth).rem_euclid(width);
+            let y = (star.y * height + offset.1 * star.depth).rem_euclid(height);
+            let color = (
+                self.color.0 * star.depth,
+                self.color.1 * star.depth,
+                self.color.2 * star.depth,
+                self.color.3,
+            );
+            graphics.draw_screen_rectangle(x, y, size, size, color)?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything drawn behind the entities: a clear colour, an optional starfield, and any number of
+/// layers, drawn back to front. Owned by the `Engine`, which scrolls it with the scaled frame time.
+pub struct Background<T: TGraphicIndex> {
+    pub clear_color: Color,
+    pub starfield: Option<Starfield>,
+    pub layers: Vec<BackgroundLayer<T>>,
+}
+
+impl<T: TGraphicIndex> Default for Background<T> {
+    fn default() -> Background<T> {
+        Background {
+            clear_color: (0.0, 0.0, 0.0, 1.0),
+            starfield: None,
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl<T: TGraphicIndex> Background<T> {
+    pub fn push_layer(&mut self, layer: BackgroundLayer<T>) {
+        self.layers.push(layer);
+    }
+
+    pub fn update(&mut self, delta_time: f32, graphics: &EngineGraphics<T>) {
+        if let Some(starfield) = &mut self.starfield {
+            starfield.scroll(delta_time, (graphics.width, graphics.height));
+        }
+        for layer in &mut self.layers {
+            if let Some(helper) = graphics.get_graphic(&layer.graphic) {
+                layer.scroll(delta_time, (helper.width, helper.height));
+            }
+        }
+    }
+
+    pub fn draw(&self, graphics: &mut EngineGraphics<T>) -> Result<()> {
+        if let Some(starfield) = &self.starfield {
+            starfield.draw(graphics)?;
+        }
+        for layer in &self.layers {
+            let offset = layer.offset(&graphics.camera);
+            graphics.draw_tiled(&layer.graphic, offset, &layer.effects)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wrap a scroll offset into `0..size`, leaving it as is for graphics without a size
+fn wrap(offset: f32, size: f32) -> f32 {
+    if size > 0f32 {
+        offset.rem_euclid(size)
+    } else {
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackgroundLayer, Starfield};
+    use TGraphicIndex;
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct Clouds;
+
+    impl TGraphicIndex for Clouds {}
+
+    #[test]
+    fn layers_wrap_their_scroll_to_the_graphic_size() {
+        let mut layer = BackgroundLayer::new(Clouds).with_velocity(0.3, -0.7);
+        for _ in 0..100_000 {
+            layer.scroll(16f32, (256f32, 128f32));
+        }
+        assert!(layer.scroll.0 >= 0f32 && layer.scroll.0 < 256f32);
+        assert!(layer.scroll.1 >= 0f32 && layer.scroll.1 < 128f32);
+    }
+
+    #[test]
+    fn layers_scroll_by_their_velocity() {
+        let mut layer = BackgroundLayer::new(Clouds).with_velocity(0.5, -0.25);
+        layer.scroll(100f32, (256f32, 128f32));
+        assert_eq!(layer.scroll, (50f32, 103f32));
+    }
+
+    #[test]
+    fn stars_stay_on_the_screen() {
+        let mut starfield = Starfield::new(100, 7).with_velocity(-0.4, 0.9);
+        for _ in 0..100_000 {
+            starfield.scroll(16f32, (800f32, 600f32));
+        }
+        for star in &starfield.stars {
+            assert!(star.x >= 0f32 && star.x < 1f32);
+            assert!(star.y >= 0f32 && star.y < 1f32);
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use super::error::Error;
+use super::{Color, Result, SpriteRegion, TextAlign};
+
+/// A character on the glyph sheet of a bitmap font. All values are in pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BitmapGlyph {
+    pub region: SpriteRegion,
+    /// The index of the sheet the glyph is on, see `BitmapFontDescriptor::pages`
+    pub page: usize,
+    /// The distance from the pen position to the top-left corner of the glyph
+    pub x_offset: f32,
+    pub y_offset: f32,
+    /// How far the pen moves after drawing the glyph
+    pub x_advance: f32,
+}
+
+/// A bitmap font as described by a BMFont descriptor in the text format
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitmapFontDescriptor {
+    /// The distance between two lines, in pixels
+    pub line_height: f32,
+    /// The distance from the top of a line to the baseline, in pixels
+    pub base: f32,
+    /// The file names of the glyph sheets, relative to the descriptor
+    pub pages: Vec<String>,
+    pub glyphs: HashMap<char, BitmapGlyph>,
+    /// Adjustments of the advance between two characters
+    pub kernings: HashMap<(char, char), f32>,
+}
+
+impl BitmapFontDescriptor {
+    /// Parse a descriptor in the BMFont text format.
+    /// Only the `common`, `page`, `char` and `kerning` lines are used, everything else is ignored.
+    pub fn parse(source: &str) -> Result<BitmapFontDescriptor> {
+        let mut descriptor = BitmapFontDescriptor {
+            line_height: 0f32,
+            base: 0f32,
+            pages: Vec::new(),
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+        };
+        for (index, line) in source.lines().enumerate() {
+            let (tag, attributes) = parse_line(line);
+            let attribute = |name: &str| -> Result<i64> {
+                attributes
+                    .get(name)
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| {
+                        Error::bitmap_font(&format!(
+                            "line {}: missing or invalid attribute '{}'",
+                            index + 1,
+                            name
+                        ))
+                    })
+            };
+            // Positions, sizes and indices wrap around when a negative value is cast
+            let unsigned = |name: &str| -> Result<u32> {
+                let value = attribute(name)?;
+                if value < 0 || value > i64::from(u32::max_value()) {
+                    return Err(Error::bitmap_font(&format!(
+                        "line {}: attribute '{}' is out of range: {}",
+                        index + 1,
+                        name,
+                        value
+                    )));
+                }
+                Ok(value as u32)
+            };
+            let character = |name: &str| -> Result<char> {
+                ::std::char::from_u32(unsigned(name)?).ok_or_else(|| {
+                    Error::bitmap_font(&format!("line {}: invalid character", index + 1))
+                })
+            };
+            match tag {
+                "common" => {
+                    descriptor.line_height = attribute("lineHeight")? as f32;
+                    descriptor.base = attribute("base")? as f32;
+                }
+                "page" => {
+                    let id = unsigned("id")? as usize;
+                    let file = attributes.get("file").cloned().ok_or_else(|| {
+                        Error::bitmap_font(&format!("line {}: page without a file", index + 1))
+                    })?;
+                    if descriptor.pages.len() <= id {
+                        descriptor.pages.resize(id + 1, String::new());
+                    }
+                    descriptor.pages[id] = file;
+                }
+                "char" => {
+                    let glyph = BitmapGlyph {
+                        region: SpriteRegion::new(
+                            unsigned("x")?,
+                            unsigned("y")?,
+                            unsigned("width")?,
+                            unsigned("height")?,
+                        ),
+                        page: match attributes.get("page") {
+                            Some(_) => unsigned("page")? as usize,
+                            None => 0,
+                        },
+                        x_offset: attribute("xoffset")? as f32,
+                        y_offset: attribute("yoffset")? as f32,
+                        x_advance: attribute("xadvance")? as f32,
+                    };
+                    descriptor.glyphs.insert(character("id")?, glyph);
+                }
+                "kerning" => {
+                    descriptor.kernings.insert(
+                        (character("first")?, character("second")?),
+                        attribute("amount")? as f32,
+                    );
+                }
+                _ => (),
+            }
+        }
+        if descriptor.pages.is_empty() {
+            return Err(Error::bitmap_font("the descriptor has no pages"));
+        }
+        if let Some(glyph) = descriptor
+            .glyphs
+            .values()
+            .find(|g| g.page >= descriptor.pages.len())
+        {
+            return Err(Error::bitmap_font(&format!(
+                "a glyph uses page {}, which does not exist",
+                glyph.page
+            )));
+        }
+        Ok(descriptor)
+    }
+
+    /// The advance every digit gets when digits are monospaced: the widest digit's advance
+    fn digit_advance(&self) -> f32 {
+        "0123456789"
+            .chars()
+            .filter_map(|c| self.glyphs.get(&c))
+            .map(|g| g.x_advance)
+            .fold(0f32, f32::max)
+    }
+
+    /// Place the glyphs of every line, left-aligned, and measure how far the pen moved on each line
+    fn layout_lines(
+        &self,
+        text: &str,
+        style: &BitmapTextStyle,
+    ) -> Vec<(Vec<(char, f32, f32)>, f32)> {
+        let digit_advance = self.digit_advance();
+        let mut lines = Vec::new();
+        for (line_index, line) in text.split('\n').enumerate() {
+            let top = line_index as f32 * self.line_height * style.scale;
+            let mut placed = Vec::new();
+            let mut pen = 0f32;
+            let mut previous: Option<char> = None;
+            for character in line.chars() {
+                let glyph = match self.glyphs.get(&character) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                let monospaced = style.monospace_digits && character.is_ascii_digit();
+                if let Some(previous) = previous {
+                    // Kerning would make monospaced digits uneven again
+                    if !(style.monospace_digits && (monospaced || previous.is_ascii_digit())) {
+                        pen += self.kernings.get(&(previous, character)).unwrap_or(&0f32)
+                            * style.scale;
+                    }
+                }
+                let (x_offset, advance) = if monospaced {
+                    (
+                        (digit_advance - glyph.region.width as f32) / 2f32,
+                        digit_advance,
+                    )
+                } else {
+                    (glyph.x_offset, glyph.x_advance)
+                };
+                placed.push((
+                    character,
+                    pen + x_offset * style.scale,
+                    top + glyph.y_offset * style.scale,
+                ));
+                pen += advance * style.scale;
+                previous = Some(character);
+            }
+            lines.push((placed, pen));
+        }
+        lines
+    }
+
+    /// Place every glyph of the text, relative to the alignment point of the first line.
+    /// Returns the characters with the top-left corner of their glyph.
+    pub fn layout(&self, text: &str, style: &BitmapTextStyle) -> Vec<(char, f32, f32)> {
+        let mut placed = Vec::new();
+        for (glyphs, width) in self.layout_lines(text, style) {
+            let shift = match style.align {
+                TextAlign::Left => 0f32,
+                TextAlign::Center => -width / 2f32,
+                TextAlign::Right => -width,
+            };
+            placed.extend(glyphs.into_iter().map(|(c, x, y)| (c, x + shift, y)));
+        }
+        placed
+    }
+
+    /// The width and height of the text in pixels, if it were drawn with the given style
+    pub fn measure(&self, text: &str, style: &BitmapTextStyle) -> (f32, f32) {
+        let lines = self.layout_lines(text, style);
+        let width = lines.iter().map(|(_, width)| *width).fold(0f32, f32::max);
+        (width, lines.len() as f32 * self.line_height * style.scale)
+    }
+}
+
+/// Split a descriptor line into its tag and its `key=value` attributes. Values may be quoted.
+fn parse_line(line: &str) -> (&str, HashMap<&str, String>) {
+    let line = line.trim();
+    let (tag, rest) = match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], &line[index..]),
+        None => (line, ""),
+    };
+    let mut attributes = HashMap::new();
+    let mut rest = rest.trim_start();
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].trim();
+        let after = &rest[equals + 1..];
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match after.find(char::is_whitespace) {
+                Some(end) => (&after[..end], &after[end..]),
+                None => (after, ""),
+            }
+        };
+        attributes.insert(key, value.to_owned());
+        rest = remaining.trim_start();
+    }
+    (tag, attributes)
+}
+
+/// How bitmap text is drawn. The default is unscaled, untinted and left-aligned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitmapTextStyle {
+    pub scale: f32,
+    /// Multiplied with the glyph sheet, so white glyphs take on this colour
+    pub color: Color,
+    pub align: TextAlign,
+    /// Give every digit the same width, so changing numbers do not move around
+    pub monospace_digits: bool,
+}
+
+impl Default for BitmapTextStyle {
+    fn default() -> BitmapTextStyle {
+        BitmapTextStyle {
+            scale: 1f32,
+            color: (1.0, 1.0, 1.0, 1.0),
+            align: TextAlign::Left,
+            monospace_digits: false,
+        }
+    }
+}
+
+impl BitmapTextStyle {
+    pub fn with_scale(mut self, scale: f32) -> BitmapTextStyle {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> BitmapTextStyle {
+        self.color = color;
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> BitmapTextStyle {
+        self.align = align;
+        self
+    }
+
+    pub fn monospaced_digits(mut self) -> BitmapTextStyle {
+        self.monospace_digits = true;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitmapFontDescriptor, BitmapTextStyle, SpriteRegion};
+
+    const FONT: &str = r#"info face="test" size=14 bold=0 italic=0 charset="" unicode=1
+common lineHeight=18 base=14 scaleW=64 scaleH=32 pages=2 packed=0
+page id=0 file="test_0.png"
+page id=1 file="test 1.png"
+chars count=3
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=6 page=0 chnl=15
+char id=65 x=1 y=2 width=10 height=14 xoffset=-1 yoffset=2 xadvance=11 page=0 chnl=15
+char id=86 x=3 y=4 width=9 height=14 xoffset=0 yoffset=2 xadvance=10 page=1 chnl=15
+kernings count=1
+kerning first=65 second=86 amount=-2
+"#;
+
+    fn error(source: &str) -> String {
+        format!("{:?}", BitmapFontDescriptor::parse(source).unwrap_err())
+    }
+
+    #[test]
+    fn parses_a_descriptor() {
+        let font = BitmapFontDescriptor::parse(FONT).unwrap();
+        assert_eq!(font.line_height, 18f32);
+        assert_eq!(font.base, 14f32);
+        assert_eq!(font.pages, vec!["test_0.png", "test 1.png"]);
+        assert_eq!(font.glyphs.len(), 3);
+
+        let glyph = font.glyphs[&'A'];
+        assert_eq!(glyph.region, SpriteRegion::new(1, 2, 10, 14));
+        assert_eq!(glyph.page, 0);
+        assert_eq!(glyph.x_offset, -1f32);
+        assert_eq!(glyph.y_offset, 2f32);
+        assert_eq!(glyph.x_advance, 11f32);
+        assert_eq!(font.glyphs[&'V'].page, 1);
+        assert_eq!(font.kernings[&('A', 'V')], -2f32);
+
+        // 11 for A, 10 for V, minus the kerning between them
+        let (width, height) = font.measure("AV", &BitmapTextStyle::default());
+        assert_eq!((width, height), (19f32, 18f32));
+    }
+
+    #[test]
+    fn missing_attributes_are_errors() {
+        let without_width = FONT.replace("width=10 ", "");
+        assert!(error(&without_width).contains("'width'"));
+
+        let without_file = FONT.replace(r#" file="test_0.png""#, "");
+        assert!(error(&without_file).contains("page without a file"));
+
+        let invalid_number = FONT.replace("xadvance=11", "xadvance=eleven");
+        assert!(error(&invalid_number).contains("'xadvance'"));
+    }
+
+    #[test]
+    fn negative_values_are_errors() {
+        for (from, to) in &[
+            ("x=1 y=2", "x=-1 y=2"),
+            ("width=10", "width=-10"),
+            ("page id=1", "page id=-1"),
+            ("xadvance=10 page=1", "xadvance=10 page=-1"),
+            ("first=65", "first=-65"),
+        ] {
+            assert!(
+                error(&FONT.replace(from, to)).contains("out of range"),
+                "{} should be rejected",
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn glyphs_need_an_existing_page() {
+        let font = FONT.replace("page id=1 file=\"test 1.png\"\n", "");
+        assert!(error(&font).contains("page 1"));
+        assert!(error("common lineHeight=18 base=14").contains("no pages"));
+    }
+}
@@ -0,0 +1,22 @@
+/// The position of the view into the world. Everything the entities draw is in world coordinates,
+/// and is shifted by the camera before it ends up on the screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Camera {
+    /// The world position shown at the top-left corner of the screen
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Camera {
+    pub fn new(x: f32, y: f32) -> Camera {
+        Camera { x, y }
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (x - self.x, y - self.y)
+    }
+
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        (x + self.x, y + self.y)
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use super::{
+    CollisionLayer, Color, EngineGraphics, EntityWrapper, Result, TGraphicIndex, TextStyle,
+    VirtualKeyCode,
+};
+
+/// The amount of frames shown in the frame time graph
+const GRAPH_SAMPLES: usize = 120;
+const GRAPH_BAR_WIDTH: f32 = 2f32;
+const GRAPH_HEIGHT: f32 = 80f32;
+/// The frame time at the top of the graph, in milliseconds
+const GRAPH_MAX_FRAME_TIME: f32 = 50f32;
+const TARGET_FRAME_TIME: f32 = 1000f32 / 60f32;
+const MARGIN: f32 = 10f32;
+const LINE_HEIGHT: f32 = 18f32;
+
+const COLOR_TEXT: Color = (1.0, 1.0, 1.0, 1.0);
+const COLOR_PANEL: Color = (0.0, 0.0, 0.0, 0.6);
+const COLOR_PLAYER_LAYER: Color = (0.2, 1.0, 0.2, 0.9);
+const COLOR_ENEMY_LAYER: Color = (1.0, 0.2, 0.2, 0.9);
+const COLOR_NO_LAYER: Color = (0.7, 0.7, 0.7, 0.6);
+
+/// Debugging information drawn on top of everything, including the post-processing.
+/// Shows the hitbox and name of every entity, the amount of entities per name and a frame time graph.
+pub struct DebugOverlay {
+    pub enabled: bool,
+    /// The key that turns the overlay on and off
+    pub toggle_key: VirtualKeyCode,
+    frame_times: VecDeque<f32>,
+}
+
+impl DebugOverlay {
+    pub fn new(enabled: bool) -> DebugOverlay {
+        DebugOverlay {
+            enabled,
+            toggle_key: VirtualKeyCode::F3,
+            frame_times: VecDeque::with_capacity(GRAPH_SAMPLES),
+        }
+    }
+
+    /// Record how long the last frame took, in milliseconds.
+    /// This happens even while the overlay is hidden, so the graph is filled when it is shown.
+    pub fn record_frame(&mut self, frame_time: f32) {
+        if self.frame_times.len() == GRAPH_SAMPLES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// The average frame time over the frames in the graph, in milliseconds
+    pub fn average_frame_time(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0f32;
+        }
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32
+    }
+
+    pub fn draw<T: TGraphicIndex>(
+        &self,
+        graphics: &mut EngineGraphics<T>,
+        entities: &[EntityWrapper<T>],
+        particle_count: usize,
+    ) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        for entity in entities {
+            let state = &entity.state;
+            let color = match entity.entity.collision_layers() {
+                Some(CollisionLayer::Player) => COLOR_PLAYER_LAYER,
+                Some(CollisionLayer::Enemy) => COLOR_ENEMY_LAYER,
+                None => COLOR_NO_LAYER,
+            };
+            graphics.draw_rectangle_outline(
+                state.x - state.hitbox.left,
+                state.y - state.hitbox.top,
+                state.hitbox.left + state.hitbox.right,
+                state.hitbox.top + state.hitbox.bottom,
+                1f32,
+                color,
+            )?;
+        }
+        let label_style = TextStyle::default()
+            .with_size(12)
+            .with_color(color_with_alpha(COLOR_TEXT, 0.8));
+        for entity in entities {
+            let state = &entity.state;
+            graphics.draw_text(
+                &format!("{} ({:.0}, {:.0})", entity.name, state.x, state.y),
+                state.x + state.hitbox.right + 4f32,
+                state.y - state.hitbox.top,
+                &label_style,
+            )?;
+        }
+
+        let mut counts = BTreeMap::new();
+        for entity in entities {
+            *counts.entry(entity.name.as_str()).or_insert(0) += 1;
+        }
+        let mut lines = vec![
+            format!("Entities: {}", entities.len()),
+            format!("Particles: {}", particle_count),
+        ];
+        lines.extend(
+            counts
+                .iter()
+                .map(|(name, count)| format!("  {}: {}", name, count)),
+        );
+        graphics.draw_screen_rectangle(
+            MARGIN,
+            MARGIN,
+            200f32,
+            lines.len() as f32 * LINE_HEIGHT + MARGIN,
+            COLOR_PANEL,
+        )?;
+        for (index, line) in lines.into_iter().enumerate() {
+            graphics.draw_screen_text(
+                &line,
+                MARGIN * 1.5,
+                MARGIN * 1.5 + index as f32 * LINE_HEIGHT,
+                &TextStyle::default(),
+            )?;
+        }
+
+        self.draw_graph(graphics)
+    }
+
+    fn draw_graph<T: TGraphicIndex>(&self, graphics: &mut EngineGraphics<T>) -> Result<()> {
+        let width = GRAPH_SAMPLES as f32 * GRAPH_BAR_WIDTH;
+        let left = graphics.width - width - MARGIN;
+        let top = MARGIN + LINE_HEIGHT;
+        let bottom = top + GRAPH_HEIGHT;
+        let pixels_per_ms = GRAPH_HEIGHT / GRAPH_MAX_FRAME_TIME;
+
+        graphics.draw_screen_rectangle(
+            left,
+            MARGIN,
+            width,
+            GRAPH_HEIGHT + LINE_HEIGHT,
+            COLOR_PANEL,
+        )?;
+        for (index, frame_time) in self.frame_times.iter().enumerate() {
+            let height = (frame_time * pixels_per_ms).min(GRAPH_HEIGHT);
+            let color = if *frame_time <= TARGET_FRAME_TIME {
+                (0.2, 0.9, 0.2, 0.9)
+            } else if *frame_time <= TARGET_FRAME_TIME * 2f32 {
+                (0.9, 0.9, 0.2, 0.9)
+            } else {
+                (0.9, 0.2, 0.2, 0.9)
+            };
+            graphics.draw_screen_rectangle(
+                left + index as f32 * GRAPH_BAR_WIDTH,
+                bottom - height,
+                GRAPH_BAR_WIDTH,
+                height,
+                color,
+            )?;
+        }
+        let target_y = bottom - TARGET_FRAME_TIME * pixels_per_ms;
+        graphics.draw_screen_rectangle(
+            left,
+            target_y,
+            width,
+            1f32,
+            color_with_alpha(COLOR_TEXT, 0.5),
+        )?;
+
+        let average = self.average_frame_time();
+        let fps = if average > 0f32 {
+            1000f32 / average
+        } else {
+            0f32
+        };
+        graphics.draw_screen_text(
+            &format!("FPS: {:.0} - {:.1} ms", fps, average),
+            left + MARGIN / 2f32,
+            MARGIN,
+            &TextStyle::default(),
+        )
+    }
+}
+
+fn color_with_alpha((r, g, b, _): Color, alpha: f32) -> Color {
+    (r, g, b, alpha)
+}
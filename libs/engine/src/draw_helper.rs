@@ -1,17 +1,47 @@
 #![allow(deprecated)]
 
 use glium::index::PrimitiveType;
-use glium::texture::{RawImage2d, Texture2d};
+use glium::texture::Texture2d;
 use glium::{IndexBuffer, VertexBuffer};
-use image;
-use std::io::Cursor;
+use std::rc::Rc;
 
-use super::{EngineGraphics, Result, TGraphicIndex};
+use super::{Color, EngineGraphics, Result, TGraphicIndex, TextureRegion};
+
+/// Colour adjustments applied to a sprite when it is drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteEffects {
+    /// Multiplied with every pixel of the sprite
+    pub tint: Color,
+    /// Added to every pixel of the sprite, scaled by the alpha of the flash colour
+    pub flash: Color,
+    /// Multiplied with the alpha of every pixel of the sprite
+    pub opacity: f32,
+}
+
+impl Default for SpriteEffects {
+    fn default() -> SpriteEffects {
+        SpriteEffects {
+            tint: (1.0, 1.0, 1.0, 1.0),
+            flash: (0.0, 0.0, 0.0, 0.0),
+            opacity: 1.0,
+        }
+    }
+}
+
+impl SpriteEffects {
+    pub fn tinted(tint: Color) -> SpriteEffects {
+        SpriteEffects {
+            tint,
+            ..SpriteEffects::default()
+        }
+    }
+}
 
 pub struct DrawHelper {
     pub vertex_buffer: VertexBuffer<Vertex>,
     pub index_buffer: IndexBuffer<u8>,
-    pub texture: Texture2d,
+    pub texture: Rc<Texture2d>,
+    pub region: TextureRegion,
     pub width: f32,
     pub height: f32,
 }
@@ -21,26 +51,27 @@ impl DrawHelper {
         engine: &EngineGraphics<T>,
         width: f32,
         height: f32,
-        texture: &[u8],
+        texture: Rc<Texture2d>,
+        region: TextureRegion,
     ) -> Result<DrawHelper> {
         let vertex_buffer = VertexBuffer::new(
             &engine.display,
             &[
                 Vertex {
                     position: [-width / 2f32, height / 2f32],
-                    tex_coords: [0.0, 0.0],
+                    tex_coords: [region.left, region.bottom],
                 },
                 Vertex {
                     position: [width / 2f32, height / 2f32],
-                    tex_coords: [1.0, 0.0],
+                    tex_coords: [region.right, region.bottom],
                 },
                 Vertex {
                     position: [-width / 2f32, -height / 2f32],
-                    tex_coords: [0.0, 1.0],
+                    tex_coords: [region.left, region.top],
                 },
                 Vertex {
                     position: [width / 2f32, -height / 2f32],
-                    tex_coords: [1.0, 1.0],
+                    tex_coords: [region.right, region.top],
                 },
             ],
         )?;
@@ -48,15 +79,11 @@ impl DrawHelper {
         let index_buffer =
             IndexBuffer::<u8>::new(&engine.display, PrimitiveType::TriangleStrip, &[0, 1, 2, 3])?;
 
-        let image = image::load(Cursor::new(texture), image::ImageFormat::Png)?.to_rgba();
-        let image_dimensions = image.dimensions();
-        let image = RawImage2d::from_raw_rgba_reversed(&image.into_raw(), image_dimensions);
-        let texture = Texture2d::new(&engine.display, image)?;
-
         Ok(DrawHelper {
             vertex_buffer,
             index_buffer,
             texture,
+            region,
             width,
             height,
         })
@@ -1,90 +1,167 @@
 use super::*;
 use glium::Surface;
-use winit::{KeyboardInput, WindowEvent};
+use rand::rngs::StdRng;
+use std::mem;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use winit::{KeyboardInput, MouseScrollDelta, WindowEvent};
+
+/// How long a frame takes while the window is minimized, in milliseconds
+const MINIMIZED_FRAME_TIME: u64 = 100;
 
 pub struct Engine<T: TGraphicIndex> {
     pub graphics: EngineGraphics<T>,
     pub keyboard: KeyboardState,
+    pub mouse: MouseState,
+    /// The bindings of the actions and axes the entities use
+    pub input: InputMap,
+    pub input_queue: InputQueue,
+    pub text_input: TextInput,
     pub running: bool,
-    pub render_hitboxes: bool,
+    /// Shown in the window title before the frame rate
+    pub title: String,
+    /// The frame rate limit and the longest frame time the entities see
+    pub frame_pacing: FramePacing,
+    pub entity_limit: usize,
+    pub debug_overlay: DebugOverlay,
+    pub ui: Ui,
+    pub ui_skin: UiSkin<T>,
+    /// The sounds the entities play, see `EntityEvent::PlaySound`
+    pub audio: Audio,
+    /// Multiplied with the frame time before the entities, particles and background are updated
+    pub time_scale: f32,
+    /// While paused, the entities are updated with a frame time of 0, so menus keep working
+    pub paused: bool,
+    /// Pause while the window is in the background or minimized, and continue when it comes back
+    pub auto_pause: bool,
+    /// Whether the current pause was started by `auto_pause`
+    pub auto_paused: bool,
+    pub focused: bool,
+    pub minimized: bool,
+    /// What happened to the window since the last frame
+    pub lifecycle_events: Vec<LifecycleEvent>,
+    /// Where screenshots are saved when F12 is pressed
+    pub screenshot_directory: String,
+    pub screenshot_requested: bool,
+    /// The file of the last screenshot, e.g. to tell the player where it is, or why it failed.
+    /// A failed screenshot does not stop the game.
+    pub last_screenshot: Option<Result<PathBuf>>,
+    /// Events that failed without stopping the game, see `take_errors`
+    pub errors: Vec<Error>,
 
-    pub last_update_time: u64,
+    /// Where the frame times come from, see `set_clock`
+    pub clock: Rc<dyn Clock>,
+    pub last_update_time: f64,
+    pub last_draw_time: f64,
     pub entities: Vec<EntityWrapper<T>>,
-    pub rng: rand::prelude::ThreadRng,
+    pub background: Background<T>,
+    pub particles: ParticleSystem<T>,
+    pub next_entity_id: u64,
+    pub rng: StdRng,
 }
 
 impl<T: TGraphicIndex> Engine<T> {
-    #[cfg(not(debug_assertions))]
+    /// Open a window of the given size with the default settings, see `builder` for the others
     pub fn new(width: f32, height: f32) -> Result<Engine<T>> {
-        let engine = Engine {
-            graphics: EngineGraphics::<T>::new(width, height)?,
-            keyboard: KeyboardState::default(),
-            running: true,
-            render_hitboxes: false,
-            last_update_time: self::time::get(),
-            entities: Vec::new(),
-            rng: ::rand::thread_rng(),
-        };
-        Ok(engine)
+        EngineBuilder::default().with_size(width, height).build()
     }
-    #[cfg(debug_assertions)]
-    pub fn new(width: f32, height: f32) -> Result<Engine<T>> {
-        let engine = Engine {
-            graphics: EngineGraphics::<T>::new(width, height)?,
-            keyboard: KeyboardState::default(),
-            running: true,
-            render_hitboxes: true,
-            last_update_time: self::time::get(),
-            entities: Vec::new(),
-            rng: ::rand::thread_rng(),
-        };
-        Ok(engine)
+
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
     }
 
-    #[cfg(debug_assertions)]
+    /// Take the time from another clock, e.g. a `MockClock` to step the engine in tests.
+    /// The next frame starts at the current time of the new clock.
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        self.last_update_time = clock.now();
+        self.last_draw_time = clock.now();
+        self.frame_pacing.reset();
+        self.input_queue.reset_time(clock.now());
+        self.clock = clock;
+    }
+
+    /// Change the vsync while the engine runs, e.g. from an options menu through `EntityEvent::SetVSync`.
+    /// The window and its context are created again, as the swap interval can only be set when they are created.
+    pub fn set_vsync(&mut self, vsync: VSync) -> Result<()> {
+        self.graphics.set_vsync(vsync)?;
+        self.frame_pacing.set_vsync(vsync);
+        Ok(())
+    }
+
+    /// The errors of events that could not be handled, e.g. a vsync the driver refused.
+    /// Sound errors are kept by `Audio::take_errors`.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        mem::replace(&mut self.errors, Vec::new())
+    }
+
+    /// Add an entity, unless there are `entity_limit` entities already
     pub fn register_entity(&mut self, entity: Box<dyn EntityTrait<T>>) {
-        if self.entities.len() < 200 {
-            let wrapper = EntityWrapper::new(entity, self);
+        if self.entities.len() < self.entity_limit {
+            let wrapper = EntityWrapper::new(entity, self, self.next_entity_id);
+            self.next_entity_id += 1;
             self.entities.push(wrapper);
         }
     }
 
-    #[cfg(not(debug_assertions))]
-    pub fn register_entity(&mut self, entity: Box<EntityTrait<T>>) {
-        if self.entities.len() < 10000 {
-            let wrapper = EntityWrapper::new(entity, self);
-            self.entities.push(wrapper);
-        }
+    /// Forget the input and window events of the last frame
+    fn frame_start(&mut self) {
+        self.keyboard.frame_start();
+        self.mouse.frame_start();
+        self.text_input.frame_start();
+        self.input_queue.frame_start();
+        self.lifecycle_events.clear();
     }
 
     pub fn draw(&mut self) -> Result<()> {
-        self.keyboard.frame_start();
-        self.graphics.frame = Some(self.graphics.display.draw());
+        self.frame_start();
+        self.graphics.pack_graphics()?;
+        self.graphics
+            .post_processing
+            .set_time((self.clock.now() / 1000f64) as f32);
+        self.graphics.frame = Some(
+            self.graphics
+                .post_processing
+                .begin(&self.graphics.display)?,
+        );
 
         if let Some(frame) = &mut self.graphics.frame {
-            frame.clear_color(0.0, 0.0, 1.0, 1.0);
-        }
-
-        if self.render_hitboxes {
-            for entity in &self.entities {
-                self.graphics.draw_rectangle(
-                    entity.state.x - entity.state.hitbox.left,
-                    entity.state.y - entity.state.hitbox.top,
-                    entity.state.hitbox.left + entity.state.hitbox.right,
-                    entity.state.hitbox.top + entity.state.hitbox.bottom,
-                    (0.0, 0.0, 0.0, 0.0f32),
-                )?;
-            }
+            let (r, g, b, a) = self.background.clear_color;
+            frame.clear_color(r, g, b, a);
         }
+        self.background.draw(&mut self.graphics)?;
 
         for entity in &self.entities {
             entity.entity.draw(&entity.state, &mut self.graphics)?;
         }
+        self.particles.draw(&mut self.graphics)?;
+        self.graphics.flush_primitives()?;
 
-        if let Some(frame) = self.graphics.frame.take() {
-            frame.finish()?;
+        if let Some(target) = self.graphics.frame.take() {
+            let frame = self
+                .graphics
+                .post_processing
+                .finish(&self.graphics.display, target)?;
+            // The UI and the overlay are drawn after the post-processing, so they stay readable
+            self.graphics.frame = Some(RenderTarget::Frame(frame));
+            self.ui.draw(&mut self.graphics, &self.ui_skin)?;
+            self.debug_overlay.draw(
+                &mut self.graphics,
+                &self.entities,
+                self.particles.particle_count(),
+            )?;
+            self.graphics.flush_primitives()?;
+            if let Some(target) = self.graphics.frame.take() {
+                target.into_parts().0.finish()?;
+            }
+        }
+        let frame_time = self::time::since(&*self.clock, &mut self.last_draw_time) as f32;
+        self.debug_overlay.record_frame(frame_time);
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            self.last_screenshot = Some(self.graphics.save_screenshot(&self.screenshot_directory));
         }
-        self.graphics.text_graphics.frame_end();
         Ok(())
     }
 
@@ -96,6 +173,25 @@ impl<T: TGraphicIndex> Engine<T> {
                 }
                 EntityEvent::ClearAllEntities => {
                     self.entities.clear();
+                    self.particles.clear();
+                }
+                EntityEvent::SpawnEmitter(emitter) => {
+                    self.particles.add(emitter);
+                }
+                EntityEvent::PlaySound(request) => {
+                    if let Err(error) = self.audio.play(&request) {
+                        self.audio.mixer().report(error);
+                    }
+                }
+                EntityEvent::Music(command) => {
+                    if let Err(error) = self.audio.music_command(&command) {
+                        self.audio.mixer().report(error);
+                    }
+                }
+                EntityEvent::SetVSync(vsync) => {
+                    if let Err(error) = self.set_vsync(vsync) {
+                        self.errors.push(error);
+                    }
                 }
             }
         }
@@ -138,17 +234,51 @@ impl<T: TGraphicIndex> Engine<T> {
     }
 
     pub fn update_entities(&mut self) {
-        let delta_time = self::time::since(&mut self.last_update_time) as f32;
+        // While typing, the keys belong to the entity with the text focus
+        if !self.text_input.has_focus() {
+            if self
+                .keyboard
+                .is_pressed_this_frame(self.debug_overlay.toggle_key)
+            {
+                self.debug_overlay.enabled = !self.debug_overlay.enabled;
+            }
+            if self
+                .input
+                .action_pressed_this_frame(QUIT_ACTION, &self.keyboard, &self.mouse)
+            {
+                self.running = false;
+            }
+        }
+
+        let time_scale = if self.paused { 0f32 } else { self.time_scale };
+        let elapsed = self
+            .frame_pacing
+            .clamp_delta_time(self::time::since(&*self.clock, &mut self.last_update_time) as f32);
+        let delta_time = elapsed * time_scale;
 
         let mut events = Vec::new();
 
+        self.mouse.set_camera(self.graphics.camera);
+        self.input_queue.set_time(self.clock.now());
+        let ui_keyboard = if self.text_input.has_focus() {
+            None
+        } else {
+            Some(&self.keyboard)
+        };
+        self.ui.begin_frame(ui_keyboard, &self.mouse);
         for entity in &mut self.entities {
             let mut state = GameState {
                 delta_time,
                 keyboard: &self.keyboard,
+                mouse: &self.mouse,
+                input: &self.input,
+                input_queue: &self.input_queue,
+                text_input: &mut self.text_input,
                 screen_width: self.graphics.width,
                 screen_height: self.graphics.height,
                 rng: &mut self.rng,
+                ui: &mut self.ui,
+                lifecycle_events: &self.lifecycle_events,
             };
             let update_result = entity.entity.update(&mut state, &mut entity.state);
             events.extend(update_result.into_iter());
@@ -159,27 +289,59 @@ impl<T: TGraphicIndex> Engine<T> {
         self.check_collisions();
 
         self.entities.retain(|e| e.state.active);
+        if let Some(focus) = self.text_input.focus() {
+            if !self.entities.iter().any(|e| e.state.id == focus) {
+                self.text_input.release_focus(focus);
+            }
+        }
+
+        self.particles
+            .update(delta_time, &self.entities, &mut self.rng);
+        self.background.update(delta_time, &self.graphics);
+        self.audio.update_positions(
+            &self.entities,
+            self.graphics.camera,
+            self.graphics.width,
+            self.graphics.height,
+        );
+        // Sounds keep their pitch and speed in slow motion
+        if let Err(error) = self.audio.update(elapsed) {
+            self.audio.mixer().report(error);
+        }
     }
 
+    /// Update and draw until the engine stops.
+    /// Sounds that could not be played do not stop it, they are kept for `Audio::take_errors`.
+    /// Neither do screenshots that could not be saved, see `last_screenshot`.
     pub fn run(&mut self) {
-        let mut frame_count = 0;
-        let mut last_frame_time = self::time::get();
+        let mut last_frame_time = self.clock.now();
         while self.running {
-            self.draw().unwrap();
+            if self.minimized {
+                // Nothing can be seen, so there is no need to draw or to update at full speed
+                self.frame_start();
+                thread::sleep(Duration::from_millis(MINIMIZED_FRAME_TIME));
+            } else {
+                self.draw().unwrap();
+            }
             self.update_events();
             self.update_entities();
+            self.frame_pacing.end_frame(&*self.clock);
 
-            frame_count += 1;
-            if self::time::has_elapsed(&mut last_frame_time, 500) {
-                // println!("FPS: {} - entities: {}", frame_count * 2, self.entities.len());
-                self.graphics.display.gl_window().set_title(&format!(
-                    "FPS: {} - entities: {}",
-                    frame_count * 2,
-                    self.entities.len()
-                ));
-                frame_count = 0;
+            if self::time::has_elapsed(&*self.clock, &mut last_frame_time, 500f64) {
+                let title = self.graphics.localization.format(
+                    "engine.window_title",
+                    &[
+                        ("title", &self.title),
+                        ("fps", &(self.frame_pacing.frame_rate().round() as u32)),
+                        ("entities", &self.entities.len()),
+                    ],
+                );
+                self.graphics.display.gl_window().set_title(&title);
             }
         }
+        if let Err(error) = self.audio.finish() {
+            self.audio.mixer().report(error);
+        }
     }
 
     pub fn update_events(&mut self) {
@@ -187,24 +349,46 @@ impl<T: TGraphicIndex> Engine<T> {
             graphics,
             running,
             keyboard,
+            mouse,
+            input_queue,
+            text_input,
+            screenshot_requested,
+            clock,
             ..
         } = self;
 
+        let mut lifecycle = Vec::new();
+        let screen_size = (graphics.width, graphics.height);
+        let mut window_size = graphics
+            .display
+            .gl_window()
+            .get_inner_size()
+            .map(|size| (size.width as f32, size.height as f32))
+            .unwrap_or(screen_size);
         graphics.events_loop.poll_events(|event| {
+            if let Event::Suspended(suspended) = event {
+                lifecycle.push(if suspended {
+                    LifecycleEvent::Minimized
+                } else {
+                    LifecycleEvent::Restored
+                });
+            }
             if let Event::WindowEvent { event, .. } = event {
                 match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
-                        ..
-                    } => {
+                    WindowEvent::CloseRequested => {
                         *running = false;
                     }
+                    WindowEvent::Focused(true) => lifecycle.push(LifecycleEvent::FocusGained),
+                    WindowEvent::Focused(false) => lifecycle.push(LifecycleEvent::FocusLost),
+                    // Windows are resized to nothing when they are minimized
+                    WindowEvent::Resized(size) => {
+                        window_size = (size.width as f32, size.height as f32);
+                        lifecycle.push(if size.width <= 0.0 || size.height <= 0.0 {
+                            LifecycleEvent::Minimized
+                        } else {
+                            LifecycleEvent::Restored
+                        })
+                    }
                     WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
@@ -213,7 +397,13 @@ impl<T: TGraphicIndex> Engine<T> {
                                 ..
                             },
                         ..
-                    } => keyboard.set_keydown(code),
+                    } => {
+                        if code == VirtualKeyCode::F12 && !text_input.has_focus() {
+                            *screenshot_requested = true;
+                        }
+                        keyboard.set_keydown(code);
+                        input_queue.press(InputBinding::Key(code), clock.now());
+                    }
                     WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
@@ -222,10 +412,82 @@ impl<T: TGraphicIndex> Engine<T> {
                                 ..
                             },
                         ..
-                    } => keyboard.clear_keydown(code),
+                    } => {
+                        keyboard.clear_keydown(code);
+                        input_queue.release(InputBinding::Key(code), clock.now());
+                    }
+                    WindowEvent::ReceivedCharacter(character) => {
+                        text_input.push_character(character)
+                    }
+                    WindowEvent::CursorMoved { position, .. } => {
+                        // The cursor can start inside the window without entering it
+                        mouse.set_inside(true);
+                        mouse.set_window_position(
+                            position.x as f32,
+                            position.y as f32,
+                            window_size,
+                            screen_size,
+                        );
+                    }
+                    WindowEvent::CursorEntered { .. } => mouse.set_inside(true),
+                    WindowEvent::CursorLeft { .. } => mouse.set_inside(false),
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button,
+                        ..
+                    } => {
+                        mouse.set_button_down(button);
+                        input_queue.press(InputBinding::Mouse(button), clock.now());
+                    }
+                    WindowEvent::MouseInput {
+                        state: ElementState::Released,
+                        button,
+                        ..
+                    } => {
+                        mouse.clear_button_down(button);
+                        input_queue.release(InputBinding::Mouse(button), clock.now());
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => match delta {
+                        MouseScrollDelta::LineDelta(x, y) => mouse.scroll_lines(x, y),
+                        MouseScrollDelta::PixelDelta(position) => {
+                            mouse.scroll_pixels(position.x as f32, position.y as f32)
+                        }
+                    },
                     _ => (),
                 }
             }
         });
+
+        for event in lifecycle {
+            self.handle_lifecycle_event(event);
+        }
+    }
+
+    fn handle_lifecycle_event(&mut self, event: LifecycleEvent) {
+        match event {
+            LifecycleEvent::FocusLost if self.focused => {
+                self.focused = false;
+                // The releases of held keys go to the window that has the focus now
+                self.keyboard.release_all();
+                self.mouse.release_all();
+                self.input_queue.release_all(self.clock.now());
+            }
+            LifecycleEvent::FocusGained if !self.focused => self.focused = true,
+            LifecycleEvent::Minimized if !self.minimized => self.minimized = true,
+            LifecycleEvent::Restored if self.minimized => self.minimized = false,
+            _ => return,
+        }
+        self.lifecycle_events.push(event);
+
+        let in_background = !self.focused || self.minimized;
+        if self.auto_pause && in_background && !self.paused {
+            self.paused = true;
+            self.auto_paused = true;
+            self.lifecycle_events.push(LifecycleEvent::AutoPaused);
+        } else if !in_background && self.auto_paused {
+            self.paused = false;
+            self.auto_paused = false;
+            self.lifecycle_events.push(LifecycleEvent::AutoResumed);
+        }
     }
 }
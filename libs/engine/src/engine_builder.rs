@@ -0,0 +1,245 @@
+use super::error::Error;
+use super::{
+    Audio, Background, Clock, Color, DebugOverlay, Engine, EngineGraphics, FramePacing, InputMap,
+    InputQueue, KeyboardState, MonotonicClock, MouseState, ParticleSystem, Result, TGraphicIndex,
+    TextInput, Ui, UiSkin, VSync, DEFAULT_MAX_DELTA_TIME,
+};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::rc::Rc;
+
+/// The window and OpenGL context the engine is created with
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowSettings {
+    pub title: String,
+    /// The size of the screen the entities see, in logical pixels
+    pub width: f32,
+    pub height: f32,
+    /// Resizable and fullscreen windows stretch the screen to the size of the window
+    pub resizable: bool,
+    /// Cover the primary monitor
+    pub fullscreen: bool,
+    pub vsync: VSync,
+    /// The amount of samples per pixel for anti-aliasing, 0 turns it off
+    pub multisampling: u16,
+}
+
+impl Default for WindowSettings {
+    fn default() -> WindowSettings {
+        WindowSettings {
+            title: "Engine".to_owned(),
+            width: 1280f32,
+            height: 720f32,
+            resizable: false,
+            fullscreen: false,
+            vsync: VSync::On,
+            multisampling: 0,
+        }
+    }
+}
+
+/// Configures the window and the engine before they are created, see `Engine::builder`
+#[derive(Clone)]
+pub struct EngineBuilder {
+    pub window: WindowSettings,
+    pub clear_color: Color,
+    /// Entities registered after this many are dropped
+    pub entity_limit: usize,
+    pub target_frame_rate: Option<f32>,
+    pub max_delta_time: f32,
+    pub debug_overlay: bool,
+    /// Print the OpenGL version once the window is created
+    pub log_gl_version: bool,
+    pub clock: Rc<dyn Clock>,
+    /// The seed of `Engine::rng`, a random one is used when it's not set
+    pub seed: Option<u64>,
+}
+
+impl Default for EngineBuilder {
+    fn default() -> EngineBuilder {
+        EngineBuilder {
+            window: WindowSettings::default(),
+            clear_color: (0.0, 0.0, 0.0, 1.0),
+            entity_limit: if cfg!(debug_assertions) { 200 } else { 10000 },
+            target_frame_rate: None,
+            max_delta_time: DEFAULT_MAX_DELTA_TIME,
+            debug_overlay: cfg!(debug_assertions),
+            log_gl_version: false,
+            clock: Rc::new(MonotonicClock::default()),
+            seed: None,
+        }
+    }
+}
+
+impl EngineBuilder {
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.window.title = title.to_owned();
+        self
+    }
+
+    pub fn with_size(mut self, width: f32, height: f32) -> Self {
+        self.window.width = width;
+        self.window.height = height;
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.window.resizable = resizable;
+        self
+    }
+
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.window.fullscreen = fullscreen;
+        self
+    }
+
+    /// The vsync the window starts with, see `Engine::set_vsync` to change it later
+    pub fn with_vsync(mut self, vsync: VSync) -> Self {
+        self.window.vsync = vsync;
+        self
+    }
+
+    /// Limit the frames per second, on top of vsync
+    pub fn with_frame_rate(mut self, frame_rate: f32) -> Self {
+        self.target_frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// The longest frame time the entities see, in milliseconds
+    pub fn with_max_delta_time(mut self, max_delta_time: f32) -> Self {
+        self.max_delta_time = max_delta_time;
+        self
+    }
+
+    /// Use a power of two up to 16, or 0 for no anti-aliasing.
+    /// The scene is anti-aliased the same way when it is rendered for post-processing.
+    pub fn with_multisampling(mut self, samples: u16) -> Self {
+        self.window.multisampling = samples;
+        self
+    }
+
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    pub fn with_entity_limit(mut self, limit: usize) -> Self {
+        self.entity_limit = limit;
+        self
+    }
+
+    /// Show the overlay from the start, it can still be toggled with its key
+    pub fn with_debug_overlay(mut self, enabled: bool) -> Self {
+        self.debug_overlay = enabled;
+        self
+    }
+
+    pub fn with_gl_version_logging(mut self, enabled: bool) -> Self {
+        self.log_gl_version = enabled;
+        self
+    }
+
+    /// Take the frame times from another clock than the system's, e.g. a `MockClock`
+    pub fn with_clock(mut self, clock: Rc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Make `Engine::rng` give the same numbers every run
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Check the settings before a window is opened for them
+    pub fn validate(&self) -> Result<()> {
+        let window = &self.window;
+        if !(window.width > 0f32 && window.height > 0f32) {
+            return Err(Error::engine_settings(&format!(
+                "the size {}x{} is not positive",
+                window.width, window.height
+            )));
+        }
+        if window.multisampling > 16
+            || !(window.multisampling == 0 || window.multisampling.is_power_of_two())
+        {
+            return Err(Error::engine_settings(&format!(
+                "{} samples is not a power of two up to 16",
+                window.multisampling
+            )));
+        }
+        if let Some(frame_rate) = self.target_frame_rate {
+            if !(frame_rate > 0f32) {
+                return Err(Error::engine_settings(&format!(
+                    "the frame rate {} is not positive",
+                    frame_rate
+                )));
+            }
+        }
+        if !(self.max_delta_time > 0f32) {
+            return Err(Error::engine_settings(&format!(
+                "the maximum delta time {} is not positive",
+                self.max_delta_time
+            )));
+        }
+        if self.entity_limit == 0 {
+            return Err(Error::engine_settings("the entity limit is 0"));
+        }
+        Ok(())
+    }
+
+    /// Open the window and create the engine
+    pub fn build<T: TGraphicIndex>(self) -> Result<Engine<T>> {
+        self.validate()?;
+        let graphics = EngineGraphics::<T>::new(&self.window)?;
+        if self.log_gl_version {
+            println!("OpenGL {:?}", graphics.display.get_opengl_version());
+        }
+        let rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut frame_pacing = FramePacing::new(self.window.vsync);
+        frame_pacing.target_frame_rate = self.target_frame_rate;
+        frame_pacing.max_delta_time = self.max_delta_time;
+        let engine = Engine {
+            graphics,
+            keyboard: KeyboardState::default(),
+            mouse: MouseState::default(),
+            input: InputMap::default(),
+            input_queue: InputQueue::default(),
+            text_input: TextInput::default(),
+            running: true,
+            title: self.window.title,
+            frame_pacing,
+            entity_limit: self.entity_limit,
+            debug_overlay: DebugOverlay::new(self.debug_overlay),
+            ui: Ui::default(),
+            ui_skin: UiSkin::default(),
+            audio: Audio::default_output(),
+            time_scale: 1f32,
+            paused: false,
+            auto_pause: false,
+            auto_paused: false,
+            focused: true,
+            minimized: false,
+            lifecycle_events: Vec::new(),
+            screenshot_directory: "screenshots".to_owned(),
+            screenshot_requested: false,
+            last_screenshot: None,
+            errors: Vec::new(),
+            last_update_time: self.clock.now(),
+            last_draw_time: self.clock.now(),
+            clock: self.clock,
+            entities: Vec::new(),
+            background: Background {
+                clear_color: self.clear_color,
+                ..Background::default()
+            },
+            particles: ParticleSystem::default(),
+            next_entity_id: 1,
+            rng,
+        };
+        Ok(engine)
+    }
+}
@@ -1,48 +1,140 @@
 #![allow(deprecated)]
 
-use super::{Color, DrawHelper, Result, TGraphicIndex, TextGraphics};
+use super::error::Error;
+use super::nine_slice::slice_vertices;
+use super::{
+    screenshot, Animation, AnimationCursor, Arguments, AtlasEntry, BitmapFontDescriptor,
+    BitmapTextStyle, Camera, Color, DrawHelper, Localization, NineSlice, PostProcessing,
+    PrimitiveBatch, RenderTarget, Result, RetainedText, SpriteEffects, SpriteRegion, TGraphicIndex,
+    TextGraphics, TextStyle, TextureAtlas, VSync, WindowSettings,
+};
 use glium::glutin::dpi::LogicalSize;
 use glium::glutin::{ContextBuilder, WindowBuilder};
-use glium::index::PrimitiveType;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::{RawImage2d, Texture2d};
 use glium::uniforms::UniformsStorage;
-use glium::{Blend, Display, DrawParameters, Frame, IndexBuffer, Program, Surface, VertexBuffer};
+use glium::{Blend, Display, DrawParameters, Program, Surface, VertexBuffer};
+use image::{self, RgbaImage};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+use std::f32::consts::PI;
+use std::fs::{self, File};
+use std::io::{Cursor, Read};
+use std::mem;
+use std::path::{Path, PathBuf};
 use winit::EventsLoop;
 
-#[derive(Copy, Clone)]
-struct Vertex {
-    pub dimension_affinity: [f32; 2],
+/// The language of the engine's own strings, and the fallback for missing translations
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// A graphic that has been loaded, but is waiting for the atlas to be packed
+struct PendingGraphic<T: TGraphicIndex> {
+    key: T,
+    image: AtlasImage,
+    width: f32,
+    height: f32,
+    kind: PendingKind,
+}
+
+/// A bitmap font whose glyph sheets are waiting for the atlas to be packed
+struct PendingFont {
+    name: String,
+    descriptor: BitmapFontDescriptor,
+    /// The image of every page of the font
+    pages: Vec<AtlasImage>,
+}
+
+/// Where the pixels of a loaded file are
+#[derive(Clone)]
+enum AtlasImage {
+    /// Waiting in the atlas at this index until the next `pack_graphics`
+    Pending(usize),
+    /// On a page of an earlier pack, files loaded again later reuse it
+    Packed(AtlasEntry),
+}
+
+/// A bitmap font with a sprite for every glyph
+struct BitmapFont {
+    descriptor: BitmapFontDescriptor,
+    glyphs: HashMap<char, DrawHelper>,
+}
+
+enum PendingKind {
+    Image,
+    Region(SpriteRegion),
+    Animation(Vec<SpriteRegion>),
 }
 
-implement_vertex!(Vertex, dimension_affinity);
+/// How the transparency of a sprite sheet is determined
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SheetTransparency {
+    /// Use the alpha channel of the image
+    ImageAlpha,
+    /// The image has a black background. The brightest channel of every pixel is used as its alpha,
+    /// so glowing edges fade out instead of becoming dark.
+    BlackToAlpha,
+}
 
 pub struct EngineGraphics<T: TGraphicIndex> {
     pub display: Display,
     pub events_loop: EventsLoop,
     pub textured_program: Program,
     pub color_program: Program,
-    pub frame: Option<Frame>,
+    pub particle_program: Program,
+    pub frame: Option<RenderTarget>,
+    pub post_processing: PostProcessing,
+    pub camera: Camera,
     pub width: f32,
     pub height: f32,
     pub text_graphics: TextGraphics,
+    pub localization: Localization,
 
+    /// The settings the window was created with, to create it again
+    window: WindowSettings,
     graphics: HashMap<T, DrawHelper>,
+    animations: HashMap<T, Vec<DrawHelper>>,
+    atlas: TextureAtlas,
+    /// Every file is loaded once, with the transparency of its first load
+    atlas_files: HashMap<String, (SheetTransparency, AtlasImage)>,
+    pending_graphics: Vec<PendingGraphic<T>>,
+    bitmap_fonts: HashMap<String, BitmapFont>,
+    pending_fonts: Vec<PendingFont>,
+    primitives: PrimitiveBatch,
+}
+
+fn window_builder(
+    settings: &WindowSettings,
+    size: LogicalSize,
+    events_loop: &EventsLoop,
+) -> WindowBuilder {
+    let mut wb = WindowBuilder::new()
+        .with_title(settings.title.clone())
+        .with_dimensions(size)
+        .with_resizable(settings.resizable);
+    if !settings.resizable && !settings.fullscreen {
+        wb = wb.with_min_dimensions(size).with_max_dimensions(size);
+    }
+    if settings.fullscreen {
+        wb = wb.with_fullscreen(Some(events_loop.get_primary_monitor()));
+    }
+    wb
+}
 
-    rectangle_vertex_buffer: VertexBuffer<Vertex>,
-    rectangle_index_buffer: IndexBuffer<u8>,
+fn context_builder<'a>(settings: &WindowSettings) -> ContextBuilder<'a> {
+    ContextBuilder::new()
+        .with_vsync(settings.vsync == VSync::On)
+        .with_multisampling(settings.multisampling)
 }
 
 impl<T: TGraphicIndex> EngineGraphics<T> {
-    pub fn new(width: f32, height: f32) -> Result<EngineGraphics<T>> {
-        let wb = WindowBuilder::new()
-            .with_dimensions(LogicalSize::new(width as _, height as _))
-            .with_min_dimensions(LogicalSize::new(width as _, height as _))
-            .with_max_dimensions(LogicalSize::new(width as _, height as _));
-        let cb = ContextBuilder::new();
+    pub fn new(settings: &WindowSettings) -> Result<EngineGraphics<T>> {
+        let (width, height) = (settings.width, settings.height);
+        let size = LogicalSize::new(f64::from(width), f64::from(height));
         let events_loop = EventsLoop::new();
-        let display = Display::new(wb, cb, &events_loop).unwrap();
+        let display = Display::new(
+            window_builder(settings, size, &events_loop),
+            context_builder(settings),
+            &events_loop,
+        )?;
 
         let textured_program = Program::from_source(
             &display,
@@ -57,76 +149,451 @@ impl<T: TGraphicIndex> EngineGraphics<T> {
             None,
         )?;
 
-        println!("{:?}", display.get_opengl_version());
-
-        let rectangle_vertex_buffer = VertexBuffer::new(
+        let particle_program = Program::from_source(
             &display,
-            &[
-                Vertex {
-                    dimension_affinity: [0f32, 0f32],
-                },
-                Vertex {
-                    dimension_affinity: [1f32, 0f32],
-                },
-                Vertex {
-                    dimension_affinity: [0f32, 1f32],
-                },
-                Vertex {
-                    dimension_affinity: [1f32, 1f32],
-                },
-            ],
+            include_str!("../assets/particle_shader.vert"),
+            include_str!("../assets/particle_shader.frag"),
+            None,
         )?;
 
-        let rectangle_index_buffer =
-            IndexBuffer::<u8>::new(&display, PrimitiveType::TriangleStrip, &[0, 1, 2, 3])?;
         let text = TextGraphics::new(&display)?;
+        let post_processing = PostProcessing::new(&display, settings.multisampling)?;
+        let mut localization = Localization::new(DEFAULT_LOCALE);
+        localization.load_str(DEFAULT_LOCALE, include_str!("../assets/lang/en.lang"))?;
 
         Ok(EngineGraphics {
             display,
             events_loop,
             textured_program,
             color_program,
+            particle_program,
             frame: None,
+            post_processing,
+            camera: Camera::default(),
             width,
             height,
             text_graphics: text,
+            localization,
 
+            window: settings.clone(),
             graphics: HashMap::new(),
-
-            rectangle_vertex_buffer,
-            rectangle_index_buffer,
+            animations: HashMap::new(),
+            atlas: TextureAtlas::default(),
+            atlas_files: HashMap::new(),
+            pending_graphics: Vec::new(),
+            bitmap_fonts: HashMap::new(),
+            pending_fonts: Vec::new(),
+            primitives: PrimitiveBatch::default(),
         })
     }
 
+    /// Load a PNG file to be drawn with the given size.
+    /// The graphic becomes available after the next `pack_graphics`, which the engine calls at the start of every frame.
+    /// Create the window and its context again with another vsync.
+    /// Textures and shaders are shared with the new context, so everything loaded stays usable.
+    pub fn set_vsync(&mut self, vsync: VSync) -> Result<()> {
+        if self.window.vsync == vsync {
+            return Ok(());
+        }
+        let settings = WindowSettings {
+            vsync,
+            ..self.window.clone()
+        };
+        // A resized window keeps its size
+        let size = self
+            .display
+            .gl_window()
+            .get_inner_size()
+            .unwrap_or_else(|| LogicalSize::new(f64::from(self.width), f64::from(self.height)));
+        self.display.rebuild(
+            window_builder(&settings, size, &self.events_loop),
+            context_builder(&settings),
+            &self.events_loop,
+        )?;
+        self.window = settings;
+        Ok(())
+    }
+
     pub fn load_graphic(&mut self, key: T, file: &str, width: f32, height: f32) -> Result<()> {
+        self.queue_graphic(key, file, width, height, PendingKind::Image)
+    }
+
+    /// Load a sprite sheet, so that regions and animations can be loaded from it.
+    /// This is only needed if the sheet needs a different `SheetTransparency` than `ImageAlpha`,
+    /// otherwise the sheet is loaded by the first region or animation that uses it.
+    /// Fails if the sheet was already loaded with another transparency, so load it before its regions.
+    pub fn load_sprite_sheet(&mut self, file: &str, transparency: SheetTransparency) -> Result<()> {
+        self.load_atlas_image(file, Some(transparency))?;
+        Ok(())
+    }
+
+    /// Load a rectangle of a sprite sheet as its own graphic, to be drawn with the given size.
+    pub fn load_graphic_region(
+        &mut self,
+        key: T,
+        file: &str,
+        region: SpriteRegion,
+        width: f32,
+        height: f32,
+    ) -> Result<()> {
+        self.queue_graphic(key, file, width, height, PendingKind::Region(region))
+    }
+
+    /// Load the frames of an animation from a sprite sheet, every frame is drawn with the given size.
+    /// Draw it with `draw_animation`.
+    pub fn load_animation(
+        &mut self,
+        key: T,
+        file: &str,
+        animation: &Animation,
+        width: f32,
+        height: f32,
+    ) -> Result<()> {
+        let regions = animation.frames.iter().map(|f| f.region).collect();
+        self.queue_graphic(key, file, width, height, PendingKind::Animation(regions))
+    }
+
+    /// Load a bitmap font from a BMFont descriptor in the text format.
+    /// The glyph sheets are loaded from the paths in the descriptor, relative to the descriptor file.
+    /// Like graphics, the font becomes available after the next `pack_graphics`.
+    pub fn load_bitmap_font(&mut self, name: &str, descriptor_file: &str) -> Result<()> {
+        let source = fs::read_to_string(descriptor_file)?;
+        let descriptor = BitmapFontDescriptor::parse(&source)?;
+        let directory = Path::new(descriptor_file)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let mut pages = Vec::with_capacity(descriptor.pages.len());
+        for page in &descriptor.pages {
+            let file = directory.join(page);
+            pages.push(
+                self.load_atlas_image(
+                    &file.to_string_lossy(),
+                    Some(SheetTransparency::ImageAlpha),
+                )?,
+            );
+        }
+        self.pending_fonts.push(PendingFont {
+            name: name.to_owned(),
+            descriptor,
+            pages,
+        });
+        Ok(())
+    }
+
+    fn queue_graphic(
+        &mut self,
+        key: T,
+        file: &str,
+        width: f32,
+        height: f32,
+        kind: PendingKind,
+    ) -> Result<()> {
+        let image = self.load_atlas_image(file, None)?;
+        self.pending_graphics.push(PendingGraphic {
+            key,
+            image,
+            width,
+            height,
+            kind,
+        });
+        Ok(())
+    }
+
+    /// Load a file into the atlas, or reuse it if it was loaded before.
+    /// Without a `transparency`, a new file uses `ImageAlpha` and a loaded one is used as it is.
+    fn load_atlas_image(
+        &mut self,
+        file: &str,
+        transparency: Option<SheetTransparency>,
+    ) -> Result<AtlasImage> {
+        if let Some((loaded_transparency, image)) = self.atlas_files.get(file) {
+            return match transparency {
+                Some(transparency) if transparency != *loaded_transparency => {
+                    Err(Error::sprite_sheet(&format!(
+                        "{} was loaded with {:?} before, it can not be loaded with {:?} as well",
+                        file, loaded_transparency, transparency
+                    )))
+                }
+                _ => Ok(image.clone()),
+            };
+        }
+        let transparency = transparency.unwrap_or(SheetTransparency::ImageAlpha);
         let bytes = {
             let mut file = File::open(file)?;
             let mut vec = Vec::new();
             file.read_to_end(&mut vec)?;
             vec
         };
-        let param = DrawHelper::new(self, width, height, &bytes)?;
-        self.graphics.insert(key, param);
+        let mut image = image::load(Cursor::new(bytes), image::ImageFormat::Png)?.to_rgba();
+        if transparency == SheetTransparency::BlackToAlpha {
+            for pixel in image.pixels_mut() {
+                let alpha = pixel[0].max(pixel[1]).max(pixel[2]);
+                if alpha > 0 {
+                    for channel in 0..3 {
+                        pixel[channel] = (pixel[channel] as u32 * 255 / alpha as u32) as u8;
+                    }
+                }
+                pixel[3] = ((pixel[3] as u32 * alpha as u32) / 255) as u8;
+            }
+        }
+        let image = AtlasImage::Pending(self.atlas.add(image));
+        self.atlas_files
+            .insert(file.to_owned(), (transparency, image.clone()));
+        Ok(image)
+    }
+
+    /// Pack all graphics that were loaded since the last call into atlas textures.
+    pub fn pack_graphics(&mut self) -> Result<()> {
+        if self.pending_graphics.is_empty() && self.pending_fonts.is_empty() {
+            return Ok(());
+        }
+        let atlas = mem::take(&mut self.atlas);
+        let entries = atlas.build(&self.display)?;
+        for (_, image) in self.atlas_files.values_mut() {
+            if let AtlasImage::Pending(index) = *image {
+                *image = AtlasImage::Packed(entries[index].clone());
+            }
+        }
+        let entry_of = |image: &AtlasImage| match image {
+            AtlasImage::Pending(index) => entries[*index].clone(),
+            AtlasImage::Packed(entry) => entry.clone(),
+        };
+
+        for pending in mem::take(&mut self.pending_graphics) {
+            let entry = &entry_of(&pending.image);
+            let (width, height) = (pending.width, pending.height);
+            let new_helper = |graphics: &Self, region: SpriteRegion| {
+                DrawHelper::new(
+                    graphics,
+                    width,
+                    height,
+                    entry.texture.clone(),
+                    entry.sub_region(region.x, region.y, region.width, region.height),
+                )
+            };
+            match pending.kind {
+                PendingKind::Image => {
+                    let region = SpriteRegion::new(0, 0, entry.width, entry.height);
+                    let helper = new_helper(self, region)?;
+                    self.graphics.insert(pending.key, helper);
+                }
+                PendingKind::Region(region) => {
+                    let helper = new_helper(self, region)?;
+                    self.graphics.insert(pending.key, helper);
+                }
+                PendingKind::Animation(regions) => {
+                    let mut frames = Vec::with_capacity(regions.len());
+                    for region in regions {
+                        frames.push(new_helper(self, region)?);
+                    }
+                    self.animations.insert(pending.key, frames);
+                }
+            }
+        }
+
+        for pending in mem::take(&mut self.pending_fonts) {
+            let mut glyphs = HashMap::new();
+            for (&character, glyph) in &pending.descriptor.glyphs {
+                let region = glyph.region;
+                if region.width == 0 || region.height == 0 {
+                    continue;
+                }
+                let entry = &entry_of(&pending.pages[glyph.page]);
+                let helper = DrawHelper::new(
+                    self,
+                    region.width as f32,
+                    region.height as f32,
+                    entry.texture.clone(),
+                    entry.sub_region(region.x, region.y, region.width, region.height),
+                )?;
+                glyphs.insert(character, helper);
+            }
+            self.bitmap_fonts.insert(
+                pending.name,
+                BitmapFont {
+                    descriptor: pending.descriptor,
+                    glyphs,
+                },
+            );
+        }
         Ok(())
     }
 
-    // pub fn get_graphic(&self, key: T) -> Option<&DrawHelper> {
-    // self.graphics.get(&key)
-    // }
-    //
+    /// Compile a fragment shader for the post-processing chain, see `PostProcessing`
+    pub fn register_post_shader(&mut self, name: &str, fragment_shader: &str) -> Result<()> {
+        self.post_processing
+            .register_shader(&self.display, name, fragment_shader)
+    }
+
+    /// Read back the last frame that was shown in the window
+    pub fn read_frame(&self) -> Result<RgbaImage> {
+        screenshot::image_from_raw(self.display.read_front_buffer::<RawImage2d<u8>>())
+    }
+
+    /// Read back the contents of a texture, e.g. an offscreen render target
+    pub fn read_texture(&self, texture: &Texture2d) -> Result<RgbaImage> {
+        screenshot::image_from_raw(texture.read::<RawImage2d<u8>>())
+    }
+
+    /// Save the last frame that was shown in the window as a timestamped PNG in `directory`
+    pub fn save_screenshot<P: AsRef<Path>>(&self, directory: P) -> Result<PathBuf> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+        let path = screenshot::screenshot_path(directory);
+        self.read_frame()?.save(&path)?;
+        Ok(path)
+    }
+
+    pub fn get_graphic(&self, key: &T) -> Option<&DrawHelper> {
+        self.graphics.get(key)
+    }
+
     // pub fn draw_directly(&mut self, wrapper: &EntityWrapper<T>) -> Result<()> {
     // if let Some(helper) = wrapper.drawable {
     //
     // }
     // }
     pub fn draw_text_at(&mut self, string: String, x: f32, y: f32, color: Color) -> Result<()> {
+        self.draw_text(&string, x, y, &TextStyle::default().with_color(color))
+    }
+    /// Draw text with its top at `y`, aligned to `x` as set in the style
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, style: &TextStyle) -> Result<()> {
+        let (x, y) = self.camera.world_to_screen(x, y);
+        self.draw_screen_text(text, x, y, style)
+    }
+    /// Draw text in screen coordinates, the camera is not applied
+    pub fn draw_screen_text(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        style: &TextStyle,
+    ) -> Result<()> {
+        self.flush_primitives()?;
+        if let Some(frame) = &mut self.frame {
+            self.text_graphics
+                .draw_at(frame, text, (self.width, self.height), (x, y), style)?;
+        }
+        Ok(())
+    }
+    /// Draw the string with the given key in the current language, see `Localization`
+    pub fn draw_localized_text(
+        &mut self,
+        key: &str,
+        arguments: &Arguments,
+        x: f32,
+        y: f32,
+        style: &TextStyle,
+    ) -> Result<()> {
+        let text = self.localization.format(key, arguments);
+        self.draw_text(&text, x, y, style)
+    }
+    /// Lay out text once, so it can be drawn every frame without any lookups
+    pub fn create_text(&mut self, text: &str, style: &TextStyle) -> Result<RetainedText> {
+        self.text_graphics.create_text(text, style)
+    }
+    pub fn update_text(&mut self, retained: &mut RetainedText, text: &str) -> Result<()> {
+        self.text_graphics.update_text(retained, text)
+    }
+    pub fn draw_retained_text(&mut self, retained: &RetainedText, x: f32, y: f32) -> Result<()> {
+        let (x, y) = self.camera.world_to_screen(x, y);
+        self.draw_screen_retained_text(retained, x, y)
+    }
+    /// Draw a retained text in screen coordinates, the camera is not applied
+    pub fn draw_screen_retained_text(
+        &mut self,
+        retained: &RetainedText,
+        x: f32,
+        y: f32,
+    ) -> Result<()> {
+        self.flush_primitives()?;
         if let Some(frame) = &mut self.frame {
             self.text_graphics
-                .draw_at(frame, string, (self.width, self.height), (x, y), color)?;
+                .draw_retained(frame, retained, (self.width, self.height), (x, y));
+        }
+        Ok(())
+    }
+    /// The width and height of the text in pixels
+    pub fn measure_text(&mut self, text: &str, style: &TextStyle) -> Result<(f32, f32)> {
+        self.text_graphics.measure(text, style)
+    }
+    /// Draw text with a bitmap font, with the top of the first line at `y`
+    pub fn draw_bitmap_text(
+        &mut self,
+        font: &str,
+        text: &str,
+        x: f32,
+        y: f32,
+        style: &BitmapTextStyle,
+    ) -> Result<()> {
+        let (x, y) = self.camera.world_to_screen(x, y);
+        self.draw_screen_bitmap_text(font, text, x, y, style)
+    }
+    /// Draw text with a bitmap font in screen coordinates, the camera is not applied.
+    /// Like a graphic, a font that is not loaded (yet) is not drawn.
+    pub fn draw_screen_bitmap_text(
+        &mut self,
+        font: &str,
+        text: &str,
+        x: f32,
+        y: f32,
+        style: &BitmapTextStyle,
+    ) -> Result<()> {
+        self.flush_primitives()?;
+        let font = match self.bitmap_fonts.get(font) {
+            Some(font) => font,
+            None => return Ok(()),
+        };
+        let effects = SpriteEffects::tinted(style.color);
+        if let Some(frame) = &mut self.frame {
+            for (character, left, top) in font.descriptor.layout(text, style) {
+                if let Some(helper) = font.glyphs.get(&character) {
+                    let center = (
+                        x + left + helper.width * style.scale / 2f32,
+                        y + top + helper.height * style.scale / 2f32,
+                    );
+                    EngineGraphics::<T>::draw_at(
+                        &self.textured_program,
+                        frame,
+                        helper,
+                        (self.width, self.height),
+                        center,
+                        (0f32, style.scale),
+                        &effects,
+                    )?;
+                }
+            }
         }
         Ok(())
     }
+    /// The width and height of the text in pixels, if it were drawn with the given bitmap font.
+    /// Text in a font that is not loaded (yet) is not drawn, so it has no size.
+    pub fn measure_bitmap_text(
+        &self,
+        font: &str,
+        text: &str,
+        style: &BitmapTextStyle,
+    ) -> Result<(f32, f32)> {
+        Ok(self
+            .bitmap_fonts
+            .get(font)
+            .map(|font| font.descriptor.measure(text, style))
+            .unwrap_or((0f32, 0f32)))
+    }
     pub fn draw(&mut self, key: T, x: f32, y: f32, rotation: f32, scale: f32) -> Result<()> {
+        self.draw_with(key, x, y, rotation, scale, &SpriteEffects::default())
+    }
+    /// Draw a graphic with a tint, flash colour and/or opacity
+    pub fn draw_with(
+        &mut self,
+        key: T,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        scale: f32,
+        effects: &SpriteEffects,
+    ) -> Result<()> {
+        self.flush_primitives()?;
         if let Some(ref helper) = self.graphics.get(&key) {
             if let Some(frame) = &mut self.frame {
                 EngineGraphics::<T>::draw_at(
@@ -134,9 +601,61 @@ impl<T: TGraphicIndex> EngineGraphics<T> {
                     frame,
                     helper,
                     (self.width, self.height),
-                    (x, y),
-                    rotation,
-                    scale,
+                    self.camera.world_to_screen(x, y),
+                    (rotation, scale),
+                    effects,
+                )?;
+            }
+        }
+        Ok(())
+    }
+    /// Draw the current frame of an animation, as indicated by the cursor
+    pub fn draw_animation(
+        &mut self,
+        key: T,
+        cursor: &AnimationCursor,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        scale: f32,
+    ) -> Result<()> {
+        self.draw_animation_with(
+            key,
+            cursor,
+            x,
+            y,
+            rotation,
+            scale,
+            &SpriteEffects::default(),
+        )
+    }
+    /// Draw the current frame of an animation with a tint, flash colour and/or opacity
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_animation_with(
+        &mut self,
+        key: T,
+        cursor: &AnimationCursor,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        scale: f32,
+        effects: &SpriteEffects,
+    ) -> Result<()> {
+        self.flush_primitives()?;
+        let helper = self
+            .animations
+            .get(&key)
+            .and_then(|frames| frames.get(cursor.frame()));
+        if let Some(helper) = helper {
+            if let Some(frame) = &mut self.frame {
+                EngineGraphics::<T>::draw_at(
+                    &self.textured_program,
+                    frame,
+                    helper,
+                    (self.width, self.height),
+                    self.camera.world_to_screen(x, y),
+                    (rotation, scale),
+                    effects,
                 )?;
             }
         }
@@ -144,12 +663,12 @@ impl<T: TGraphicIndex> EngineGraphics<T> {
     }
     fn draw_at(
         textured_program: &Program,
-        frame: &mut Frame,
+        frame: &mut RenderTarget,
         helper: &DrawHelper,
         (width, height): (f32, f32),
         (x, y): (f32, f32),
-        rotation: f32,
-        scale: f32,
+        (rotation, scale): (f32, f32),
+        effects: &SpriteEffects,
     ) -> Result<()> {
         let matrix = [
             [scale * rotation.cos(), scale * rotation.sin(), 0.0],
@@ -157,8 +676,11 @@ impl<T: TGraphicIndex> EngineGraphics<T> {
             [x, y, 1.0f32],
         ];
         let uniform = UniformsStorage::new("matrix", matrix);
-        let uniform = uniform.add("tex", &helper.texture);
+        let uniform = uniform.add("tex", &*helper.texture);
         let uniform = uniform.add("screen_size", [width, height]);
+        let uniform = uniform.add("tint", effects.tint);
+        let uniform = uniform.add("flash", effects.flash);
+        let uniform = uniform.add("opacity", effects.opacity);
 
         let draw_parameters = DrawParameters {
             blend: Blend::alpha_blending(),
@@ -175,6 +697,86 @@ impl<T: TGraphicIndex> EngineGraphics<T> {
         Ok(())
     }
 
+    /// Repeat a graphic over the whole screen, shifted by `offset` pixels.
+    /// This is in screen coordinates, the camera is not applied.
+    pub fn draw_tiled(
+        &mut self,
+        key: &T,
+        offset: (f32, f32),
+        effects: &SpriteEffects,
+    ) -> Result<()> {
+        self.flush_primitives()?;
+        if let Some(ref helper) = self.graphics.get(key) {
+            if helper.width <= 0f32 || helper.height <= 0f32 {
+                return Ok(());
+            }
+            if let Some(frame) = &mut self.frame {
+                let start_x = offset.0.rem_euclid(helper.width) - helper.width;
+                let start_y = offset.1.rem_euclid(helper.height) - helper.height;
+                let mut y = start_y;
+                while y < self.height {
+                    let mut x = start_x;
+                    while x < self.width {
+                        EngineGraphics::<T>::draw_at(
+                            &self.textured_program,
+                            frame,
+                            helper,
+                            (self.width, self.height),
+                            (x + helper.width / 2f32, y + helper.height / 2f32),
+                            (0f32, 1f32),
+                            effects,
+                        )?;
+                        x += helper.width;
+                    }
+                    y += helper.height;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn draw_nine_slice(
+        &mut self,
+        slice: &NineSlice<T>,
+        (x, y, width, height): (f32, f32, f32, f32),
+        color: Color,
+    ) -> Result<()> {
+        let (x, y) = self.camera.world_to_screen(x, y);
+        self.draw_screen_nine_slice(slice, (x, y, width, height), color)
+    }
+
+    /// Stretch a nine-slice graphic over a rectangle in screen coordinates, multiplied with `color`
+    pub fn draw_screen_nine_slice(
+        &mut self,
+        slice: &NineSlice<T>,
+        rectangle: (f32, f32, f32, f32),
+        color: Color,
+    ) -> Result<()> {
+        self.flush_primitives()?;
+        let helper = match self.graphics.get(&slice.graphic) {
+            Some(helper) => helper,
+            None => return Ok(()),
+        };
+        let vertices = slice_vertices(helper, slice.border, rectangle, color);
+        let vertex_buffer = VertexBuffer::new(&self.display, &vertices)?;
+        if let Some(frame) = &mut self.frame {
+            let uniform = UniformsStorage::new("tex", &*helper.texture);
+            let uniform = uniform.add("screen_size", [self.width, self.height]);
+            let draw_parameters = DrawParameters {
+                blend: Blend::alpha_blending(),
+                ..DrawParameters::default()
+            };
+            frame.draw(
+                &vertex_buffer,
+                NoIndices(PrimitiveType::TrianglesList),
+                &self.particle_program,
+                &uniform,
+                &draw_parameters,
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn draw_rectangle(
         &mut self,
         x: f32,
@@ -183,17 +785,134 @@ impl<T: TGraphicIndex> EngineGraphics<T> {
         height: f32,
         color: Color,
     ) -> Result<()> {
+        let (x, y) = self.camera.world_to_screen(x, y);
+        self.draw_screen_rectangle(x, y, width, height, color)
+    }
+
+    /// Draw a rectangle in screen coordinates, the camera is not applied
+    pub fn draw_screen_rectangle(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: Color,
+    ) -> Result<()> {
+        self.primitives.rectangle(x, y, width, height, color);
+        Ok(())
+    }
+
+    /// The outline is drawn on the inside of the rectangle
+    pub fn draw_rectangle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        thickness: f32,
+        color: Color,
+    ) -> Result<()> {
+        let (x, y) = self.camera.world_to_screen(x, y);
+        self.primitives
+            .rectangle_outline(x, y, width, height, thickness, color);
+        Ok(())
+    }
+
+    pub fn draw_line(
+        &mut self,
+        from: (f32, f32),
+        to: (f32, f32),
+        thickness: f32,
+        color: Color,
+    ) -> Result<()> {
+        let from = self.camera.world_to_screen(from.0, from.1);
+        let to = self.camera.world_to_screen(to.0, to.1);
+        self.primitives.line(from, to, thickness, color);
+        Ok(())
+    }
+
+    pub fn draw_circle(&mut self, x: f32, y: f32, radius: f32, color: Color) -> Result<()> {
+        let center = self.camera.world_to_screen(x, y);
+        self.primitives.circle(center, radius, color);
+        Ok(())
+    }
+
+    /// The outline is centered on the edge of the circle
+    pub fn draw_circle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        thickness: f32,
+        color: Color,
+    ) -> Result<()> {
+        let center = self.camera.world_to_screen(x, y);
+        self.primitives
+            .arc(center, radius, (0f32, 2f32 * PI), thickness, color);
+        Ok(())
+    }
+
+    /// Draw part of a circle outline, the angles are in radians with 0 being to the right
+    pub fn draw_arc(
+        &mut self,
+        (x, y): (f32, f32),
+        radius: f32,
+        angles: (f32, f32),
+        thickness: f32,
+        color: Color,
+    ) -> Result<()> {
+        let center = self.camera.world_to_screen(x, y);
+        self.primitives
+            .arc(center, radius, angles, thickness, color);
+        Ok(())
+    }
+
+    /// Fill a convex polygon
+    pub fn draw_polygon(&mut self, points: &[(f32, f32)], color: Color) -> Result<()> {
+        let camera = self.camera;
+        let points = points
+            .iter()
+            .map(|&(x, y)| camera.world_to_screen(x, y))
+            .collect::<Vec<_>>();
+        self.primitives.polygon(&points, color);
+        Ok(())
+    }
+
+    pub fn draw_polygon_outline(
+        &mut self,
+        points: &[(f32, f32)],
+        thickness: f32,
+        color: Color,
+    ) -> Result<()> {
+        let camera = self.camera;
+        let points = points
+            .iter()
+            .map(|&(x, y)| camera.world_to_screen(x, y))
+            .collect::<Vec<_>>();
+        self.primitives.polygon_outline(&points, thickness, color);
+        Ok(())
+    }
+
+    /// Draw all primitives that were queued since the last flush.
+    /// Everything that is not a primitive calls this before drawing, so the drawing order is kept.
+    pub fn flush_primitives(&mut self) -> Result<()> {
+        if self.primitives.is_empty() {
+            return Ok(());
+        }
+        let vertex_buffer = VertexBuffer::new(&self.display, self.primitives.vertices())?;
+        self.primitives.clear();
         if let Some(frame) = &mut self.frame {
-            let uniform = UniformsStorage::new("offset", [x, y]);
-            let uniform = uniform.add("dimensions", [width, height]);
-            let uniform = uniform.add("color", color);
-            let uniform = uniform.add("screen_size", [self.width as f32, self.height as f32]);
+            let uniform = UniformsStorage::new("screen_size", [self.width, self.height]);
+            let draw_parameters = DrawParameters {
+                blend: Blend::alpha_blending(),
+                ..DrawParameters::default()
+            };
             frame.draw(
-                &self.rectangle_vertex_buffer,
-                &self.rectangle_index_buffer,
+                &vertex_buffer,
+                NoIndices(PrimitiveType::TrianglesList),
                 &self.color_program,
                 &uniform,
-                &DrawParameters::default(),
+                &draw_parameters,
             )?;
         }
         Ok(())
@@ -6,7 +6,7 @@ pub use self::entity_trait::EntityTrait;
 pub use self::state::EntityState;
 pub use self::wrapper::EntityWrapper;
 
-use super::TGraphicIndex;
+use super::{MusicCommand, ParticleEmitter, PlaySound, TGraphicIndex, VSync};
 
 #[derive(PartialEq, Eq, Hash, Debug)]
 #[allow(dead_code)]
@@ -19,6 +19,11 @@ pub enum EntityEvent<T: TGraphicIndex> {
     #[allow(dead_code)]
     ClearAllEntities,
     SpawnEntity(Box<dyn EntityTrait<T>>),
+    SpawnEmitter(ParticleEmitter<T>),
+    PlaySound(PlaySound),
+    Music(MusicCommand),
+    /// Switch the vsync of the window, see `Engine::set_vsync`
+    SetVSync(VSync),
 }
 
 #[derive(Default)]
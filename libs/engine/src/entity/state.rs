@@ -1,6 +1,8 @@
 use super::Hitbox;
 
 pub struct EntityState {
+    /// A unique number for this entity, assigned by the engine when the entity is registered
+    pub id: u64,
     pub active: bool,
     pub hitbox: Hitbox,
     pub x: f32,
@@ -10,6 +12,7 @@ pub struct EntityState {
 impl Default for EntityState {
     fn default() -> EntityState {
         EntityState {
+            id: 0,
             active: true,
             hitbox: Default::default(),
             x: 0f32,
@@ -9,10 +9,12 @@ pub struct EntityWrapper<T: TGraphicIndex> {
 }
 
 impl<T: TGraphicIndex> EntityWrapper<T> {
-    pub fn new(entity: Box<dyn EntityTrait<T>>, engine: &Engine<T>) -> EntityWrapper<T> {
+    pub fn new(entity: Box<dyn EntityTrait<T>>, engine: &Engine<T>, id: u64) -> EntityWrapper<T> {
         let mut entity = entity;
+        let mut state = entity.get_initial_state(engine);
+        state.id = id;
         EntityWrapper {
-            state: entity.get_initial_state(engine),
+            state,
             name: entity.identifying_string(),
             entity,
         }
@@ -1,3 +1,5 @@
+use glium::backend::glutin::DisplayCreationError;
+use glium::framebuffer::ValidationError;
 use glium::index::BufferCreationError as IndexCreationError;
 use glium::texture::TextureCreationError;
 use glium::vertex::BufferCreationError as VertexCreationError;
@@ -17,17 +19,85 @@ pub struct Error {
 #[derive(Debug)]
 pub enum ErrorEnum {
     NoContext,
+    DisplayCreationError(DisplayCreationError),
     ProgramCreationError(ProgramCreationError),
     TextureCreationError(TextureCreationError),
     IndexCreationError(IndexCreationError),
     VertexCreationError(VertexCreationError),
     DrawError(DrawError),
     SwapBuffersError(SwapBuffersError),
+    ValidationError(ValidationError),
     // NoWindow,
     // NoInnerPixelSize,
     // ComponentNotFound(u64),
     ImageError(ImageError),
     IOError(ioError),
+    FontNotFound(String),
+    BitmapFontError(String),
+    SpriteSheetError(String),
+    LocalizationError(String),
+    InputMapError(String),
+    AudioError(String),
+    ReadbackError(String),
+    EngineSettingsError(String),
+}
+
+impl Error {
+    pub fn font_not_found(name: &str) -> Error {
+        Error {
+            description: format!("Could not find font {}", name),
+            error_type: ErrorEnum::FontNotFound(name.to_owned()),
+        }
+    }
+
+    pub fn bitmap_font(message: &str) -> Error {
+        Error {
+            description: format!("Invalid bitmap font: {}", message),
+            error_type: ErrorEnum::BitmapFontError(message.to_owned()),
+        }
+    }
+
+    pub fn sprite_sheet(message: &str) -> Error {
+        Error {
+            description: format!("Invalid sprite sheet: {}", message),
+            error_type: ErrorEnum::SpriteSheetError(message.to_owned()),
+        }
+    }
+
+    pub fn localization(message: &str) -> Error {
+        Error {
+            description: format!("Invalid string table: {}", message),
+            error_type: ErrorEnum::LocalizationError(message.to_owned()),
+        }
+    }
+
+    pub fn input_map(message: &str) -> Error {
+        Error {
+            description: format!("Invalid input bindings: {}", message),
+            error_type: ErrorEnum::InputMapError(message.to_owned()),
+        }
+    }
+
+    pub fn readback(message: &str) -> Error {
+        Error {
+            description: format!("Invalid readback: {}", message),
+            error_type: ErrorEnum::ReadbackError(message.to_owned()),
+        }
+    }
+
+    pub fn audio(message: &str) -> Error {
+        Error {
+            description: format!("Audio error: {}", message),
+            error_type: ErrorEnum::AudioError(message.to_owned()),
+        }
+    }
+
+    pub fn engine_settings(message: &str) -> Error {
+        Error {
+            description: format!("Invalid engine settings: {}", message),
+            error_type: ErrorEnum::EngineSettingsError(message.to_owned()),
+        }
+    }
 }
 
 // impl Error {
@@ -51,6 +121,15 @@ pub enum ErrorEnum {
 //
 //
 
+impl From<DisplayCreationError> for Error {
+    fn from(error: DisplayCreationError) -> Self {
+        Error {
+            description: format!("Display creation error: {:?}", error),
+            error_type: ErrorEnum::DisplayCreationError(error),
+        }
+    }
+}
+
 impl From<ProgramCreationError> for Error {
     fn from(error: ProgramCreationError) -> Self {
         Error {
@@ -105,6 +184,15 @@ impl From<SwapBuffersError> for Error {
     }
 }
 
+impl From<ValidationError> for Error {
+    fn from(error: ValidationError) -> Self {
+        Error {
+            description: format!("Framebuffer validation error: {:?}", error),
+            error_type: ErrorEnum::ValidationError(error),
+        }
+    }
+}
+
 impl From<ImageError> for Error {
     fn from(error: ImageError) -> Self {
         Error {
@@ -0,0 +1,251 @@
+use super::Clock;
+
+/// The refresh rate adaptive vsync is limited to, as winit can not tell the rate of the monitor
+pub const DEFAULT_REFRESH_RATE: f32 = 60f32;
+/// The longest frame time the entities see, in milliseconds
+pub const DEFAULT_MAX_DELTA_TIME: f32 = 100f32;
+/// How long before the next frame the limiter stops sleeping and starts spinning, in milliseconds
+pub const DEFAULT_SPIN_THRESHOLD: f32 = 2f32;
+
+/// How the buffer swap waits for the monitor.
+/// The swap interval is part of the OpenGL context, so `Engine::set_vsync` creates the context again to change it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VSync {
+    /// Show frames as soon as they are drawn, which can tear
+    Off,
+    /// Wait for the monitor to refresh, which halves the frame rate when a frame is a little late
+    On,
+    /// Wait for the refresh only when the frame is on time.
+    /// The driver can not be asked for this, so frames are limited to `FramePacing::refresh_rate` instead
+    /// and late frames are shown immediately.
+    Adaptive,
+}
+
+/// Limits the frame rate, and the frame time the entities see
+#[derive(Clone, Debug)]
+pub struct FramePacing {
+    /// Frames per second, no limit when `None`
+    pub target_frame_rate: Option<f32>,
+    vsync: VSync,
+    /// The frame rate of `VSync::Adaptive`
+    pub refresh_rate: f32,
+    /// A breakpoint or dragging the window would otherwise move every entity by seconds at once
+    pub max_delta_time: f32,
+    /// Sleeping is not precise, so the last part of the wait is spent spinning.
+    /// Systems with a coarse timer need a higher threshold.
+    pub spin_threshold: f32,
+    /// When the current frame was allowed to start, which can be before the previous frame ended.
+    /// In milliseconds of the engine's clock, like `last_frame_end`.
+    frame_start: Option<f64>,
+    last_frame_end: Option<f64>,
+    average_frame_time: f32,
+}
+
+impl FramePacing {
+    /// The vsync of the window, see `Engine::set_vsync` to change it
+    pub fn new(vsync: VSync) -> FramePacing {
+        FramePacing {
+            target_frame_rate: None,
+            vsync,
+            refresh_rate: DEFAULT_REFRESH_RATE,
+            max_delta_time: DEFAULT_MAX_DELTA_TIME,
+            spin_threshold: DEFAULT_SPIN_THRESHOLD,
+            frame_start: None,
+            last_frame_end: None,
+            average_frame_time: 0f32,
+        }
+    }
+
+    pub fn vsync(&self) -> VSync {
+        self.vsync
+    }
+
+    /// Only changes the limiter, `Engine::set_vsync` changes the window as well
+    pub fn set_vsync(&mut self, vsync: VSync) {
+        self.vsync = vsync;
+        self.reset();
+    }
+
+    /// The shortest time a frame may take in milliseconds, from the target frame rate and adaptive vsync
+    pub fn min_frame_time(&self) -> Option<f64> {
+        let refresh_rate = match self.vsync {
+            VSync::Adaptive => Some(self.refresh_rate),
+            _ => None,
+        };
+        let frame_rate = match (self.target_frame_rate, refresh_rate) {
+            (Some(target), Some(refresh)) => Some(target.min(refresh)),
+            (target, refresh) => target.or(refresh),
+        };
+        frame_rate
+            .filter(|rate| *rate > 0f32)
+            .map(|rate| 1000f64 / f64::from(rate))
+    }
+
+    pub fn clamp_delta_time(&self, elapsed: f32) -> f32 {
+        elapsed.max(0f32).min(self.max_delta_time)
+    }
+
+    /// Wait on the engine's clock until the next frame may start. Called once at the end of every frame.
+    /// A `MockClock` does not wait, it moves to the start of the next frame.
+    pub fn end_frame(&mut self, clock: &dyn Clock) {
+        let now = clock.now();
+        let frame_start = self.frame_start.unwrap_or(now);
+        let next_frame = match self.min_frame_time() {
+            Some(frame_time) => {
+                let deadline = frame_start + frame_time;
+                if deadline > now {
+                    clock.wait_until(deadline, f64::from(self.spin_threshold));
+                    deadline
+                } else if now - deadline < frame_time {
+                    // Slightly late, the next frame makes up for it so the average rate stays on target
+                    deadline
+                } else {
+                    // Far behind, catching up would rush several frames
+                    now
+                }
+            }
+            None => now,
+        };
+        let frame_end = clock.now();
+        if let Some(last_frame_end) = self.last_frame_end {
+            let frame_time = (frame_end - last_frame_end).max(0f64) as f32;
+            self.average_frame_time = if self.average_frame_time > 0f32 {
+                self.average_frame_time * 0.9 + frame_time * 0.1
+            } else {
+                frame_time
+            };
+        }
+        self.last_frame_end = Some(frame_end);
+        self.frame_start = Some(next_frame);
+    }
+
+    /// Forget when the last frame ended, e.g. when the engine changes its clock
+    pub fn reset(&mut self) {
+        self.frame_start = None;
+        self.last_frame_end = None;
+    }
+
+    /// The frame time in milliseconds, averaged over the last frames
+    pub fn average_frame_time(&self) -> f32 {
+        self.average_frame_time
+    }
+
+    pub fn frame_rate(&self) -> f32 {
+        if self.average_frame_time > 0f32 {
+            1000f32 / self.average_frame_time
+        } else {
+            0f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FramePacing, VSync};
+    use {Clock, MockClock};
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_lower_of_frame_rate_and_refresh_rate_limits_the_frames() {
+        let mut pacing = FramePacing::new(VSync::On);
+        assert_eq!(pacing.min_frame_time(), None);
+        pacing.target_frame_rate = Some(50f32);
+        assert_close(pacing.min_frame_time().unwrap(), 20f64);
+
+        let mut pacing = FramePacing::new(VSync::Adaptive);
+        pacing.refresh_rate = 100f32;
+        assert_close(pacing.min_frame_time().unwrap(), 10f64);
+        pacing.target_frame_rate = Some(50f32);
+        assert_close(pacing.min_frame_time().unwrap(), 20f64);
+    }
+
+    #[test]
+    fn switching_to_adaptive_vsync_limits_the_frames() {
+        let clock = MockClock::new(0f64);
+        let mut pacing = FramePacing::new(VSync::On);
+        pacing.refresh_rate = 100f32;
+        pacing.end_frame(&clock);
+        pacing.set_vsync(VSync::Adaptive);
+        assert_eq!(pacing.vsync(), VSync::Adaptive);
+        assert_close(pacing.min_frame_time().unwrap(), 10f64);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 10f64);
+
+        pacing.set_vsync(VSync::Off);
+        assert_eq!(pacing.min_frame_time(), None);
+    }
+
+    #[test]
+    fn frames_wait_for_the_frame_time() {
+        let clock = MockClock::new(0f64);
+        let mut pacing = FramePacing::new(VSync::Off);
+        pacing.target_frame_rate = Some(50f32);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 20f64);
+
+        clock.advance(5f64);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 40f64);
+        assert_close(f64::from(pacing.average_frame_time()), 20f64);
+        assert_close(f64::from(pacing.frame_rate()), 50f64);
+    }
+
+    #[test]
+    fn late_frames_are_made_up_for_by_the_next_frame() {
+        let clock = MockClock::new(0f64);
+        let mut pacing = FramePacing::new(VSync::Off);
+        pacing.target_frame_rate = Some(50f32);
+        pacing.end_frame(&clock);
+        clock.advance(25f64);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 45f64);
+
+        // The frame after the late one ends on the schedule again
+        clock.advance(5f64);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 60f64);
+    }
+
+    #[test]
+    fn frames_far_behind_start_a_new_schedule() {
+        let clock = MockClock::new(0f64);
+        let mut pacing = FramePacing::new(VSync::Off);
+        pacing.target_frame_rate = Some(50f32);
+        pacing.end_frame(&clock);
+        clock.advance(100f64);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 120f64);
+
+        clock.advance(5f64);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 140f64);
+    }
+
+    #[test]
+    fn frames_without_a_limit_do_not_wait() {
+        let clock = MockClock::new(0f64);
+        let mut pacing = FramePacing::new(VSync::On);
+        pacing.end_frame(&clock);
+        clock.advance(4f64);
+        pacing.end_frame(&clock);
+        assert_close(clock.now(), 4f64);
+        assert_close(f64::from(pacing.average_frame_time()), 4f64);
+    }
+
+    #[test]
+    fn delta_times_are_clamped() {
+        let mut pacing = FramePacing::new(VSync::On);
+        pacing.max_delta_time = 50f32;
+        assert_eq!(pacing.clamp_delta_time(16f32), 16f32);
+        assert_eq!(pacing.clamp_delta_time(500f32), 50f32);
+        assert_eq!(pacing.clamp_delta_time(-1f32), 0f32);
+    }
+}
@@ -1,12 +1,23 @@
-use super::KeyboardState;
-use rand::{prelude::ThreadRng, Rng};
+use super::{InputMap, InputQueue, KeyboardState, LifecycleEvent, MouseState, TextInput, Ui};
+use rand::rngs::StdRng;
+use rand::Rng;
 
 pub struct GameState<'a> {
     pub delta_time: f32,
     pub keyboard: &'a KeyboardState,
+    pub mouse: &'a MouseState,
+    pub input: &'a InputMap,
+    /// The presses and releases of this frame in order, for taps that are shorter than a frame
+    pub input_queue: &'a InputQueue,
+    /// The text typed this frame. Entities claim its focus to receive the keyboard for themselves.
+    pub text_input: &'a mut TextInput,
     pub screen_width: f32,
     pub screen_height: f32,
-    pub rng: &'a mut ThreadRng,
+    pub rng: &'a mut StdRng,
+    /// Widgets created here are drawn above the world this frame
+    pub ui: &'a mut Ui,
+    /// What happened to the window since the last frame, e.g. to open the pause menu when the focus is lost
+    pub lifecycle_events: &'a [LifecycleEvent],
 }
 
 impl<'a> GameState<'a> {
@@ -14,4 +25,69 @@ impl<'a> GameState<'a> {
     pub fn rand_f32(&mut self) -> f32 {
         self.rng.gen()
     }
+
+    /// Actions are never down while an entity has the text focus
+    pub fn is_action_down(&self, action: &str) -> bool {
+        !self.text_input.has_focus() && self.input.is_action_down(action, self.keyboard, self.mouse)
+    }
+
+    pub fn action_pressed_this_frame(&self, action: &str) -> bool {
+        !self.text_input.has_focus()
+            && self
+                .input
+                .action_pressed_this_frame(action, self.keyboard, self.mouse)
+    }
+
+    pub fn action_released_this_frame(&self, action: &str) -> bool {
+        !self.text_input.has_focus()
+            && self
+                .input
+                .action_released_this_frame(action, self.keyboard, self.mouse)
+    }
+
+    /// The value of an axis, between -1 and 1
+    pub fn axis(&self, axis: &str) -> f32 {
+        if self.text_input.has_focus() {
+            return 0f32;
+        }
+        self.input.axis(axis, self.keyboard, self.mouse)
+    }
+
+    /// Whether a binding of the action was pressed twice within `max_interval` milliseconds, the second time this frame
+    pub fn action_double_tapped(&self, action: &str, max_interval: f64) -> bool {
+        !self.text_input.has_focus()
+            && self
+                .input
+                .bindings(action)
+                .iter()
+                .any(|b| self.input_queue.double_tapped(*b, max_interval))
+    }
+
+    /// How long the action has been held, in milliseconds
+    pub fn action_hold_duration(&self, action: &str) -> Option<f64> {
+        if self.text_input.has_focus() {
+            return None;
+        }
+        self.input
+            .bindings(action)
+            .iter()
+            .filter_map(|b| self.input_queue.hold_duration(*b))
+            .fold(None, |longest: Option<f64>, duration| {
+                Some(longest.map_or(duration, |longest| longest.max(duration)))
+            })
+    }
+
+    /// How long the action was held before it was released this frame, in milliseconds, e.g. to charge a shot
+    pub fn action_released_after(&self, action: &str) -> Option<f64> {
+        if self.text_input.has_focus() {
+            return None;
+        }
+        self.input
+            .bindings(action)
+            .iter()
+            .filter_map(|b| self.input_queue.released_after(*b))
+            .fold(None, |longest: Option<f64>, duration| {
+                Some(longest.map_or(duration, |longest| longest.max(duration)))
+            })
+    }
 }
@@ -0,0 +1,565 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::error::Error;
+use super::{KeyboardState, MouseButton, MouseState, Result, VirtualKeyCode};
+
+/// The action that closes the game, bound to Escape by default
+pub const QUIT_ACTION: &str = "quit";
+
+/// A key or mouse button that triggers an action
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InputBinding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl InputBinding {
+    /// Parse a binding as written in a config file: the name of a key like `Space` or `A`,
+    /// or `mouse:` followed by `Left`, `Right`, `Middle` or the number of another button
+    pub fn parse(name: &str) -> Option<InputBinding> {
+        let name = name.trim();
+        if let Some(button) = name.strip_prefix("mouse:") {
+            let button = match button {
+                "Left" => MouseButton::Left,
+                "Right" => MouseButton::Right,
+                "Middle" => MouseButton::Middle,
+                other => MouseButton::Other(other.parse().ok()?),
+            };
+            return Some(InputBinding::Mouse(button));
+        }
+        parse_key(name).map(InputBinding::Key)
+    }
+
+    /// The name of the binding in a config file
+    pub fn name(self) -> String {
+        match self {
+            InputBinding::Key(key) => format!("{:?}", key),
+            InputBinding::Mouse(MouseButton::Other(button)) => format!("mouse:{}", button),
+            InputBinding::Mouse(button) => format!("mouse:{:?}", button),
+        }
+    }
+
+    pub fn is_down(self, keyboard: &KeyboardState, mouse: &MouseState) -> bool {
+        match self {
+            InputBinding::Key(key) => keyboard.is_keydown(key),
+            InputBinding::Mouse(button) => mouse.is_button_down(button),
+        }
+    }
+
+    pub fn is_pressed_this_frame(self, keyboard: &KeyboardState, mouse: &MouseState) -> bool {
+        match self {
+            InputBinding::Key(key) => keyboard.is_pressed_this_frame(key),
+            InputBinding::Mouse(button) => mouse.is_pressed_this_frame(button),
+        }
+    }
+
+    pub fn is_released_this_frame(self, keyboard: &KeyboardState, mouse: &MouseState) -> bool {
+        match self {
+            InputBinding::Key(key) => keyboard.is_released_this_frame(key),
+            InputBinding::Mouse(button) => mouse.is_released_this_frame(button),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct AxisBindings {
+    negative: Vec<InputBinding>,
+    positive: Vec<InputBinding>,
+}
+
+/// Maps keys and mouse buttons to named actions and axes, so entities ask for "fire" instead of a key,
+/// and players can change the keys.
+///
+/// Bindings can be loaded from a config file with one action per line, e.g. `fire = Space, mouse:Left`.
+/// Axes have the bindings for the negative and the positive direction, separated by a slash,
+/// e.g. `move_x = A, Left / D, Right`. Lines starting with `#` are comments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<InputBinding>>,
+    axes: BTreeMap<String, AxisBindings>,
+}
+
+impl Default for InputMap {
+    /// A map with the actions of the engine itself
+    fn default() -> InputMap {
+        let mut input = InputMap {
+            actions: BTreeMap::new(),
+            axes: BTreeMap::new(),
+        };
+        input.bind(QUIT_ACTION, InputBinding::Key(VirtualKeyCode::Escape));
+        input
+    }
+}
+
+impl InputMap {
+    /// Add a binding to an action, in addition to its existing bindings
+    pub fn bind(&mut self, action: &str, binding: InputBinding) {
+        let bindings = self
+            .actions
+            .entry(action.to_owned())
+            .or_insert_with(Vec::new);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: InputBinding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Replace all bindings of an action, e.g. when the player picks a new key in the options
+    pub fn set_bindings(&mut self, action: &str, bindings: Vec<InputBinding>) {
+        self.actions.insert(action.to_owned(), bindings);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[InputBinding] {
+        self.actions
+            .get(action)
+            .map(|bindings| &bindings[..])
+            .unwrap_or(&[])
+    }
+
+    /// All actions with bindings, sorted by name
+    pub fn actions(&self) -> Vec<&str> {
+        self.actions.keys().map(|a| a.as_str()).collect()
+    }
+
+    /// Add bindings that move an axis towards -1 and towards 1
+    pub fn bind_axis(&mut self, axis: &str, negative: InputBinding, positive: InputBinding) {
+        let bindings = self.axes.entry(axis.to_owned()).or_default();
+        if !bindings.negative.contains(&negative) {
+            bindings.negative.push(negative);
+        }
+        if !bindings.positive.contains(&positive) {
+            bindings.positive.push(positive);
+        }
+    }
+
+    /// Replace all bindings of an axis
+    pub fn set_axis_bindings(
+        &mut self,
+        axis: &str,
+        negative: Vec<InputBinding>,
+        positive: Vec<InputBinding>,
+    ) {
+        self.axes
+            .insert(axis.to_owned(), AxisBindings { negative, positive });
+    }
+
+    /// The bindings of an axis, for the negative and the positive direction
+    pub fn axis_bindings(&self, axis: &str) -> (&[InputBinding], &[InputBinding]) {
+        match self.axes.get(axis) {
+            Some(bindings) => (&bindings.negative, &bindings.positive),
+            None => (&[], &[]),
+        }
+    }
+
+    pub fn axes(&self) -> Vec<&str> {
+        self.axes.keys().map(|a| a.as_str()).collect()
+    }
+
+    pub fn is_action_down(
+        &self,
+        action: &str,
+        keyboard: &KeyboardState,
+        mouse: &MouseState,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| b.is_down(keyboard, mouse))
+    }
+
+    pub fn action_pressed_this_frame(
+        &self,
+        action: &str,
+        keyboard: &KeyboardState,
+        mouse: &MouseState,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| b.is_pressed_this_frame(keyboard, mouse))
+    }
+
+    /// Whether the last binding of an action that was held down was released this frame
+    pub fn action_released_this_frame(
+        &self,
+        action: &str,
+        keyboard: &KeyboardState,
+        mouse: &MouseState,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| b.is_released_this_frame(keyboard, mouse))
+            && !self.is_action_down(action, keyboard, mouse)
+    }
+
+    /// The value of an axis: -1, 0 or 1. Holding both directions cancels out.
+    pub fn axis(&self, axis: &str, keyboard: &KeyboardState, mouse: &MouseState) -> f32 {
+        let (negative, positive) = self.axis_bindings(axis);
+        let mut value = 0f32;
+        if negative.iter().any(|b| b.is_down(keyboard, mouse)) {
+            value -= 1f32;
+        }
+        if positive.iter().any(|b| b.is_down(keyboard, mouse)) {
+            value += 1f32;
+        }
+        value
+    }
+
+    /// Read bindings from a config file. Actions and axes in the file replace their existing bindings,
+    /// everything else keeps its bindings.
+    pub fn load_str(&mut self, source: &str) -> Result<()> {
+        let mut loaded = self.clone();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error =
+                |message: &str| Error::input_map(&format!("line {}: {}", index + 1, message));
+            let equals = line
+                .find('=')
+                .ok_or_else(|| error("expected 'action = bindings'"))?;
+            let name = line[..equals].trim();
+            if name.is_empty() {
+                return Err(error("empty action name"));
+            }
+            let bindings = &line[equals + 1..];
+            match bindings.find('/') {
+                Some(slash) => {
+                    if bindings[slash + 1..].contains('/') {
+                        return Err(error("expected 'axis = negative / positive'"));
+                    }
+                    let negative = parse_bindings(&bindings[..slash]).map_err(|e| error(&e))?;
+                    let positive = parse_bindings(&bindings[slash + 1..]).map_err(|e| error(&e))?;
+                    loaded.set_axis_bindings(name, negative, positive);
+                }
+                None => {
+                    let bindings = parse_bindings(bindings).map_err(|e| error(&e))?;
+                    loaded.set_bindings(name, bindings);
+                }
+            }
+        }
+        *self = loaded;
+        Ok(())
+    }
+
+    pub fn load_file<P: AsRef<Path>>(&mut self, file: P) -> Result<()> {
+        let source = fs::read_to_string(file)?;
+        self.load_str(&source)
+    }
+
+    /// Write all bindings in the format of `load_str`
+    pub fn to_config(&self) -> String {
+        let names = |bindings: &[InputBinding]| {
+            bindings
+                .iter()
+                .map(|b| b.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut config = String::from("# Actions\n");
+        for (action, bindings) in &self.actions {
+            config.push_str(&format!("{} = {}\n", action, names(bindings)));
+        }
+        config.push_str("\n# Axes: negative / positive\n");
+        for (axis, bindings) in &self.axes {
+            config.push_str(&format!(
+                "{} = {} / {}\n",
+                axis,
+                names(&bindings.negative),
+                names(&bindings.positive)
+            ));
+        }
+        config
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        fs::write(file, self.to_config())?;
+        Ok(())
+    }
+}
+
+/// Parse a comma separated list of bindings. An empty list leaves the action unbound.
+fn parse_bindings(list: &str) -> ::std::result::Result<Vec<InputBinding>, String> {
+    list.split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            InputBinding::parse(name).ok_or_else(|| format!("unknown key or button '{}'", name))
+        })
+        .collect()
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)*) => {
+        /// Look up a key by the name of its `VirtualKeyCode` variant
+        fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($key) => Some(VirtualKeyCode::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+key_names!(
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key0,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    Snapshot,
+    Scroll,
+    Pause,
+    Insert,
+    Home,
+    Delete,
+    End,
+    PageDown,
+    PageUp,
+    Left,
+    Up,
+    Right,
+    Down,
+    Back,
+    Return,
+    Space,
+    Compose,
+    Caret,
+    Numlock,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    AbntC1,
+    AbntC2,
+    Add,
+    Apostrophe,
+    Apps,
+    At,
+    Ax,
+    Backslash,
+    Calculator,
+    Capital,
+    Colon,
+    Comma,
+    Convert,
+    Decimal,
+    Divide,
+    Equals,
+    Grave,
+    Kana,
+    Kanji,
+    LAlt,
+    LBracket,
+    LControl,
+    LShift,
+    LWin,
+    Mail,
+    MediaSelect,
+    MediaStop,
+    Minus,
+    Multiply,
+    Mute,
+    MyComputer,
+    NavigateForward,
+    NavigateBackward,
+    NextTrack,
+    NoConvert,
+    NumpadComma,
+    NumpadEnter,
+    NumpadEquals,
+    OEM102,
+    Period,
+    PlayPause,
+    Power,
+    PrevTrack,
+    RAlt,
+    RBracket,
+    RControl,
+    RShift,
+    RWin,
+    Semicolon,
+    Slash,
+    Sleep,
+    Stop,
+    Subtract,
+    Sysrq,
+    Tab,
+    Underline,
+    Unlabeled,
+    VolumeDown,
+    VolumeUp,
+    Wake,
+    WebBack,
+    WebFavorites,
+    WebForward,
+    WebHome,
+    WebRefresh,
+    WebSearch,
+    WebStop,
+    Yen,
+    Copy,
+    Paste,
+    Cut,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{InputBinding, InputMap, QUIT_ACTION};
+    use {MouseButton, VirtualKeyCode};
+
+    const CONFIG: &str = "
+        # Actions
+        fire = Space, mouse:Left
+        pause = P, mouse:8
+        jump =
+        move_x = A, Left / D, Right
+        move_y = mouse:Middle / S
+    ";
+
+    fn loaded(source: &str) -> InputMap {
+        let mut input = InputMap::default();
+        input.load_str(source).unwrap();
+        input
+    }
+
+    #[test]
+    fn bindings_are_parsed() {
+        let input = loaded(CONFIG);
+        assert_eq!(
+            input.bindings("fire"),
+            &[
+                InputBinding::Key(VirtualKeyCode::Space),
+                InputBinding::Mouse(MouseButton::Left)
+            ]
+        );
+        assert_eq!(
+            input.bindings("pause"),
+            &[
+                InputBinding::Key(VirtualKeyCode::P),
+                InputBinding::Mouse(MouseButton::Other(8))
+            ]
+        );
+        assert!(input.bindings("jump").is_empty());
+        assert_eq!(
+            input.bindings(QUIT_ACTION),
+            &[InputBinding::Key(VirtualKeyCode::Escape)]
+        );
+    }
+
+    #[test]
+    fn configs_survive_a_round_trip() {
+        let input = loaded(CONFIG);
+        let config = input.to_config();
+        let reloaded = loaded(&config);
+        assert_eq!(reloaded, input);
+        assert_eq!(reloaded.to_config(), config);
+    }
+
+    fn load_error(source: &str) -> String {
+        let mut input = loaded(CONFIG);
+        let result = input.load_str(source);
+        assert_eq!(input, loaded(CONFIG), "a failed load keeps the bindings");
+        format!("{:?}", result.unwrap_err())
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        let error = load_error("fire = Space\njump = Spcae");
+        assert!(error.contains("line 2"), "{}", error);
+        assert!(error.contains("Spcae"), "{}", error);
+    }
+
+    #[test]
+    fn axes_need_exactly_one_slash() {
+        let error = load_error("move_x = A / D / W");
+        assert!(error.contains("negative / positive"), "{}", error);
+        let error = load_error("move_x = A / mouse:Wheel");
+        assert!(error.contains("mouse:Wheel"), "{}", error);
+    }
+
+    #[test]
+    fn mouse_buttons_need_a_name_or_number() {
+        for source in &["fire = mouse:Fourth", "fire = mouse:", "fire = mouse:-1"] {
+            let error = load_error(source);
+            assert!(error.contains("unknown key or button"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn lines_need_an_equals_sign() {
+        let error = load_error("# Actions\nfire Space");
+        assert!(error.contains("line 2"), "{}", error);
+        assert!(error.contains("expected 'action = bindings'"), "{}", error);
+        let error = load_error("= Space");
+        assert!(error.contains("empty action name"), "{}", error);
+    }
+}
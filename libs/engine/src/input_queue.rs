@@ -0,0 +1,293 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::InputBinding;
+
+/// How long presses are remembered for double tap detection, in milliseconds
+const HISTORY_DURATION: f64 = 1000f64;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputEventKind {
+    Pressed,
+    /// The binding was released after being held for this many milliseconds
+    Released {
+        held_for: f64,
+    },
+}
+
+/// A key or mouse button going down or up
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InputEvent {
+    pub binding: InputBinding,
+    pub kind: InputEventKind,
+    /// When the engine polled the event, in milliseconds. Events of one poll share their time,
+    /// their order is the order of `InputQueue::events`.
+    pub time: f64,
+}
+
+/// Every press and release in the order it happened, with the time it happened.
+/// Unlike `KeyboardState`, a key that is tapped twice within a frame shows up as two presses,
+/// and a key that is pressed and released within a frame can be told apart from one that is released and pressed again.
+///
+/// Key repeat of held keys is not recorded as new presses.
+#[derive(Default)]
+pub struct InputQueue {
+    events: Vec<InputEvent>,
+    /// The presses of the last second, the oldest first, including this frame's presses
+    history: VecDeque<InputEvent>,
+    held_since: HashMap<InputBinding, f64>,
+    now: f64,
+}
+
+impl InputQueue {
+    pub fn frame_start(&mut self) {
+        self.events.clear();
+    }
+
+    /// Set the time of the current frame, which hold durations are measured against
+    pub fn set_time(&mut self, now: f64) {
+        self.now = now;
+        while let Some(oldest) = self.history.front() {
+            if oldest.time + HISTORY_DURATION >= now {
+                break;
+            }
+            self.history.pop_front();
+        }
+    }
+
+    pub fn press(&mut self, binding: InputBinding, time: f64) {
+        if self.held_since.contains_key(&binding) {
+            return;
+        }
+        self.held_since.insert(binding, time);
+        let event = InputEvent {
+            binding,
+            kind: InputEventKind::Pressed,
+            time,
+        };
+        self.events.push(event);
+        self.history.push_back(event);
+    }
+
+    pub fn release(&mut self, binding: InputBinding, time: f64) {
+        let held_for = match self.held_since.remove(&binding) {
+            Some(since) => (time - since).max(0f64),
+            None => 0f64,
+        };
+        self.events.push(InputEvent {
+            binding,
+            kind: InputEventKind::Released { held_for },
+            time,
+        });
+    }
+
+    /// Release every held binding, e.g. when the window loses the focus and will not see the releases
+    pub fn release_all(&mut self, time: f64) {
+        let held = self.held_since.keys().cloned().collect::<Vec<_>>();
+        for binding in held {
+            self.release(binding, time);
+        }
+    }
+
+    /// Start over at the time of a new clock. The press history is forgotten,
+    /// and held bindings count as pressed now, as times of the old clock can not be compared to the new one.
+    pub fn reset_time(&mut self, now: f64) {
+        self.history.clear();
+        for since in self.held_since.values_mut() {
+            *since = now;
+        }
+        self.now = now;
+    }
+
+    /// The events of this frame, the oldest first
+    pub fn events(&self) -> &[InputEvent] {
+        &self.events
+    }
+
+    /// How often the binding was pressed this frame
+    pub fn presses_this_frame(&self, binding: InputBinding) -> usize {
+        self.events
+            .iter()
+            .filter(|e| e.binding == binding && e.kind == InputEventKind::Pressed)
+            .count()
+    }
+
+    /// Whether the binding was pressed this frame, at most `max_interval` milliseconds after its previous press
+    pub fn double_tapped(&self, binding: InputBinding, max_interval: f64) -> bool {
+        let mut previous: Option<f64> = None;
+        for event in self.history.iter().filter(|e| e.binding == binding) {
+            if let Some(previous) = previous {
+                if event.time - previous <= max_interval && self.events.contains(event) {
+                    return true;
+                }
+            }
+            previous = Some(event.time);
+        }
+        false
+    }
+
+    /// How long the binding has been held down, in milliseconds
+    pub fn hold_duration(&self, binding: InputBinding) -> Option<f64> {
+        self.held_since
+            .get(&binding)
+            .map(|since| (self.now - *since).max(0f64))
+    }
+
+    /// How long the binding was held before it was released this frame, in milliseconds
+    pub fn released_after(&self, binding: InputBinding) -> Option<f64> {
+        self.events
+            .iter()
+            .filter(|e| e.binding == binding)
+            .filter_map(|e| match e.kind {
+                InputEventKind::Released { held_for } => Some(held_for),
+                InputEventKind::Pressed => None,
+            })
+            .last()
+    }
+
+    /// Whether `binding` was pressed this frame while all the `held` bindings were already down, e.g. Shift+Space.
+    /// The order of the events decides, as events polled together have the same time.
+    pub fn chord_pressed(&self, held: &[InputBinding], binding: InputBinding) -> bool {
+        self.events
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.binding == binding && e.kind == InputEventKind::Pressed)
+            .any(|(index, _)| {
+                held.iter()
+                    .all(|modifier| self.was_down_before(*modifier, index))
+            })
+    }
+
+    /// Whether the binding was down just before the event at `index` of this frame
+    fn was_down_before(&self, binding: InputBinding, index: usize) -> bool {
+        let mut changes = self.events.iter().filter(|e| e.binding == binding);
+        let down_at_frame_start = match changes.next() {
+            Some(first) => first.kind != InputEventKind::Pressed,
+            None => self.held_since.contains_key(&binding),
+        };
+        match self.events[..index]
+            .iter()
+            .rev()
+            .find(|e| e.binding == binding)
+        {
+            Some(last) => last.kind == InputEventKind::Pressed,
+            None => down_at_frame_start,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputBinding, InputEventKind, InputQueue};
+    use VirtualKeyCode::{LShift, Space};
+
+    const SPACE: InputBinding = InputBinding::Key(Space);
+    const SHIFT: InputBinding = InputBinding::Key(LShift);
+
+    #[test]
+    fn double_taps_need_two_presses_within_the_interval() {
+        let mut queue = InputQueue::default();
+        queue.set_time(1000f64);
+        queue.press(SPACE, 1000f64);
+        queue.release(SPACE, 1050f64);
+        assert!(!queue.double_tapped(SPACE, 300f64));
+
+        queue.frame_start();
+        queue.set_time(1200f64);
+        queue.press(SPACE, 1200f64);
+        assert!(queue.double_tapped(SPACE, 300f64));
+        assert!(!queue.double_tapped(SPACE, 100f64));
+
+        // The double tap is only reported in the frame of the second press
+        queue.frame_start();
+        queue.set_time(1250f64);
+        assert!(!queue.double_tapped(SPACE, 300f64));
+    }
+
+    #[test]
+    fn two_taps_within_a_frame_are_a_double_tap() {
+        let mut queue = InputQueue::default();
+        queue.set_time(500f64);
+        queue.press(SPACE, 500f64);
+        queue.release(SPACE, 500f64);
+        queue.press(SPACE, 500f64);
+        assert_eq!(queue.presses_this_frame(SPACE), 2);
+        assert!(queue.double_tapped(SPACE, 0f64));
+    }
+
+    #[test]
+    fn hold_duration_grows_until_the_release() {
+        let mut queue = InputQueue::default();
+        queue.set_time(100f64);
+        assert_eq!(queue.hold_duration(SPACE), None);
+        queue.press(SPACE, 100f64);
+        // Key repeat is not a new press
+        queue.press(SPACE, 150f64);
+        queue.set_time(400f64);
+        assert_eq!(queue.hold_duration(SPACE), Some(300f64));
+
+        queue.frame_start();
+        queue.release(SPACE, 450f64);
+        assert_eq!(queue.hold_duration(SPACE), None);
+        assert_eq!(queue.released_after(SPACE), Some(350f64));
+        assert_eq!(
+            queue.events()[0].kind,
+            InputEventKind::Released { held_for: 350f64 }
+        );
+    }
+
+    #[test]
+    fn chords_follow_the_order_of_the_events() {
+        let mut queue = InputQueue::default();
+        queue.press(SHIFT, 10f64);
+        queue.press(SPACE, 10f64);
+        assert!(queue.chord_pressed(&[SHIFT], SPACE));
+
+        // Space first, in the same poll, is not Shift+Space
+        let mut queue = InputQueue::default();
+        queue.press(SPACE, 10f64);
+        queue.press(SHIFT, 10f64);
+        assert!(!queue.chord_pressed(&[SHIFT], SPACE));
+        assert!(queue.chord_pressed(&[], SPACE));
+    }
+
+    #[test]
+    fn chords_use_modifiers_held_since_earlier_frames() {
+        let mut queue = InputQueue::default();
+        queue.press(SHIFT, 10f64);
+        queue.frame_start();
+        queue.press(SPACE, 20f64);
+        assert!(queue.chord_pressed(&[SHIFT], SPACE));
+
+        // Shift was let go before Space, within the frame
+        queue.frame_start();
+        queue.release(SPACE, 30f64);
+        queue.release(SHIFT, 30f64);
+        queue.press(SPACE, 30f64);
+        assert!(!queue.chord_pressed(&[SHIFT], SPACE));
+    }
+
+    #[test]
+    fn fractions_of_a_millisecond_are_kept() {
+        let mut queue = InputQueue::default();
+        queue.press(SPACE, 10.25);
+        queue.release(SPACE, 10.75);
+        assert_eq!(queue.released_after(SPACE), Some(0.5));
+    }
+
+    #[test]
+    fn a_new_clock_starts_over() {
+        let mut queue = InputQueue::default();
+        queue.set_time(5000f64);
+        queue.press(SPACE, 5000f64);
+        queue.release(SPACE, 5010f64);
+        queue.press(SHIFT, 5020f64);
+
+        // The new clock is behind the old one
+        queue.frame_start();
+        queue.reset_time(0f64);
+        queue.press(SPACE, 10f64);
+        assert!(!queue.double_tapped(SPACE, 300f64));
+        queue.set_time(100f64);
+        assert_eq!(queue.hold_duration(SHIFT), Some(100f64));
+    }
+}
@@ -1,3 +1,5 @@
+use std::mem;
+
 use glium::glutin::VirtualKeyCode;
 
 #[derive(Default)]
@@ -25,6 +27,12 @@ impl KeyboardState {
         self.released_keys_this_frame.push(key);
     }
 
+    /// Release every held key, e.g. when the window loses the focus and will not see the releases
+    pub fn release_all(&mut self) {
+        let keys = mem::replace(&mut self.pressed_keys, Vec::new());
+        self.released_keys_this_frame.extend(keys);
+    }
+
     pub fn is_keydown(&self, key: VirtualKeyCode) -> bool {
         self.pressed_keys.iter().any(|k| *k == key)
     }
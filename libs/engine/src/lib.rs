@@ -1,35 +1,108 @@
 #![allow(clippy::borrowed_box)]
 
+#[cfg(feature = "device-audio")]
+extern crate cpal;
 #[macro_use]
 extern crate glium;
 extern crate glium_text;
+extern crate hound;
 extern crate image;
+extern crate lewton;
 extern crate rand;
 extern crate time as __time;
 extern crate winit;
 
+mod animation;
+mod atlas;
+mod audio;
+mod audio_output;
+mod background;
+mod bitmap_font;
+mod camera;
+mod debug_overlay;
 mod draw_helper;
 mod engine;
+mod engine_builder;
 mod engine_graphics;
 mod entity;
 mod error;
+mod frame_pacing;
 mod game_state;
+mod input_map;
+mod input_queue;
 mod keyboard_state;
+mod lifecycle;
+mod localization;
+mod lru_cache;
+mod mixer;
+mod mouse_state;
+mod music;
+mod nine_slice;
+mod particles;
+mod post_processing;
+mod primitives;
+mod render_target;
+mod screenshot;
+mod sound;
 mod text_graphics;
+mod text_input;
 mod time;
+mod ui;
 
-pub use self::draw_helper::DrawHelper;
+pub use self::animation::{
+    Animation, AnimationCursor, AnimationFrame, AnimationMode, SpriteRegion,
+};
+pub use self::atlas::{AtlasEntry, AtlasPlacement, TextureAtlas, TextureRegion};
+pub use self::audio::{Audio, PlaySound};
+#[cfg(feature = "device-audio")]
+pub use self::audio_output::DeviceOutput;
+pub use self::audio_output::{AudioOutput, NullOutput, WavFileOutput};
+pub use self::background::{Background, BackgroundLayer, Starfield};
+pub use self::bitmap_font::{BitmapFontDescriptor, BitmapGlyph, BitmapTextStyle};
+pub use self::camera::Camera;
+pub use self::debug_overlay::DebugOverlay;
+pub use self::draw_helper::{DrawHelper, SpriteEffects};
 pub use self::engine::Engine;
-pub use self::engine_graphics::EngineGraphics;
+pub use self::engine_builder::{EngineBuilder, WindowSettings};
+pub use self::engine_graphics::{EngineGraphics, SheetTransparency, DEFAULT_LOCALE};
 pub use self::entity::*;
-pub use self::error::Result;
+pub use self::error::{Error, Result};
+pub use self::frame_pacing::{
+    FramePacing, VSync, DEFAULT_MAX_DELTA_TIME, DEFAULT_REFRESH_RATE, DEFAULT_SPIN_THRESHOLD,
+};
 pub use self::game_state::GameState;
+pub use self::input_map::{InputBinding, InputMap, QUIT_ACTION};
+pub use self::input_queue::{InputEvent, InputEventKind, InputQueue};
 pub use self::keyboard_state::KeyboardState;
-pub use self::text_graphics::TextGraphics;
+pub use self::lifecycle::LifecycleEvent;
+pub use self::localization::{Arguments, Localization, PluralForm};
+pub use self::lru_cache::LruCache;
+pub use self::mixer::{
+    Bus, Ducking, Mixer, Spatialization, VoiceId, DEFAULT_SAMPLE_RATE, MAX_AUDIO_ERRORS, MUSIC_BUS,
+    SFX_BUS, UI_BUS,
+};
+pub use self::mouse_state::MouseState;
+pub use self::music::{
+    MusicCommand, MusicDecoder, MusicSource, MusicTrack, MusicVoice, OggStream, PlayMusic,
+    Playlist, SampleStream, SoundStream, DEFAULT_CROSSFADE, MUSIC_BUFFER_AHEAD,
+};
+pub use self::nine_slice::NineSlice;
+pub use self::particles::{EmitterMode, ParticleEmitter, ParticleSystem, DEFAULT_MAX_PARTICLES};
+pub use self::post_processing::{PostEffect, PostParameter, PostProcessing};
+pub use self::primitives::{ColorVertex, PrimitiveBatch};
+pub use self::render_target::RenderTarget;
+pub use self::screenshot::compare_images;
+pub use self::sound::SoundData;
+pub use self::text_graphics::{
+    RetainedText, TextAlign, TextGraphics, TextStyle, DEFAULT_FONT, DEFAULT_TEXT_CACHE_SIZE,
+};
+pub use self::text_input::{TextEvent, TextInput};
+pub use self::time::{Clock, MockClock, MonotonicClock};
+pub use self::ui::{Ui, UiSkin, UiStyle};
 
 use glium::glutin::{ElementState, Event};
 use std::hash::Hash;
 
-pub use glium::glutin::VirtualKeyCode;
+pub use glium::glutin::{MouseButton, VirtualKeyCode};
 pub type Color = (f32, f32, f32, f32);
 pub trait TGraphicIndex: PartialEq + Eq + Hash {}
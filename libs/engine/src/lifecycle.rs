@@ -0,0 +1,13 @@
+/// Changes to the state of the window, passed to the entities through `GameState::lifecycle_events`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// The window lost the keyboard focus. All held keys and buttons were released.
+    FocusLost,
+    FocusGained,
+    Minimized,
+    Restored,
+    /// The engine paused itself because the window lost the focus or was minimized, see `Engine::auto_pause`
+    AutoPaused,
+    /// The window is back, and the engine ended the pause it started itself
+    AutoResumed,
+}
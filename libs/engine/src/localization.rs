@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use super::error::Error;
+use super::Result;
+
+/// The arguments that are interpolated into a localized string, by name
+pub type Arguments<'a> = [(&'a str, &'a dyn Display)];
+
+/// The plural forms a language can distinguish. A string table only needs the forms its language uses,
+/// and `Other` is used whenever a form is missing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PluralForm {
+    Zero,
+    One,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralForm {
+    /// The plural form of `count` in the given language, following the CLDR rules for integers
+    pub fn of(locale: &str, count: i64) -> PluralForm {
+        let n = count.abs();
+        match language(locale) {
+            "ja" | "ko" | "zh" | "th" | "vi" | "id" => PluralForm::Other,
+            "fr" | "pt" => {
+                if n < 2 {
+                    PluralForm::One
+                } else {
+                    PluralForm::Other
+                }
+            }
+            "ru" | "uk" | "be" | "sr" | "hr" | "bs" => {
+                if n % 10 == 1 && n % 100 != 11 {
+                    PluralForm::One
+                } else if (2..=4).contains(&(n % 10)) && !(12..=14).contains(&(n % 100)) {
+                    PluralForm::Few
+                } else {
+                    PluralForm::Many
+                }
+            }
+            "pl" => {
+                if n == 1 {
+                    PluralForm::One
+                } else if (2..=4).contains(&(n % 10)) && !(12..=14).contains(&(n % 100)) {
+                    PluralForm::Few
+                } else {
+                    PluralForm::Many
+                }
+            }
+            "cs" | "sk" => match n {
+                1 => PluralForm::One,
+                2..=4 => PluralForm::Few,
+                _ => PluralForm::Other,
+            },
+            _ => {
+                if n == 1 {
+                    PluralForm::One
+                } else {
+                    PluralForm::Other
+                }
+            }
+        }
+    }
+
+    /// The suffix of the keys of this form in a string table, e.g. `enemies.one`
+    pub fn suffix(self) -> &'static str {
+        match self {
+            PluralForm::Zero => "zero",
+            PluralForm::One => "one",
+            PluralForm::Few => "few",
+            PluralForm::Many => "many",
+            PluralForm::Other => "other",
+        }
+    }
+}
+
+/// The language part of a locale, e.g. `pt` for `pt-BR`
+fn language(locale: &str) -> &str {
+    locale
+        .split(|c| c == '-' || c == '_')
+        .next()
+        .unwrap_or(locale)
+}
+
+/// String tables for every language, with the language that is currently used.
+///
+/// Tables are text files with one `key = value` pair per line. Lines starting with `#` are comments,
+/// and `\n` in a value is a line break. Values can contain arguments as `{name}`, and `{{` and `}}` for a literal `{` and `}`.
+/// Plural strings have a key per plural form, e.g. `enemies.one = {count} enemy` and `enemies.other = {count} enemies`.
+///
+/// A key that is missing in the current language is looked up in its base language, e.g. `de` for `de-AT`,
+/// then in the default language, and if it is missing there as well, the key itself is shown.
+pub struct Localization {
+    default_locale: String,
+    locale: String,
+    tables: HashMap<String, HashMap<String, String>>,
+}
+
+impl Localization {
+    pub fn new(default_locale: &str) -> Localization {
+        Localization {
+            default_locale: default_locale.to_owned(),
+            locale: default_locale.to_owned(),
+            tables: HashMap::new(),
+        }
+    }
+
+    /// The language of the user, from the `LC_ALL`, `LC_MESSAGES` or `LANG` environment variables, e.g. `de` for `de_DE.UTF-8`
+    pub fn system_locale() -> Option<String> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+            .map(|value| language(value.split('.').next().unwrap_or(&value)).to_owned())
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// Switch the language. Switching to a language without a table shows its base language, or the default language.
+    pub fn set_locale(&mut self, locale: &str) {
+        self.locale = locale.to_owned();
+    }
+
+    /// All languages that have a string table, sorted
+    pub fn locales(&self) -> Vec<&str> {
+        let mut locales = self.tables.keys().map(|l| l.as_str()).collect::<Vec<_>>();
+        locales.sort();
+        locales
+    }
+
+    /// Add the strings of a table to a language. Existing keys are replaced.
+    pub fn load_str(&mut self, locale: &str, source: &str) -> Result<()> {
+        let mut strings = HashMap::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let equals = line.find('=').ok_or_else(|| {
+                Error::localization(&format!(
+                    "{}, line {}: expected 'key = value'",
+                    locale,
+                    index + 1
+                ))
+            })?;
+            let key = line[..equals].trim();
+            if key.is_empty() {
+                return Err(Error::localization(&format!(
+                    "{}, line {}: empty key",
+                    locale,
+                    index + 1
+                )));
+            }
+            let value = line[equals + 1..].trim().replace("\\n", "\n");
+            strings.insert(key.to_owned(), value);
+        }
+        self.tables
+            .entry(locale.to_owned())
+            .or_insert_with(HashMap::new)
+            .extend(strings);
+        Ok(())
+    }
+
+    pub fn load_file<P: AsRef<Path>>(&mut self, locale: &str, file: P) -> Result<()> {
+        let source = fs::read_to_string(file)?;
+        self.load_str(locale, &source)
+    }
+
+    /// Load every `.lang` file in a directory, using the file name as the language, e.g. `de.lang`
+    pub fn load_directory<P: AsRef<Path>>(&mut self, directory: P) -> Result<()> {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().map(|e| e == "lang").unwrap_or(false) {
+                if let Some(locale) = path.file_stem().and_then(|s| s.to_str()) {
+                    let locale = locale.to_owned();
+                    self.load_file(&locale, &path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The languages a string is looked up in, in order: the current language, its base language and the default language
+    fn fallbacks(&self) -> Vec<&str> {
+        let mut locales = vec![self.locale.as_str()];
+        for locale in &[language(&self.locale), self.default_locale.as_str()] {
+            if !locales.contains(locale) {
+                locales.push(locale);
+            }
+        }
+        locales
+    }
+
+    /// Look up a string in the current language, falling back to the base and the default language
+    fn lookup(&self, key: &str) -> Option<&str> {
+        self.fallbacks()
+            .iter()
+            .filter_map(|locale| self.tables.get(*locale).and_then(|table| table.get(key)))
+            .map(|value| value.as_str())
+            .next()
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    /// Look up a string and replace its `{name}` arguments
+    pub fn format(&self, key: &str, arguments: &Arguments) -> String {
+        match self.lookup(key) {
+            Some(value) => interpolate(value, arguments),
+            None => key.to_owned(),
+        }
+    }
+
+    /// Look up the plural form of a string for `count`, which is also available as the `{count}` argument
+    pub fn plural(&self, key: &str, count: i64, arguments: &Arguments) -> String {
+        let mut all_arguments: Vec<(&str, &dyn Display)> = vec![("count", &count)];
+        all_arguments.extend_from_slice(arguments);
+        for locale in self.fallbacks() {
+            let table = match self.tables.get(locale) {
+                Some(table) => table,
+                None => continue,
+            };
+            let form = PluralForm::of(locale, count);
+            let exact = format!("{}.{}", key, form.suffix());
+            let other = format!("{}.{}", key, PluralForm::Other.suffix());
+            // An explicit zero form is used when present, even in languages that have no zero form
+            let zero = format!("{}.{}", key, PluralForm::Zero.suffix());
+            let value = if count == 0 && table.contains_key(&zero) {
+                table.get(&zero)
+            } else {
+                table.get(&exact).or_else(|| table.get(&other))
+            };
+            if let Some(value) = value {
+                return interpolate(value, &all_arguments);
+            }
+        }
+        key.to_owned()
+    }
+}
+
+/// Replace `{name}` with the argument of that name. Unknown arguments are left as they are.
+fn interpolate(value: &str, arguments: &Arguments) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(|c| c == '{' || c == '}') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if rest[start..].starts_with('}') {
+            // `}}` is a literal `}`, and so is a single `}` without an argument
+            result.push('}');
+            rest = after.strip_prefix('}').unwrap_or(after);
+            continue;
+        }
+        if let Some(after_escape) = after.strip_prefix('{') {
+            result.push('{');
+            rest = after_escape;
+            continue;
+        }
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                match arguments.iter().find(|(n, _)| *n == name) {
+                    Some((_, argument)) => result.push_str(&argument.to_string()),
+                    None => {
+                        result.push('{');
+                        result.push_str(name);
+                        result.push('}');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpolate, Localization, PluralForm};
+    use std::fmt::Display;
+
+    fn forms(locale: &str, counts: &[i64]) -> Vec<PluralForm> {
+        counts.iter().map(|&n| PluralForm::of(locale, n)).collect()
+    }
+
+    #[test]
+    fn plural_forms_follow_the_language() {
+        use super::PluralForm::{Few, Many, One, Other};
+
+        let counts = [0, 1, 2, 5, 11, 12, 21, 22, 25, 101, 111, 112];
+        let table: &[(&str, [PluralForm; 12])] = &[
+            (
+                "en",
+                [
+                    Other, One, Other, Other, Other, Other, Other, Other, Other, Other, Other,
+                    Other,
+                ],
+            ),
+            (
+                "de",
+                [
+                    Other, One, Other, Other, Other, Other, Other, Other, Other, Other, Other,
+                    Other,
+                ],
+            ),
+            (
+                "fr",
+                [
+                    One, One, Other, Other, Other, Other, Other, Other, Other, Other, Other, Other,
+                ],
+            ),
+            (
+                "ru",
+                [
+                    Many, One, Few, Many, Many, Many, One, Few, Many, One, Many, Many,
+                ],
+            ),
+            (
+                "pl",
+                [
+                    Many, One, Few, Many, Many, Many, Many, Few, Many, Many, Many, Many,
+                ],
+            ),
+            (
+                "ja",
+                [
+                    Other, Other, Other, Other, Other, Other, Other, Other, Other, Other, Other,
+                    Other,
+                ],
+            ),
+        ];
+        for (locale, expected) in table {
+            assert_eq!(forms(locale, &counts), expected.to_vec(), "{}", locale);
+        }
+        assert_eq!(forms("pt-BR", &[0, 1, 2]), vec![One, One, Other]);
+        assert_eq!(forms("ru", &[-1, -3]), vec![One, Few]);
+    }
+
+    #[test]
+    fn plural_strings_use_the_form_of_the_count() {
+        let mut localization = Localization::new("en");
+        localization
+            .load_str("ru", "enemies.one = {count} враг\nenemies.few = {count} врага\nenemies.many = {count} врагов")
+            .unwrap();
+        localization.set_locale("ru");
+        let plurals = [1, 3, 5, 21]
+            .iter()
+            .map(|&n| localization.plural("enemies", n, &[]))
+            .collect::<Vec<_>>();
+        assert_eq!(plurals, vec!["1 враг", "3 врага", "5 врагов", "21 враг"]);
+    }
+
+    #[test]
+    fn braces_can_be_escaped() {
+        let arguments: &[(&str, &dyn Display)] = &[("name", &"Ada")];
+        assert_eq!(
+            interpolate("{{name}} is {name}", arguments),
+            "{name} is Ada"
+        );
+        assert_eq!(interpolate("{{ {name} }}", arguments), "{ Ada }");
+        assert_eq!(interpolate("a } b", arguments), "a } b");
+    }
+
+    #[test]
+    fn missing_arguments_are_left_as_they_are() {
+        let arguments: &[(&str, &dyn Display)] = &[("name", &"Ada")];
+        assert_eq!(
+            interpolate("{name} has {score}", arguments),
+            "Ada has {score}"
+        );
+        assert_eq!(interpolate("{name} {unclosed", arguments), "Ada {unclosed");
+    }
+
+    #[test]
+    fn regions_fall_back_to_their_language_and_then_the_default() {
+        let mut localization = Localization::new("en");
+        localization
+            .load_str("en", "start = Start\nquit = Quit\nscore = Score")
+            .unwrap();
+        localization
+            .load_str("de", "start = Start\nquit = Beenden")
+            .unwrap();
+        localization.load_str("de-AT", "start = Los").unwrap();
+        localization.set_locale("de-AT");
+        assert_eq!(localization.get("start"), "Los");
+        assert_eq!(localization.get("quit"), "Beenden");
+        assert_eq!(localization.get("score"), "Score");
+        assert_eq!(localization.get("missing"), "missing");
+
+        localization.set_locale("pt-BR");
+        assert_eq!(localization.get("quit"), "Quit");
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// A map with a maximum amount of entries. When it is full, inserting an entry removes the
+/// entry that was least recently inserted or looked up.
+pub struct LruCache<K: Hash + Eq + Clone, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    /// The keys of all entries by the time they were last used, the oldest first
+    usage: BTreeMap<u64, K>,
+    clock: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> LruCache<K, V> {
+        LruCache {
+            capacity,
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the maximum amount of entries, evicting the least recently used entries if there are too many
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Check whether an entry exists, without marking it as used
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Look up an entry and mark it as the most recently used one
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let stamp = self.next_stamp();
+        match self.entries.get_mut(key) {
+            Some((value, last_used)) => {
+                self.usage.remove(last_used);
+                *last_used = stamp;
+                self.usage.insert(stamp, key.clone());
+                Some(value)
+            }
+            None => None,
+        }
+    }
+
+    /// Insert or replace an entry as the most recently used one.
+    /// Returns the entries that were evicted to make room for it.
+    pub fn insert(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let stamp = self.next_stamp();
+        if let Some((_, last_used)) = self.entries.insert(key.clone(), (value, stamp)) {
+            self.usage.remove(&last_used);
+        }
+        self.usage.insert(stamp, key);
+        self.evict()
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(value, last_used)| {
+            self.usage.remove(&last_used);
+            value
+        })
+    }
+
+    /// Remove all entries for which the predicate returns false
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut predicate: F) {
+        let usage = &mut self.usage;
+        self.entries.retain(|key, (value, last_used)| {
+            let keep = predicate(key, value);
+            if !keep {
+                usage.remove(last_used);
+            }
+            keep
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.usage.clear();
+    }
+
+    fn next_stamp(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn evict(&mut self) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        while self.entries.len() > self.capacity {
+            let oldest = match self.usage.keys().next() {
+                Some(oldest) => *oldest,
+                None => break,
+            };
+            let key = self.usage.remove(&oldest).unwrap();
+            if let Some((value, _)) = self.entries.remove(&key) {
+                evicted.push((key, value));
+            }
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruCache;
+
+    fn keys(cache: &LruCache<&'static str, u32>) -> Vec<&'static str> {
+        let mut keys = ["a", "b", "c", "d"]
+            .iter()
+            .cloned()
+            .filter(|k| cache.contains_key(k))
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn evicts_the_oldest_entry_when_full() {
+        let mut cache = LruCache::new(2);
+        assert!(cache.insert("a", 1).is_empty());
+        assert!(cache.insert("b", 2).is_empty());
+        assert_eq!(cache.insert("c", 3), vec![("a", 1)]);
+        assert_eq!(keys(&cache), vec!["b", "c"]);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn lookups_keep_entries_alive() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.insert("c", 3), vec![("b", 2)]);
+        assert_eq!(keys(&cache), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn contains_key_does_not_count_as_use() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert!(cache.contains_key(&"a"));
+        assert_eq!(cache.insert("c", 3), vec![("a", 1)]);
+    }
+
+    #[test]
+    fn replacing_an_entry_does_not_evict() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert!(cache.insert("a", 10).is_empty());
+        assert_eq!(cache.get(&"a"), Some(&10));
+        assert_eq!(cache.insert("c", 3), vec![("b", 2)]);
+    }
+
+    #[test]
+    fn shrinking_evicts_the_least_recently_used_entries() {
+        let mut cache = LruCache::new(4);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        cache.insert("d", 4);
+        cache.get(&"a");
+        cache.set_capacity(2);
+        assert_eq!(keys(&cache), vec!["a", "d"]);
+    }
+
+    #[test]
+    fn removed_entries_are_not_evicted_later() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.remove(&"a"), Some(1));
+        cache.retain(|_, value| *value != 2);
+        assert!(cache.is_empty());
+        assert!(cache.insert("c", 3).is_empty());
+        assert!(cache.insert("d", 4).is_empty());
+        assert_eq!(keys(&cache), vec!["c", "d"]);
+    }
+
+    #[test]
+    fn a_cache_without_capacity_keeps_nothing() {
+        let mut cache = LruCache::new(0);
+        assert_eq!(cache.insert("a", 1), vec![("a", 1)]);
+        assert!(cache.is_empty());
+    }
+}
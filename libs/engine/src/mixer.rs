@@ -0,0 +1,660 @@
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+
+use super::error::Error;
+use super::music::MusicPlayer;
+use super::{Camera, MusicTrack, MusicVoice, PlaySound, Result, SoundData};
+
+pub const SFX_BUS: &str = "sfx";
+pub const MUSIC_BUS: &str = "music";
+pub const UI_BUS: &str = "ui";
+
+/// The sample rate of the null and WAV outputs
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// How many voices the default buses play at once
+const DEFAULT_VOICE_LIMIT: usize = 16;
+
+/// How many errors the mixer keeps until they are taken, later ones are dropped
+pub const MAX_AUDIO_ERRORS: usize = 16;
+
+/// A playing sound, which can be stopped while it plays
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+/// A group of voices that share a volume, e.g. all sound effects
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bus {
+    pub volume: f32,
+    /// How many voices play at once. When another voice starts, the oldest one of the bus is stopped.
+    pub voice_limit: usize,
+}
+
+/// How far the music bus is turned down while sounds that duck it play
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ducking {
+    pub volume: f32,
+    /// How long turning the music down takes, in milliseconds
+    pub attack: f32,
+    /// How long turning it back up takes after the last ducking sound ends, in milliseconds
+    pub release: f32,
+}
+
+impl Default for Ducking {
+    fn default() -> Ducking {
+        Ducking {
+            volume: 0.35f32,
+            attack: 40f32,
+            release: 600f32,
+        }
+    }
+}
+
+/// How positional sounds are panned and attenuated
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Spatialization {
+    /// How far a sound at the edge of the screen is panned, 1 plays it on one side only
+    pub pan_width: f32,
+    /// How far outside of the screen a sound becomes silent, in pixels
+    pub falloff: f32,
+}
+
+impl Default for Spatialization {
+    fn default() -> Spatialization {
+        Spatialization {
+            pan_width: 0.8f32,
+            falloff: 600f32,
+        }
+    }
+}
+
+/// The part of the world that is on screen, which positional sounds are heard from
+#[derive(Copy, Clone, Default)]
+struct Listener {
+    camera: Camera,
+    width: f32,
+    height: f32,
+}
+
+/// Where a positional voice is in the world
+#[derive(Copy, Clone)]
+struct Emitter {
+    /// The position, relative to the entity it follows
+    offset: (f32, f32),
+    follow: Option<u64>,
+    /// The position of the entity it follows
+    origin: (f32, f32),
+}
+
+struct Voice {
+    id: VoiceId,
+    sound: Arc<SoundData>,
+    bus: String,
+    /// The frame of the sound that plays next, between two frames when the pitch or sample rate differ
+    position: f64,
+    volume: f32,
+    pitch: f32,
+    looping: bool,
+    ducks_music: bool,
+    pan: f32,
+    emitter: Option<Emitter>,
+    /// The left and right gain of the last render, which the next render fades from
+    stereo_gains: Option<(f32, f32)>,
+}
+
+impl Voice {
+    /// The left and right gain from the pan and the position on screen
+    fn target_gains(&self, listener: &Listener, spatialization: &Spatialization) -> (f32, f32) {
+        let mut pan = self.pan;
+        let mut attenuation = 1f32;
+        if let (Some(emitter), true) = (self.emitter, listener.width > 0f32) {
+            let (x, y) = listener.camera.world_to_screen(
+                emitter.origin.0 + emitter.offset.0,
+                emitter.origin.1 + emitter.offset.1,
+            );
+            let from_center = (x / listener.width * 2f32 - 1f32).max(-1f32).min(1f32);
+            pan += from_center * spatialization.pan_width;
+            let outside_x = (-x).max(x - listener.width).max(0f32);
+            let outside_y = (-y).max(y - listener.height).max(0f32);
+            let distance = (outside_x * outside_x + outside_y * outside_y).sqrt();
+            attenuation = (1f32 - distance / spatialization.falloff.max(1f32)).max(0f32);
+        }
+        let pan = pan.max(-1f32).min(1f32);
+        (
+            (1f32 - pan).min(1f32) * attenuation,
+            (1f32 + pan).min(1f32) * attenuation,
+        )
+    }
+}
+
+/// Mixes the playing voices into interleaved stereo samples
+pub struct Mixer {
+    sample_rate: u32,
+    pub master_volume: f32,
+    pub ducking: Ducking,
+    pub spatialization: Spatialization,
+    listener: Listener,
+    /// The current gain of the music bus from ducking
+    duck_gain: f32,
+    buses: HashMap<String, Bus>,
+    /// The oldest voice first
+    voices: Vec<Voice>,
+    music: MusicPlayer,
+    next_id: u64,
+    /// Errors that happened while playing, e.g. on the thread of the sound device
+    errors: Vec<Error>,
+}
+
+impl Mixer {
+    /// A mixer with the sfx, music and ui buses
+    pub fn new(sample_rate: u32) -> Mixer {
+        let mut mixer = Mixer {
+            sample_rate,
+            master_volume: 1f32,
+            ducking: Ducking::default(),
+            spatialization: Spatialization::default(),
+            listener: Listener::default(),
+            duck_gain: 1f32,
+            buses: HashMap::new(),
+            voices: Vec::new(),
+            music: MusicPlayer::default(),
+            next_id: 0,
+            errors: Vec::with_capacity(MAX_AUDIO_ERRORS),
+        };
+        mixer.add_bus(SFX_BUS, DEFAULT_VOICE_LIMIT);
+        mixer.add_bus(MUSIC_BUS, 2);
+        mixer.add_bus(UI_BUS, 4);
+        mixer
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Add a bus at full volume, or change the voice limit of an existing bus.
+    /// Every bus plays at least one voice.
+    pub fn add_bus(&mut self, name: &str, voice_limit: usize) {
+        let voice_limit = voice_limit.max(1);
+        self.buses
+            .entry(name.to_owned())
+            .or_insert(Bus {
+                volume: 1f32,
+                voice_limit,
+            })
+            .voice_limit = voice_limit;
+        self.limit_voices(name, voice_limit);
+    }
+
+    pub fn bus(&self, name: &str) -> Option<Bus> {
+        self.buses.get(name).cloned()
+    }
+
+    pub fn set_bus_volume(&mut self, name: &str, volume: f32) -> Result<()> {
+        self.bus_mut(name)?.volume = volume;
+        Ok(())
+    }
+
+    pub fn set_voice_limit(&mut self, name: &str, voice_limit: usize) -> Result<()> {
+        self.bus_mut(name)?;
+        self.add_bus(name, voice_limit);
+        Ok(())
+    }
+
+    fn bus_mut(&mut self, name: &str) -> Result<&mut Bus> {
+        self.buses
+            .get_mut(name)
+            .ok_or_else(|| Error::audio(&format!("unknown bus {}", name)))
+    }
+
+    /// Start playing a sound on the bus of `request`. The name of the sound in `request` is not used.
+    pub fn play(&mut self, sound: Arc<SoundData>, request: &PlaySound) -> Result<VoiceId> {
+        let voice_limit = self.bus_mut(&request.bus)?.voice_limit;
+        self.limit_voices(&request.bus, voice_limit - 1);
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        self.voices.push(Voice {
+            id,
+            sound,
+            bus: request.bus.clone(),
+            position: 0f64,
+            volume: request.volume,
+            pitch: request.pitch.max(0f32),
+            looping: request.looping,
+            ducks_music: request.ducks_music,
+            pan: request.pan,
+            emitter: if request.position.is_some() || request.follow.is_some() {
+                Some(Emitter {
+                    offset: request.position.unwrap_or((0f32, 0f32)),
+                    follow: request.follow,
+                    origin: (0f32, 0f32),
+                })
+            } else {
+                None
+            },
+            stereo_gains: None,
+        });
+        Ok(id)
+    }
+
+    /// Set the part of the world positional sounds are heard from, in world coordinates
+    pub fn set_listener(&mut self, camera: Camera, width: f32, height: f32) {
+        self.listener = Listener {
+            camera,
+            width,
+            height,
+        };
+    }
+
+    /// Move the voices that follow an entity to the entity's position.
+    /// Voices whose entity is gone stay where it was last seen.
+    pub fn move_emitters<F: Fn(u64) -> Option<(f32, f32)>>(&mut self, position_of: F) {
+        for voice in &mut self.voices {
+            if let Some(ref mut emitter) = voice.emitter {
+                if let Some(origin) = emitter.follow.and_then(|id| position_of(id)) {
+                    emitter.origin = origin;
+                }
+            }
+        }
+    }
+
+    /// Stop the oldest voices of a bus until at most `limit` are left
+    fn limit_voices(&mut self, bus: &str, limit: usize) {
+        let playing = self.bus_voice_count(bus);
+        let mut excess = playing.saturating_sub(limit);
+        self.voices.retain(|voice| {
+            if excess > 0 && voice.bus == bus {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    pub fn stop(&mut self, id: VoiceId) {
+        self.voices.retain(|voice| voice.id != id);
+    }
+
+    pub fn stop_bus(&mut self, bus: &str) {
+        self.voices.retain(|voice| voice.bus != bus);
+    }
+
+    /// Stop all sounds and the music, without fading out
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+        self.music.stop(0f32, self.sample_rate);
+    }
+
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.voices.iter().any(|voice| voice.id == id)
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn bus_voice_count(&self, bus: &str) -> usize {
+        self.voices.iter().filter(|voice| voice.bus == bus).count()
+    }
+
+    /// Crossfade to a music track on the music bus, see `Audio::play_music`
+    pub fn play_music(&mut self, music: MusicVoice, crossfade: f32) {
+        self.music.play(music, crossfade, self.sample_rate);
+    }
+
+    /// Crossfade to the first track of a playlist, see `Audio::play_playlist`
+    pub fn play_playlist(
+        &mut self,
+        first: MusicVoice,
+        tracks: Vec<MusicTrack>,
+        crossfade: f32,
+        repeat: bool,
+    ) {
+        self.music
+            .play_playlist(first, tracks, crossfade, repeat, self.sample_rate);
+    }
+
+    pub fn stop_music(&mut self, fade_out: f32) {
+        self.music.stop(fade_out, self.sample_rate);
+    }
+
+    /// Whether a track plays on its own, not as part of a playlist
+    pub fn plays_music_track(&self, name: &str) -> bool {
+        self.music.plays_track(name)
+    }
+
+    /// The next playlist track to open, see `MusicPlayer::next_to_queue`
+    pub fn next_music_to_queue(&mut self) -> Option<(MusicTrack, u64)> {
+        self.music.next_to_queue()
+    }
+
+    pub fn queue_music(&mut self, music: MusicVoice, generation: u64) {
+        self.music.queue(music, generation);
+    }
+
+    /// The name of the track that plays, not counting tracks that fade out
+    pub fn current_music(&self) -> Option<&str> {
+        self.music.current_track()
+    }
+
+    pub fn is_music_playing(&self) -> bool {
+        self.music.is_playing()
+    }
+
+    /// How far the music bus is turned down at the moment, 1 when nothing ducks it
+    pub fn duck_gain(&self) -> f32 {
+        self.duck_gain
+    }
+
+    /// Keep an error for `take_errors`, for errors that can not be returned to the game,
+    /// e.g. from the sound device or a music stream that broke while it played
+    pub fn report(&mut self, error: Error) {
+        if self.errors.len() < MAX_AUDIO_ERRORS {
+            self.errors.push(error);
+        }
+    }
+
+    /// The errors that were reported since the last call, the oldest first
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        mem::replace(&mut self.errors, Vec::with_capacity(MAX_AUDIO_ERRORS))
+    }
+
+    /// The ducking gain of the music bus for each frame of the next render
+    fn duck_gains(&mut self, frames: usize) -> Vec<f32> {
+        let ducking = self.ducking;
+        let sample_rate = f64::from(self.sample_rate);
+        // How many frames of this render a sound that ducks the music plays for
+        let ducked_frames = self
+            .voices
+            .iter()
+            .filter(|voice| voice.ducks_music)
+            .map(|voice| {
+                let step =
+                    f64::from(voice.pitch) * f64::from(voice.sound.sample_rate()) / sample_rate;
+                if voice.looping || step <= 0f64 {
+                    frames
+                } else {
+                    ((voice.sound.frames() as f64 - voice.position) / step).ceil() as usize
+                }
+            })
+            .max()
+            .unwrap_or(0);
+        let frames_per_ms = self.sample_rate as f32 / 1000f32;
+        let attack = (1f32 - ducking.volume).abs() / (ducking.attack * frames_per_ms).max(1f32);
+        let release = (1f32 - ducking.volume).abs() / (ducking.release * frames_per_ms).max(1f32);
+        let mut gains = Vec::with_capacity(frames);
+        for frame in 0..frames {
+            self.duck_gain = if frame < ducked_frames {
+                (self.duck_gain - attack).max(ducking.volume)
+            } else {
+                (self.duck_gain + release).min(1f32)
+            };
+            gains.push(self.duck_gain);
+        }
+        gains
+    }
+
+    /// Fill `output` with interleaved stereo samples and advance the voices and the music.
+    /// Voices that reach the end of their sound are removed.
+    pub fn render(&mut self, output: &mut [f32]) {
+        for sample in output.iter_mut() {
+            *sample = 0f32;
+        }
+        let duck_gains = self.duck_gains(output.len() / 2);
+        let sample_rate = f64::from(self.sample_rate);
+        let master_volume = self.master_volume;
+        let buses = &self.buses;
+        let frame_count = (output.len() / 2).max(1) as f32;
+        for voice in &mut self.voices {
+            // Positions change once per frame, fading between them keeps moving sounds from clicking
+            let target = voice.target_gains(&self.listener, &self.spatialization);
+            let (start_left, start_right) = voice.stereo_gains.unwrap_or(target);
+            let fade = (
+                (target.0 - start_left) / frame_count,
+                (target.1 - start_right) / frame_count,
+            );
+            voice.stereo_gains = Some(target);
+            let bus_volume = buses.get(&voice.bus).map(|bus| bus.volume).unwrap_or(0f32);
+            let gain = voice.volume * bus_volume * master_volume;
+            let ducked = voice.bus == MUSIC_BUS;
+            let step = f64::from(voice.pitch) * f64::from(voice.sound.sample_rate()) / sample_rate;
+            let frames = voice.sound.frames() as f64;
+            for (index, frame) in output.chunks_mut(2).enumerate() {
+                if voice.position >= frames {
+                    if !voice.looping || frames == 0f64 {
+                        break;
+                    }
+                    voice.position %= frames;
+                }
+                let (left, right) = voice.sound.sample_at(voice.position, voice.looping);
+                let gain = if ducked {
+                    gain * duck_gains[index]
+                } else {
+                    gain
+                };
+                let progress = (index + 1) as f32;
+                frame[0] += left * gain * (start_left + fade.0 * progress);
+                if frame.len() > 1 {
+                    frame[1] += right * gain * (start_right + fade.1 * progress);
+                }
+                voice.position += step;
+            }
+        }
+
+        let music_volume =
+            buses.get(MUSIC_BUS).map(|bus| bus.volume).unwrap_or(0f32) * master_volume;
+        let music_gains = duck_gains
+            .iter()
+            .map(|duck| duck * music_volume)
+            .collect::<Vec<_>>();
+        self.music.render(output, &music_gains);
+        self.voices.retain(|voice| {
+            let frames = voice.sound.frames() as f64;
+            frames > 0f64 && (voice.looping || voice.position < frames)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mixer, MAX_AUDIO_ERRORS, SFX_BUS};
+    use error::Error;
+    use std::sync::Arc;
+    use {Camera, PlaySound, SoundData};
+
+    /// A mono sound at the sample rate of the test mixers
+    fn sound(samples: Vec<f32>) -> Arc<SoundData> {
+        Arc::new(SoundData::new(samples, 1, 100).unwrap())
+    }
+
+    fn render(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        let mut output = vec![0f32; frames * 2];
+        mixer.render(&mut output);
+        output
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_oldest_voice_is_stopped_at_the_voice_limit() {
+        let mut mixer = Mixer::new(100);
+        mixer.add_bus("test", 2);
+        let request = PlaySound::new("beep").on_bus("test");
+        let first = mixer.play(sound(vec![0.5; 10]), &request).unwrap();
+        let second = mixer.play(sound(vec![0.5; 10]), &request).unwrap();
+        let third = mixer.play(sound(vec![0.5; 10]), &request).unwrap();
+        mixer
+            .play(sound(vec![0.5; 10]), &PlaySound::new("beep"))
+            .unwrap();
+
+        assert!(!mixer.is_playing(first));
+        assert!(mixer.is_playing(second));
+        assert!(mixer.is_playing(third));
+        assert_eq!(mixer.bus_voice_count("test"), 2);
+        assert_eq!(mixer.bus_voice_count(SFX_BUS), 1);
+    }
+
+    #[test]
+    fn lowering_the_voice_limit_stops_the_oldest_voices() {
+        let mut mixer = Mixer::new(100);
+        let request = PlaySound::new("beep");
+        let ids = (0..3)
+            .map(|_| mixer.play(sound(vec![0.5; 10]), &request).unwrap())
+            .collect::<Vec<_>>();
+        mixer.set_voice_limit(SFX_BUS, 1).unwrap();
+
+        assert!(!mixer.is_playing(ids[0]));
+        assert!(!mixer.is_playing(ids[1]));
+        assert!(mixer.is_playing(ids[2]));
+    }
+
+    #[test]
+    fn bus_and_master_volume_scale_the_voices() {
+        let mut mixer = Mixer::new(100);
+        mixer.master_volume = 0.5;
+        mixer.set_bus_volume(SFX_BUS, 0.5).unwrap();
+        let request = PlaySound::new("beep").with_volume(0.8);
+        mixer.play(sound(vec![1f32; 10]), &request).unwrap();
+
+        for sample in render(&mut mixer, 4) {
+            assert_close(sample, 0.2);
+        }
+        assert!(mixer.set_bus_volume("unknown", 1f32).is_err());
+    }
+
+    #[test]
+    fn pitch_steps_through_the_sound_faster() {
+        let mut mixer = Mixer::new(100);
+        let samples = (0..8).map(|i| i as f32 / 8f32).collect();
+        let id = mixer
+            .play(sound(samples), &PlaySound::new("ramp").with_pitch(2f32))
+            .unwrap();
+
+        let output = render(&mut mixer, 6);
+        let left = output.iter().step_by(2).cloned().collect::<Vec<_>>();
+        for (actual, expected) in left.iter().zip(&[0f32, 0.25, 0.5, 0.75, 0f32, 0f32]) {
+            assert_close(*actual, *expected);
+        }
+        assert!(!mixer.is_playing(id));
+    }
+
+    #[test]
+    fn lower_sample_rates_are_interpolated() {
+        let mut mixer = Mixer::new(200);
+        mixer
+            .play(sound(vec![0f32, 1f32, 0f32]), &PlaySound::new("ramp"))
+            .unwrap();
+
+        let output = render(&mut mixer, 4);
+        let left = output.iter().step_by(2).cloned().collect::<Vec<_>>();
+        for (actual, expected) in left.iter().zip(&[0f32, 0.5, 1f32, 0.5]) {
+            assert_close(*actual, *expected);
+        }
+    }
+
+    #[test]
+    fn looping_voices_start_over() {
+        let mut mixer = Mixer::new(100);
+        let id = mixer
+            .play(sound(vec![0.25, 0.5]), &PlaySound::new("loop").looping())
+            .unwrap();
+
+        let output = render(&mut mixer, 5);
+        let left = output.iter().step_by(2).cloned().collect::<Vec<_>>();
+        for (actual, expected) in left.iter().zip(&[0.25, 0.5, 0.25, 0.5, 0.25]) {
+            assert_close(*actual, *expected);
+        }
+        assert!(mixer.is_playing(id));
+    }
+
+    /// The left and right level of a constant sound at a position, on a 100 by 100 screen
+    fn levels_at(camera: Camera, x: f32, y: f32) -> (f32, f32) {
+        let mut mixer = Mixer::new(100);
+        mixer.set_listener(camera, 100f32, 100f32);
+        mixer
+            .play(sound(vec![1f32; 10]), &PlaySound::new("beep").at(x, y))
+            .unwrap();
+        let output = render(&mut mixer, 1);
+        (output[0], output[1])
+    }
+
+    #[test]
+    fn positional_sounds_are_panned_to_their_side_of_the_screen() {
+        let (left, right) = levels_at(Camera::default(), 0f32, 50f32);
+        assert_close(left, 1f32);
+        assert_close(right, 0.2);
+
+        let (left, right) = levels_at(Camera::default(), 100f32, 50f32);
+        assert_close(left, 0.2);
+        assert_close(right, 1f32);
+
+        let (left, right) = levels_at(Camera::default(), 50f32, 50f32);
+        assert_close(left, 1f32);
+        assert_close(right, 1f32);
+    }
+
+    #[test]
+    fn positional_sounds_are_heard_from_the_camera() {
+        let camera = Camera { x: 100f32, y: 0f32 };
+        let (left, right) = levels_at(camera, 100f32, 50f32);
+        assert_close(left, 1f32);
+        assert_close(right, 0.2);
+    }
+
+    #[test]
+    fn positional_sounds_fade_out_outside_of_the_screen() {
+        let falloff = Mixer::new(100).spatialization.falloff;
+        let (left, right) = levels_at(Camera::default(), -falloff / 2f32, 50f32);
+        assert_close(left, 0.5);
+        assert_close(right, 0.1);
+
+        assert_eq!(levels_at(Camera::default(), -falloff, 50f32), (0f32, 0f32));
+        assert_eq!(
+            levels_at(Camera::default(), 50f32, 100f32 + falloff),
+            (0f32, 0f32)
+        );
+    }
+
+    #[test]
+    fn following_sounds_move_with_their_entity() {
+        let mut mixer = Mixer::new(100);
+        mixer.set_listener(Camera::default(), 100f32, 100f32);
+        let request = PlaySound::new("engine").at(0f32, 50f32).following(7);
+        mixer.play(sound(vec![1f32; 100]), &request).unwrap();
+        let output = render(&mut mixer, 4);
+        assert_close(output[6], 1f32);
+        assert_close(output[7], 0.2);
+
+        mixer.move_emitters(|entity| {
+            if entity == 7 {
+                Some((100f32, 0f32))
+            } else {
+                None
+            }
+        });
+        let output = render(&mut mixer, 4);
+        // The gains fade to the new position over the render, so the move does not click
+        assert!(output[0] < 1f32 && output[1] > 0.2);
+        assert_close(output[6], 0.2);
+        assert_close(output[7], 1f32);
+    }
+
+    #[test]
+    fn errors_are_kept_until_they_are_taken() {
+        let mut mixer = Mixer::new(100);
+        for _ in 0..MAX_AUDIO_ERRORS + 4 {
+            mixer.report(Error::audio("broken"));
+        }
+
+        assert_eq!(mixer.take_errors().len(), MAX_AUDIO_ERRORS);
+        assert!(mixer.take_errors().is_empty());
+    }
+}
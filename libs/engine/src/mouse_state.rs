@@ -0,0 +1,165 @@
+use super::Camera;
+use glium::glutin::MouseButton;
+use std::mem;
+
+/// How many pixels of a touchpad scroll count as one line of a mouse wheel
+const PIXELS_PER_LINE: f32 = 16f32;
+
+#[derive(Default)]
+pub struct MouseState {
+    position: (f32, f32),
+    last_position: (f32, f32),
+    /// The camera of the last update, to translate the cursor into world coordinates
+    camera: Camera,
+    inside: bool,
+    entered_this_frame: bool,
+    left_this_frame: bool,
+    wheel_delta: (f32, f32),
+    pressed_buttons: Vec<MouseButton>,
+    pressed_buttons_this_frame: Vec<MouseButton>,
+    released_buttons_this_frame: Vec<MouseButton>,
+}
+
+impl MouseState {
+    pub fn frame_start(&mut self) {
+        self.last_position = self.position;
+        self.entered_this_frame = false;
+        self.left_this_frame = false;
+        self.wheel_delta = (0f32, 0f32);
+        self.pressed_buttons_this_frame.clear();
+        self.released_buttons_this_frame.clear();
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.position = (x, y);
+    }
+
+    /// Set the position from window coordinates. The screen is stretched over the window,
+    /// so the cursor is scaled by the size of the screen relative to the window.
+    pub fn set_window_position(
+        &mut self,
+        x: f32,
+        y: f32,
+        window_size: (f32, f32),
+        screen_size: (f32, f32),
+    ) {
+        if window_size.0 <= 0f32 || window_size.1 <= 0f32 {
+            // A minimized window has no size to scale by
+            return self.set_position(x, y);
+        }
+        self.set_position(
+            x * screen_size.0 / window_size.0,
+            y * screen_size.1 / window_size.1,
+        );
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
+
+    pub fn set_inside(&mut self, inside: bool) {
+        if inside && !self.inside {
+            self.entered_this_frame = true;
+        } else if !inside && self.inside {
+            self.left_this_frame = true;
+        }
+        self.inside = inside;
+    }
+
+    pub fn set_button_down(&mut self, button: MouseButton) {
+        if !self.is_button_down(button) {
+            self.pressed_buttons.push(button);
+            self.pressed_buttons_this_frame.push(button);
+        }
+    }
+
+    pub fn clear_button_down(&mut self, button: MouseButton) {
+        self.pressed_buttons.retain(|b| *b != button);
+        self.released_buttons_this_frame.push(button);
+    }
+
+    /// Release every held button, e.g. when the window loses the focus and will not see the releases
+    pub fn release_all(&mut self) {
+        let buttons = mem::replace(&mut self.pressed_buttons, Vec::new());
+        self.released_buttons_this_frame.extend(buttons);
+    }
+
+    /// Add a wheel movement in lines
+    pub fn scroll_lines(&mut self, x: f32, y: f32) {
+        self.wheel_delta.0 += x;
+        self.wheel_delta.1 += y;
+    }
+
+    /// Add a touchpad movement in pixels
+    pub fn scroll_pixels(&mut self, x: f32, y: f32) {
+        self.scroll_lines(x / PIXELS_PER_LINE, y / PIXELS_PER_LINE);
+    }
+
+    /// The cursor position in screen coordinates. When the cursor is outside of the window,
+    /// this is where it left.
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// The cursor position in world coordinates, using the camera of the current frame
+    pub fn world_position(&self) -> (f32, f32) {
+        self.camera
+            .screen_to_world(self.position.0, self.position.1)
+    }
+
+    pub fn moved_this_frame(&self) -> bool {
+        self.position != self.last_position
+    }
+
+    pub fn is_inside(&self) -> bool {
+        self.inside
+    }
+
+    pub fn entered_this_frame(&self) -> bool {
+        self.entered_this_frame
+    }
+
+    pub fn left_this_frame(&self) -> bool {
+        self.left_this_frame
+    }
+
+    /// How far the wheel moved this frame, in lines. Positive `y` is away from the user.
+    pub fn wheel_delta(&self) -> (f32, f32) {
+        self.wheel_delta
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.iter().any(|b| *b == button)
+    }
+
+    pub fn is_pressed_this_frame(&self, button: MouseButton) -> bool {
+        self.pressed_buttons_this_frame.iter().any(|b| *b == button)
+    }
+
+    pub fn is_released_this_frame(&self, button: MouseButton) -> bool {
+        self.released_buttons_this_frame
+            .iter()
+            .any(|b| *b == button)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MouseState;
+
+    #[test]
+    fn the_cursor_is_scaled_to_the_screen() {
+        let mut mouse = MouseState::default();
+        mouse.set_window_position(800f32, 150f32, (1600f32, 1200f32), (800f32, 600f32));
+        assert_eq!(mouse.position(), (400f32, 75f32));
+        mouse.set_window_position(100f32, 100f32, (400f32, 400f32), (800f32, 600f32));
+        assert_eq!(mouse.position(), (200f32, 150f32));
+    }
+
+    #[test]
+    fn the_cursor_is_not_scaled_by_an_empty_window() {
+        let mut mouse = MouseState::default();
+        mouse.set_window_position(10f32, 20f32, (0f32, 0f32), (800f32, 600f32));
+        assert_eq!(mouse.position(), (10f32, 20f32));
+    }
+}
@@ -0,0 +1,830 @@
+use lewton::inside_ogg::OggStreamReader;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::error::Error;
+use super::{Result, SoundData};
+
+/// How long one track fades into the next by default, in milliseconds
+pub const DEFAULT_CROSSFADE: f32 = 1000f32;
+
+/// How far ahead of the playback music is decoded, in milliseconds.
+/// `Audio::update` has to be called at least this often, or the music breaks up.
+pub const MUSIC_BUFFER_AHEAD: f32 = 1000f32;
+
+/// How many frames a `SoundStream` hands out at once
+const CHUNK_FRAMES: usize = 4096;
+
+/// How many frames further back an `OggStream` seek starts when the page it found ends too late
+const SEEK_BACKOFF: u64 = 16_384;
+
+/// Audio that is decoded while it plays, so long tracks do not have to be kept in memory
+pub trait SampleStream: Send {
+    fn channels(&self) -> usize;
+
+    fn sample_rate(&self) -> u32;
+
+    /// The next interleaved samples, or `None` at the end of the stream
+    fn read(&mut self) -> Result<Option<Vec<f32>>>;
+
+    /// Continue at a frame, returns false when the stream is shorter
+    fn seek(&mut self, frame: u64) -> Result<bool>;
+}
+
+/// Decodes an OGG Vorbis file one packet at a time
+pub struct OggStream {
+    file: PathBuf,
+    reader: OggStreamReader<BufReader<File>>,
+    /// The rest of the packet a seek ended in
+    pending: Option<Vec<f32>>,
+    /// The last frame that was seeked to, with the granule position the seek started from
+    seek_start: Option<(u64, u64)>,
+}
+
+impl OggStream {
+    pub fn open<P: AsRef<Path>>(file: P) -> Result<OggStream> {
+        let file = file.as_ref().to_path_buf();
+        let reader = OggStream::reader(&file)?;
+        Ok(OggStream {
+            file,
+            reader,
+            pending: None,
+            seek_start: None,
+        })
+    }
+
+    fn reader(file: &Path) -> Result<OggStreamReader<BufReader<File>>> {
+        OggStreamReader::new(BufReader::new(File::open(file)?))
+            .map_err(|e| Error::audio(&format!("{}: {}", file.display(), e)))
+    }
+
+    fn read_packet(&mut self) -> Result<Option<Vec<f32>>> {
+        loop {
+            let packet = self
+                .reader
+                .read_dec_packet_itl()
+                .map_err(|e| Error::audio(&e.to_string()))?;
+            match packet {
+                // The first packet of a stream, and after a seek, only primes the decoder
+                Some(ref samples) if samples.is_empty() => continue,
+                Some(samples) => {
+                    return Ok(Some(
+                        samples
+                            .into_iter()
+                            .map(|s| f32::from(s) / 32768f32)
+                            .collect(),
+                    ))
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Seek to the page around a granule position, and decode until the end of the page.
+    /// Returns the frame that is read next, or `None` at the end of the stream.
+    fn seek_page(&mut self, granule: u64) -> Result<Option<u64>> {
+        self.reader
+            .seek_absgp_pg(granule)
+            .map_err(|e| Error::audio(&e.to_string()))?;
+        loop {
+            if self.read_packet()?.is_none() {
+                return Ok(None);
+            }
+            if let Some(position) = self.reader.get_last_absgp() {
+                return Ok(Some(position));
+            }
+        }
+    }
+
+    /// Decode from `position` until `frame`, and keep the rest of that packet for `read`
+    fn skip_to(&mut self, mut position: u64, frame: u64) -> Result<bool> {
+        let channels = self.channels() as u64;
+        loop {
+            let samples = match self.read_packet()? {
+                Some(samples) => samples,
+                None => return Ok(false),
+            };
+            let frames = samples.len() as u64 / channels;
+            if position + frames > frame {
+                let start = ((frame - position) * channels) as usize;
+                self.pending = Some(samples[start..].to_vec());
+                return Ok(true);
+            }
+            position += frames;
+        }
+    }
+}
+
+impl SampleStream for OggStream {
+    fn channels(&self) -> usize {
+        self.reader.ident_hdr.audio_channels as usize
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    fn read(&mut self) -> Result<Option<Vec<f32>>> {
+        match self.pending.take() {
+            Some(samples) => Ok(Some(samples)),
+            None => self.read_packet(),
+        }
+    }
+
+    /// lewton only seeks to the start of a page, and the frame a page starts at is only known once it ends.
+    /// So the seek starts a page early, further back when that page still ends after the frame.
+    /// Where it started is kept, so a track that loops finds its loop start with one page seek.
+    fn seek(&mut self, frame: u64) -> Result<bool> {
+        self.pending = None;
+        let mut start = match self.seek_start {
+            Some((seeked, start)) if seeked == frame => start,
+            _ => frame,
+        };
+        loop {
+            if start == 0 {
+                self.reader = OggStream::reader(&self.file)?;
+                self.seek_start = Some((frame, 0));
+                return self.skip_to(0, frame);
+            }
+            match self.seek_page(start) {
+                Ok(Some(position)) if position <= frame => {
+                    self.seek_start = Some((frame, start));
+                    return self.skip_to(position, frame);
+                }
+                // Pages close to the start can hold the headers, which do not decode as audio
+                _ => start = start.saturating_sub(SEEK_BACKOFF),
+            }
+        }
+    }
+}
+
+/// Hands out a decoded sound in chunks, for short tracks and WAV files
+pub struct SoundStream {
+    sound: Arc<SoundData>,
+    frame: usize,
+}
+
+impl SoundStream {
+    pub fn new(sound: Arc<SoundData>) -> SoundStream {
+        SoundStream { sound, frame: 0 }
+    }
+}
+
+impl SampleStream for SoundStream {
+    fn channels(&self) -> usize {
+        self.sound.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sound.sample_rate()
+    }
+
+    fn read(&mut self) -> Result<Option<Vec<f32>>> {
+        let channels = self.sound.channels();
+        let samples = self.sound.samples();
+        let start = self.frame * channels;
+        if start >= samples.len() {
+            return Ok(None);
+        }
+        let end = (start + CHUNK_FRAMES * channels).min(samples.len());
+        self.frame += (end - start) / channels;
+        Ok(Some(samples[start..end].to_vec()))
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<bool> {
+        if frame >= self.sound.frames() as u64 {
+            return Ok(false);
+        }
+        self.frame = frame as usize;
+        Ok(true)
+    }
+}
+
+/// Where the samples of a track come from
+#[derive(Clone)]
+pub enum MusicSource {
+    /// Streamed from the file while it plays
+    Ogg(PathBuf),
+    Sound(Arc<SoundData>),
+}
+
+impl MusicSource {
+    pub fn open(&self) -> Result<Box<dyn SampleStream>> {
+        Ok(match self {
+            MusicSource::Ogg(file) => Box::new(OggStream::open(file)?),
+            MusicSource::Sound(sound) => Box::new(SoundStream::new(sound.clone())),
+        })
+    }
+}
+
+/// A piece of music, with the part that repeats when it loops
+#[derive(Clone)]
+pub struct MusicTrack {
+    pub name: String,
+    pub source: MusicSource,
+    /// The frame a looping track continues at when it reaches `loop_end`, e.g. to skip an intro
+    pub loop_start: u64,
+    /// The frame after the last one that loops, the end of the track when `None`
+    pub loop_end: Option<u64>,
+}
+
+impl MusicTrack {
+    pub fn new(name: &str, source: MusicSource) -> MusicTrack {
+        MusicTrack {
+            name: name.to_owned(),
+            source,
+            loop_start: 0,
+            loop_end: None,
+        }
+    }
+}
+
+/// A request to fade to another track, e.g. when the scene changes
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayMusic {
+    pub track: String,
+    /// How long the old track fades out while this one fades in, in milliseconds
+    pub crossfade: f32,
+    pub looping: bool,
+}
+
+impl PlayMusic {
+    /// Loop a track, fading over from the current one
+    pub fn new(track: &str) -> PlayMusic {
+        PlayMusic {
+            track: track.to_owned(),
+            crossfade: DEFAULT_CROSSFADE,
+            looping: true,
+        }
+    }
+
+    pub fn with_crossfade(mut self, crossfade: f32) -> Self {
+        self.crossfade = crossfade;
+        self
+    }
+
+    /// Play the track once instead of looping it
+    pub fn once(mut self) -> Self {
+        self.looping = false;
+        self
+    }
+}
+
+/// Tracks that play one after the other, from start to end and without a gap
+#[derive(Clone, Debug, PartialEq)]
+pub struct Playlist {
+    pub tracks: Vec<String>,
+    /// How long the current music fades out while the first track fades in, in milliseconds
+    pub crossfade: f32,
+    /// Start over with the first track after the last one
+    pub repeat: bool,
+}
+
+impl Playlist {
+    pub fn new(tracks: &[&str]) -> Playlist {
+        Playlist {
+            tracks: tracks.iter().map(|t| (*t).to_owned()).collect(),
+            crossfade: DEFAULT_CROSSFADE,
+            repeat: true,
+        }
+    }
+
+    pub fn with_crossfade(mut self, crossfade: f32) -> Self {
+        self.crossfade = crossfade;
+        self
+    }
+
+    pub fn once(mut self) -> Self {
+        self.repeat = false;
+        self
+    }
+}
+
+/// What entities can ask of the music, with `EntityEvent::Music`
+#[derive(Clone, Debug, PartialEq)]
+pub enum MusicCommand {
+    Play(PlayMusic),
+    Playlist(Playlist),
+    /// Fade out the music over this many milliseconds
+    Stop(f32),
+}
+
+/// The decoded frames of a track that were not played yet
+struct TrackBuffer {
+    frames: VecDeque<(f32, f32)>,
+    /// The decoder reached the end of the track, or failed
+    ended: bool,
+}
+
+impl TrackBuffer {
+    /// The next frame, silence while the decoder falls behind, or `None` at the end of the track
+    fn next_frame(&mut self) -> Option<(f32, f32)> {
+        match self.frames.pop_front() {
+            Some(frame) => Some(frame),
+            None if self.ended => None,
+            None => Some((0f32, 0f32)),
+        }
+    }
+}
+
+/// Decodes a track ahead of the `MusicVoice` that plays it, so the audio thread never waits for a file.
+/// `Audio` keeps the decoders and fills them on every update.
+pub struct MusicDecoder {
+    stream: Box<dyn SampleStream>,
+    channels: usize,
+    /// The frame of the track that is decoded next
+    frame: u64,
+    /// The loop start and end, when the track loops
+    loop_points: Option<(u64, Option<u64>)>,
+    /// How many frames are kept decoded
+    ahead: usize,
+    buffer: Arc<Mutex<TrackBuffer>>,
+}
+
+impl MusicDecoder {
+    /// Decode until the buffer is full. Returns false once the decoder is no longer needed,
+    /// because the track ended or its voice stopped.
+    pub fn fill(&mut self) -> Result<bool> {
+        loop {
+            // Only the decoder holds the buffer once the mixer dropped the voice
+            if Arc::strong_count(&self.buffer) < 2 {
+                return Ok(false);
+            }
+            {
+                let buffer = self.buffer.lock().unwrap();
+                if buffer.ended {
+                    return Ok(false);
+                }
+                if buffer.frames.len() >= self.ahead {
+                    return Ok(true);
+                }
+            }
+            // The buffer is not locked while decoding, so the mixer can keep playing
+            let decoded = self.decode();
+            let mut buffer = self.buffer.lock().unwrap();
+            match decoded {
+                Ok(Some(frames)) => buffer.frames.extend(frames),
+                Ok(None) => buffer.ended = true,
+                Err(error) => {
+                    buffer.ended = true;
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    /// The next frames of the track as it plays, looped, or `None` at its end
+    fn decode(&mut self) -> Result<Option<Vec<(f32, f32)>>> {
+        loop {
+            if let Some((start, Some(end))) = self.loop_points {
+                if self.frame >= end && !self.seek(start)? {
+                    return Ok(None);
+                }
+            }
+            let samples = match self.stream.read()? {
+                Some(samples) => samples,
+                None => match self.loop_points {
+                    // Without a loop end, the track loops at its end
+                    Some((start, _)) if self.frame > start => {
+                        if !self.seek(start)? {
+                            return Ok(None);
+                        }
+                        continue;
+                    }
+                    _ => return Ok(None),
+                },
+            };
+            let channels = self.channels;
+            let mut frames = samples
+                .chunks(channels)
+                .filter(|frame| frame.len() == channels)
+                .map(|frame| (frame[0], if channels > 1 { frame[1] } else { frame[0] }))
+                .collect::<Vec<_>>();
+            if let Some((_, Some(end))) = self.loop_points {
+                frames.truncate(end.saturating_sub(self.frame) as usize);
+            }
+            self.frame += frames.len() as u64;
+            if !frames.is_empty() {
+                return Ok(Some(frames));
+            }
+        }
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<bool> {
+        self.frame = frame;
+        self.stream.seek(frame)
+    }
+}
+
+/// A track that is playing, or fading out
+pub struct MusicVoice {
+    track: String,
+    buffer: Arc<Mutex<TrackBuffer>>,
+    /// How many frames of the track one output frame advances
+    step: f64,
+    current: Option<(f32, f32)>,
+    next: Option<(f32, f32)>,
+    fraction: f64,
+    gain: f32,
+    /// How much the gain changes per output frame, negative while fading out
+    fade: f32,
+    finished: bool,
+}
+
+impl MusicVoice {
+    /// Open a track and decode its start, with the decoder that keeps the voice supplied
+    pub fn open(
+        track: &MusicTrack,
+        looping: bool,
+        sample_rate: u32,
+    ) -> Result<(MusicVoice, MusicDecoder)> {
+        let stream = track.source.open()?;
+        let channels = stream.channels();
+        if channels == 0 || stream.sample_rate() == 0 {
+            return Err(Error::audio(&format!("{} has no audio", track.name)));
+        }
+        let ahead = (f64::from(stream.sample_rate()) * f64::from(MUSIC_BUFFER_AHEAD) / 1000f64)
+            .ceil() as usize;
+        // Room for one more chunk, so the buffer does not grow while the mixer plays from it
+        let buffer = Arc::new(Mutex::new(TrackBuffer {
+            frames: VecDeque::with_capacity(ahead + CHUNK_FRAMES),
+            ended: false,
+        }));
+        let mut voice = MusicVoice {
+            track: track.name.clone(),
+            buffer: buffer.clone(),
+            step: f64::from(stream.sample_rate()) / f64::from(sample_rate),
+            current: None,
+            next: None,
+            fraction: 0f64,
+            gain: 1f32,
+            fade: 0f32,
+            finished: false,
+        };
+        let mut decoder = MusicDecoder {
+            stream,
+            channels,
+            frame: 0,
+            loop_points: if looping {
+                Some((track.loop_start, track.loop_end))
+            } else {
+                None
+            },
+            ahead: ahead.max(1),
+            buffer,
+        };
+        decoder.fill()?;
+        {
+            let mut buffer = voice.buffer.lock().unwrap();
+            voice.current = buffer.next_frame();
+            voice.next = buffer.next_frame();
+        }
+        Ok((voice, decoder))
+    }
+
+    fn fade_out(&mut self, frames: f32) {
+        self.fade = -self.gain / frames.max(1f32);
+    }
+
+    fn is_fading_out(&self) -> bool {
+        self.fade < 0f32
+    }
+
+    /// Add the voice to interleaved stereo samples, with a gain per frame.
+    /// Returns how many frames were rendered before the track ended.
+    fn render(&mut self, output: &mut [f32], gains: &[f32]) -> usize {
+        let mut buffer = self.buffer.lock().unwrap();
+        for (index, frame) in output.chunks_mut(2).enumerate() {
+            let current = match self.current {
+                Some(current) => current,
+                None => {
+                    self.finished = true;
+                    return index;
+                }
+            };
+            let next = self.next.unwrap_or((0f32, 0f32));
+            let fraction = self.fraction as f32;
+            let gain = self.gain * gains[index];
+            frame[0] += (current.0 + (next.0 - current.0) * fraction) * gain;
+            if frame.len() > 1 {
+                frame[1] += (current.1 + (next.1 - current.1) * fraction) * gain;
+            }
+            self.gain = (self.gain + self.fade).max(0f32).min(1f32);
+            if self.is_fading_out() && self.gain <= 0f32 {
+                self.finished = true;
+                return index + 1;
+            }
+            self.fraction += self.step;
+            while self.fraction >= 1f64 {
+                self.fraction -= 1f64;
+                self.current = self.next;
+                self.next = match self.current {
+                    Some(_) => buffer.next_frame(),
+                    None => None,
+                };
+            }
+        }
+        output.len() / 2
+    }
+}
+
+/// The current track, the tracks that are fading out and what comes next in the playlist
+#[derive(Default)]
+pub struct MusicPlayer {
+    /// The current track last
+    voices: Vec<MusicVoice>,
+    playlist: Vec<MusicTrack>,
+    /// The track of the playlist that is queued next
+    next_track: usize,
+    repeat: bool,
+    /// The next track of the playlist, decoded ahead to start where the current one ends
+    queued: Option<MusicVoice>,
+    /// Counts the changes of the music, so a track that was opened for an old playlist is not queued
+    generation: u64,
+}
+
+impl MusicPlayer {
+    /// Whether a track plays on its own, not as part of a playlist
+    pub fn plays_track(&self, name: &str) -> bool {
+        self.playlist.is_empty() && self.current_track() == Some(name)
+    }
+
+    /// Crossfade to a track
+    pub fn play(&mut self, voice: MusicVoice, crossfade: f32, sample_rate: u32) {
+        self.clear_playlist();
+        self.start(voice, crossfade, sample_rate);
+    }
+
+    /// Crossfade to the first track of a playlist, the voice of which is already opened
+    pub fn play_playlist(
+        &mut self,
+        first: MusicVoice,
+        tracks: Vec<MusicTrack>,
+        crossfade: f32,
+        repeat: bool,
+        sample_rate: u32,
+    ) {
+        self.clear_playlist();
+        self.start(first, crossfade, sample_rate);
+        self.playlist = tracks;
+        self.next_track = 1;
+        self.repeat = repeat;
+    }
+
+    pub fn stop(&mut self, fade_out: f32, sample_rate: u32) {
+        self.clear_playlist();
+        let frames = fade_out * sample_rate as f32 / 1000f32;
+        if frames < 1f32 {
+            self.voices.clear();
+        }
+        for voice in &mut self.voices {
+            voice.fade_out(frames);
+        }
+    }
+
+    fn clear_playlist(&mut self) {
+        self.playlist.clear();
+        self.queued = None;
+        self.generation += 1;
+    }
+
+    fn start(&mut self, mut voice: MusicVoice, crossfade: f32, sample_rate: u32) {
+        let frames = crossfade * sample_rate as f32 / 1000f32;
+        if frames < 1f32 {
+            self.voices.clear();
+        } else {
+            for voice in &mut self.voices {
+                voice.fade_out(frames);
+            }
+            voice.gain = 0f32;
+            voice.fade = 1f32 / frames;
+        }
+        self.voices.push(voice);
+    }
+
+    /// The name of the track that plays, or fades in
+    pub fn current_track(&self) -> Option<&str> {
+        self.voices
+            .last()
+            .filter(|voice| !voice.is_fading_out())
+            .map(|voice| voice.track.as_str())
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.voices.is_empty()
+    }
+
+    /// The track of the playlist to open and `queue` next, when none is queued.
+    /// Comes with the generation `queue` expects.
+    pub fn next_to_queue(&mut self) -> Option<(MusicTrack, u64)> {
+        if self.queued.is_some() || self.playlist.is_empty() {
+            return None;
+        }
+        if self.next_track >= self.playlist.len() {
+            if !self.repeat {
+                self.playlist.clear();
+                return None;
+            }
+            self.next_track = 0;
+        }
+        let track = self.playlist[self.next_track].clone();
+        self.next_track += 1;
+        Some((track, self.generation))
+    }
+
+    /// Play a voice from `next_to_queue` when the current track ends, unless the music changed since
+    pub fn queue(&mut self, voice: MusicVoice, generation: u64) {
+        if generation == self.generation {
+            self.queued = Some(voice);
+        }
+    }
+
+    /// Add the music to interleaved stereo samples, with a gain per frame for the bus volume and ducking
+    pub fn render(&mut self, output: &mut [f32], gains: &[f32]) {
+        // A track that was queued after the last one ended starts right away
+        if self.current_track().is_none() {
+            if let Some(voice) = self.queued.take() {
+                self.voices.push(voice);
+            }
+        }
+        let count = self.voices.len();
+        let mut ended_at = None;
+        for (index, voice) in self.voices.iter_mut().enumerate() {
+            let rendered = voice.render(output, gains);
+            if index + 1 == count && voice.finished && !voice.is_fading_out() {
+                ended_at = Some(rendered);
+            }
+        }
+        // The next track of the playlist starts where the current one ended
+        if let Some(rendered) = ended_at {
+            if let Some(mut voice) = self.queued.take() {
+                voice.render(&mut output[rendered * 2..], &gains[rendered..]);
+                self.voices.push(voice);
+            }
+        }
+        self.voices.retain(|voice| !voice.finished);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MusicSource, MusicTrack, MusicVoice};
+    use std::sync::Arc;
+    use {Audio, Mixer, NullOutput, PlayMusic, PlaySound, Playlist, SoundData};
+
+    /// Audio that mixes at 100 frames per second, so a frame is 10 milliseconds
+    fn audio() -> Audio {
+        Audio::new(Mixer::new(100), Box::new(NullOutput::default()))
+    }
+
+    fn mono(samples: Vec<f32>) -> SoundData {
+        SoundData::new(samples, 1, 100).unwrap()
+    }
+
+    fn left(samples: &[f32]) -> Vec<f32> {
+        samples.iter().step_by(2).cloned().collect()
+    }
+
+    fn assert_samples(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (index, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+            assert!(
+                (actual - expected).abs() < 1e-4,
+                "frame {} is {} instead of {}",
+                index,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn crossfades_ramp_one_track_down_and_the_other_up() {
+        let mut audio = audio();
+        // The first track plays on the left, the second on the right
+        let first = (0..1000).map(|i| if i % 2 == 0 { 1f32 } else { 0f32 });
+        let second = (0..1000).map(|i| if i % 2 == 0 { 0f32 } else { 1f32 });
+        audio.add_music("first", SoundData::new(first.collect(), 2, 100).unwrap());
+        audio.add_music("second", SoundData::new(second.collect(), 2, 100).unwrap());
+        audio
+            .play_music(&PlayMusic::new("first").with_crossfade(0f32))
+            .unwrap();
+        audio.render(2);
+        audio
+            .play_music(&PlayMusic::new("second").with_crossfade(100f32))
+            .unwrap();
+
+        let samples = audio.render(12);
+        let fade_out = (0..12)
+            .map(|i| (1f32 - i as f32 / 10f32).max(0f32))
+            .collect::<Vec<_>>();
+        let fade_in = (0..12)
+            .map(|i| (i as f32 / 10f32).min(1f32))
+            .collect::<Vec<_>>();
+        assert_samples(&left(&samples), &fade_out);
+        assert_samples(&left(&samples[1..]), &fade_in);
+        assert_eq!(audio.mixer().current_music(), Some("second"));
+    }
+
+    #[test]
+    fn loops_continue_at_the_loop_start() {
+        let mut audio = audio();
+        audio.add_music("ramp", mono((0..8).map(|i| i as f32 / 8f32).collect()));
+        audio.set_loop_points("ramp", 2, Some(6)).unwrap();
+        audio
+            .play_music(&PlayMusic::new("ramp").with_crossfade(0f32))
+            .unwrap();
+
+        let expected = [0, 1, 2, 3, 4, 5, 2, 3, 4, 5, 2, 3]
+            .iter()
+            .map(|i| *i as f32 / 8f32)
+            .collect::<Vec<_>>();
+        assert_samples(&left(&audio.render(12)), &expected);
+    }
+
+    #[test]
+    fn loops_without_an_end_continue_at_the_end_of_the_track() {
+        let mut audio = audio();
+        audio.add_music("ramp", mono((0..5).map(|i| i as f32 / 8f32).collect()));
+        audio.set_loop_points("ramp", 2, None).unwrap();
+        audio
+            .play_music(&PlayMusic::new("ramp").with_crossfade(0f32))
+            .unwrap();
+
+        let expected = [0, 1, 2, 3, 4, 2, 3, 4, 2]
+            .iter()
+            .map(|i| *i as f32 / 8f32)
+            .collect::<Vec<_>>();
+        assert_samples(&left(&audio.render(9)), &expected);
+    }
+
+    #[test]
+    fn playlists_continue_without_a_gap() {
+        let mut audio = audio();
+        audio.add_music("first", mono(vec![0.25; 3]));
+        audio.add_music("second", mono(vec![0.5; 3]));
+        audio
+            .play_playlist(
+                &Playlist::new(&["first", "second"])
+                    .with_crossfade(0f32)
+                    .once(),
+            )
+            .unwrap();
+
+        let samples = audio.render(8);
+        assert_samples(
+            &left(&samples),
+            &[0.25, 0.25, 0.25, 0.5, 0.5, 0.5, 0f32, 0f32],
+        );
+        assert!(!audio.mixer().is_music_playing());
+    }
+
+    #[test]
+    fn playlists_repeat() {
+        let mut audio = audio();
+        audio.add_music("first", mono(vec![0.25; 3]));
+        audio.add_music("second", mono(vec![0.5; 3]));
+        audio
+            .play_playlist(&Playlist::new(&["first", "second"]).with_crossfade(0f32))
+            .unwrap();
+
+        for expected in &[0.25, 0.5, 0.25, 0.5] {
+            assert_samples(&left(&audio.render(3)), &[*expected; 3]);
+        }
+    }
+
+    #[test]
+    fn ducking_lowers_the_music() {
+        let mut audio = audio();
+        audio.add_music("music", mono(vec![1f32; 1000]));
+        audio.add_sound("silence", mono(vec![0f32; 20]));
+        audio
+            .play_music(&PlayMusic::new("music").with_crossfade(0f32))
+            .unwrap();
+        assert_samples(&left(&audio.render(2)), &[1f32, 1f32]);
+        audio
+            .play(&PlaySound::new("silence").ducking_music())
+            .unwrap();
+
+        // The default ducking turns the music down to 0.35 in 40 milliseconds
+        let ducked = left(&audio.render(20));
+        assert_samples(&ducked[..5], &[0.8375, 0.675, 0.5125, 0.35, 0.35]);
+        assert_samples(&ducked[5..], &[0.35; 15]);
+        let released = left(&audio.render(10));
+        assert!(released[0] > 0.35 && released.windows(2).all(|w| w[1] > w[0]));
+    }
+
+    #[test]
+    fn decoders_stop_with_their_voice() {
+        let source = MusicSource::Sound(Arc::new(mono(vec![0.5; 1000])));
+        let track = MusicTrack::new("music", source);
+        let (voice, mut decoder) = MusicVoice::open(&track, true, 100).unwrap();
+
+        assert!(decoder.fill().unwrap());
+        drop(voice);
+        assert!(!decoder.fill().unwrap());
+    }
+}
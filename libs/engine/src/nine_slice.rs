@@ -0,0 +1,79 @@
+#![allow(deprecated)]
+
+use super::{Color, DrawHelper, TGraphicIndex};
+
+/// A graphic that is stretched to any size without distorting its border.
+/// The corners keep their size, the edges are stretched along one axis and the middle along both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NineSlice<T: TGraphicIndex> {
+    pub graphic: T,
+    /// The width of the border, in the size the graphic was loaded with
+    pub border: f32,
+}
+
+impl<T: TGraphicIndex> NineSlice<T> {
+    pub fn new(graphic: T, border: f32) -> NineSlice<T> {
+        NineSlice { graphic, border }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct SliceVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+implement_vertex!(SliceVertex, position, tex_coords, color);
+
+/// The triangles of the nine parts, for a rectangle in screen coordinates.
+/// Borders that do not fit into the rectangle are shrunk, together with their part of the texture.
+pub fn slice_vertices(
+    helper: &DrawHelper,
+    border: f32,
+    (x, y, width, height): (f32, f32, f32, f32),
+    (r, g, b, a): Color,
+) -> Vec<SliceVertex> {
+    let region = helper.region;
+    let border_x = border.min(width / 2f32).min(helper.width / 2f32).max(0f32);
+    let border_y = border
+        .min(height / 2f32)
+        .min(helper.height / 2f32)
+        .max(0f32);
+    let border_u = border_x / helper.width * (region.right - region.left);
+    let border_v = border_y / helper.height * (region.bottom - region.top);
+    let xs = [x, x + border_x, x + width - border_x, x + width];
+    let ys = [y, y + border_y, y + height - border_y, y + height];
+    let us = [
+        region.left,
+        region.left + border_u,
+        region.right - border_u,
+        region.right,
+    ];
+    let vs = [
+        region.top,
+        region.top + border_v,
+        region.bottom - border_v,
+        region.bottom,
+    ];
+
+    let vertex = |column: usize, row: usize| SliceVertex {
+        position: [xs[column], ys[row]],
+        tex_coords: [us[column], vs[row]],
+        color: [r, g, b, a],
+    };
+    let mut vertices = Vec::with_capacity(54);
+    for row in 0..3 {
+        for column in 0..3 {
+            vertices.extend_from_slice(&[
+                vertex(column, row),
+                vertex(column + 1, row),
+                vertex(column, row + 1),
+                vertex(column + 1, row),
+                vertex(column + 1, row + 1),
+                vertex(column, row + 1),
+            ]);
+        }
+    }
+    vertices
+}
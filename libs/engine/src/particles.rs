@@ -0,0 +1,489 @@
+#![allow(deprecated)]
+
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::Texture2d;
+use glium::uniforms::UniformsStorage;
+use glium::{Blend, DrawParameters, Surface, VertexBuffer};
+use rand::Rng;
+use std::rc::Rc;
+
+use super::{Color, EngineGraphics, EntityWrapper, Result, TGraphicIndex};
+
+/// How an emitter spawns its particles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EmitterMode {
+    /// Spawn this many particles at once, then stop
+    Burst(u32),
+    /// Spawn `rate` particles per second. If `duration` (in milliseconds) is given the emitter stops after that time,
+    /// otherwise it runs until the entity it follows is removed.
+    Continuous { rate: f32, duration: Option<f32> },
+}
+
+/// Spawns particles, which are drawn with the graphic `graphic` from the graphics registry.
+/// Emitters are spawned by returning `EntityEvent::SpawnEmitter` from an entity.
+/// Particles are not entities, so they do not count towards the entity limit.
+pub struct ParticleEmitter<T: TGraphicIndex> {
+    pub graphic: T,
+    pub mode: EmitterMode,
+    /// The position of the emitter. When following an entity, this is relative to the entity's position.
+    pub x: f32,
+    pub y: f32,
+    /// The id of the entity this emitter follows, see `EntityState::id`
+    pub follow: Option<u64>,
+    /// The minimum and maximum lifetime of a particle, in milliseconds
+    pub lifetime: (f32, f32),
+    /// The minimum and maximum speed of a particle, in pixels per millisecond
+    pub speed: (f32, f32),
+    /// The angle particles move in, in radians, with 0 being to the right
+    pub direction: f32,
+    /// The maximum deviation from `direction`, in radians. `PI` spawns particles in all directions.
+    pub spread: f32,
+    /// The acceleration of every particle, in pixels per millisecond squared
+    pub gravity: (f32, f32),
+    /// The colour of a particle at the start and end of its life. The alpha channel fades as well.
+    pub start_color: Color,
+    pub end_color: Color,
+    /// The scale of a particle at the start and end of its life, relative to the size the graphic was loaded with
+    pub start_scale: f32,
+    pub end_scale: f32,
+
+    particles: Vec<Particle>,
+    age: f32,
+    spawn_counter: f32,
+    active: bool,
+}
+
+struct Particle {
+    x: f32,
+    y: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+impl<T: TGraphicIndex> ParticleEmitter<T> {
+    fn new(graphic: T, x: f32, y: f32, mode: EmitterMode) -> ParticleEmitter<T> {
+        ParticleEmitter {
+            graphic,
+            mode,
+            x,
+            y,
+            follow: None,
+            lifetime: (500f32, 1000f32),
+            speed: (0.05f32, 0.2f32),
+            direction: 0f32,
+            spread: ::std::f32::consts::PI,
+            gravity: (0f32, 0f32),
+            start_color: (1.0, 1.0, 1.0, 1.0),
+            end_color: (1.0, 1.0, 1.0, 0.0),
+            start_scale: 1f32,
+            end_scale: 1f32,
+
+            particles: Vec::new(),
+            age: 0f32,
+            spawn_counter: 0f32,
+            active: true,
+        }
+    }
+
+    /// An emitter that spawns `count` particles at once
+    pub fn burst(graphic: T, x: f32, y: f32, count: u32) -> ParticleEmitter<T> {
+        ParticleEmitter::new(graphic, x, y, EmitterMode::Burst(count))
+    }
+
+    /// An emitter that keeps spawning `rate` particles per second
+    pub fn continuous(graphic: T, x: f32, y: f32, rate: f32) -> ParticleEmitter<T> {
+        ParticleEmitter::new(
+            graphic,
+            x,
+            y,
+            EmitterMode::Continuous {
+                rate,
+                duration: None,
+            },
+        )
+    }
+
+    /// Stop a continuous emitter after the given amount of milliseconds
+    pub fn with_duration(mut self, duration: f32) -> ParticleEmitter<T> {
+        if let EmitterMode::Continuous { rate, .. } = self.mode {
+            self.mode = EmitterMode::Continuous {
+                rate,
+                duration: Some(duration),
+            };
+        }
+        self
+    }
+
+    /// Follow the entity with the given id. `x` and `y` become an offset to the entity's position.
+    pub fn following(mut self, entity_id: u64) -> ParticleEmitter<T> {
+        self.follow = Some(entity_id);
+        self
+    }
+
+    pub fn with_lifetime(mut self, min: f32, max: f32) -> ParticleEmitter<T> {
+        self.lifetime = (min, max);
+        self
+    }
+
+    pub fn with_speed(mut self, min: f32, max: f32) -> ParticleEmitter<T> {
+        self.speed = (min, max);
+        self
+    }
+
+    pub fn with_direction(mut self, direction: f32, spread: f32) -> ParticleEmitter<T> {
+        self.direction = direction;
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_gravity(mut self, x: f32, y: f32) -> ParticleEmitter<T> {
+        self.gravity = (x, y);
+        self
+    }
+
+    pub fn with_colors(mut self, start: Color, end: Color) -> ParticleEmitter<T> {
+        self.start_color = start;
+        self.end_color = end;
+        self
+    }
+
+    pub fn with_scale(mut self, start: f32, end: f32) -> ParticleEmitter<T> {
+        self.start_scale = start;
+        self.end_scale = end;
+        self
+    }
+
+    /// An emitter is done when it will not spawn any more particles and all its particles have died
+    pub fn is_done(&self) -> bool {
+        !self.active && self.particles.is_empty()
+    }
+
+    /// Spawn a particle, unless the particle system already has `max_particles`
+    fn spawn<R: Rng>(&mut self, origin: (f32, f32), rng: &mut R, budget: &mut usize) {
+        if *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+        let angle = self.direction + (rng.gen::<f32>() * 2f32 - 1f32) * self.spread;
+        let speed = random_between(rng, self.speed);
+        self.particles.push(Particle {
+            x: origin.0 + self.x,
+            y: origin.1 + self.y,
+            velocity_x: angle.cos() * speed,
+            velocity_y: angle.sin() * speed,
+            age: 0f32,
+            lifetime: random_between(rng, self.lifetime),
+        });
+    }
+
+    fn move_particles(&mut self, delta_time: f32) {
+        for particle in &mut self.particles {
+            particle.age += delta_time;
+            particle.velocity_x += self.gravity.0 * delta_time;
+            particle.velocity_y += self.gravity.1 * delta_time;
+            particle.x += particle.velocity_x * delta_time;
+            particle.y += particle.velocity_y * delta_time;
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+    }
+
+    fn spawn_particles<R: Rng>(
+        &mut self,
+        delta_time: f32,
+        origin: Option<(f32, f32)>,
+        rng: &mut R,
+        budget: &mut usize,
+    ) {
+        let origin = match origin {
+            Some(origin) if self.active => origin,
+            _ => {
+                self.active = false;
+                return;
+            }
+        };
+
+        match self.mode {
+            EmitterMode::Burst(count) => {
+                for _ in 0..count {
+                    self.spawn(origin, rng, budget);
+                }
+                self.active = false;
+            }
+            EmitterMode::Continuous { rate, duration } => {
+                self.age += delta_time;
+                self.spawn_counter += rate * delta_time / 1000f32;
+                while self.spawn_counter >= 1f32 {
+                    self.spawn_counter -= 1f32;
+                    self.spawn(origin, rng, budget);
+                }
+                if let Some(duration) = duration {
+                    if self.age >= duration {
+                        self.active = false;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn random_between<R: Rng>(rng: &mut R, (min, max): (f32, f32)) -> f32 {
+    min + (max - min) * rng.gen::<f32>()
+}
+
+fn lerp(start: f32, end: f32, factor: f32) -> f32 {
+    start + (end - start) * factor
+}
+
+#[derive(Copy, Clone)]
+struct ParticleVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+implement_vertex!(ParticleVertex, position, tex_coords, color);
+
+/// How many particles live at once by default
+pub const DEFAULT_MAX_PARTICLES: usize = 10_000;
+
+/// All the emitters in the game. Owned by the `Engine`, which updates and draws them after the entities.
+pub struct ParticleSystem<T: TGraphicIndex> {
+    emitters: Vec<ParticleEmitter<T>>,
+    /// Emitters spawn no more particles while this many are alive
+    pub max_particles: usize,
+}
+
+impl<T: TGraphicIndex> Default for ParticleSystem<T> {
+    fn default() -> ParticleSystem<T> {
+        ParticleSystem {
+            emitters: Vec::new(),
+            max_particles: DEFAULT_MAX_PARTICLES,
+        }
+    }
+}
+
+impl<T: TGraphicIndex> ParticleSystem<T> {
+    pub fn add(&mut self, emitter: ParticleEmitter<T>) {
+        self.emitters.push(emitter);
+    }
+
+    pub fn clear(&mut self) {
+        self.emitters.clear();
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.emitters.iter().map(|e| e.particles.len()).sum()
+    }
+
+    pub fn update<R: Rng>(&mut self, delta_time: f32, entities: &[EntityWrapper<T>], rng: &mut R) {
+        for emitter in &mut self.emitters {
+            emitter.move_particles(delta_time);
+        }
+        let mut budget = self.max_particles.saturating_sub(self.particle_count());
+        for emitter in &mut self.emitters {
+            let origin = match emitter.follow {
+                Some(id) => entities
+                    .iter()
+                    .find(|e| e.state.id == id && e.state.active)
+                    .map(|e| (e.state.x, e.state.y)),
+                None => Some((0f32, 0f32)),
+            };
+            emitter.spawn_particles(delta_time, origin, rng, &mut budget);
+        }
+        self.emitters.retain(|e| !e.is_done());
+    }
+
+    /// Draw all particles, with one draw call per texture
+    pub fn draw(&self, graphics: &mut EngineGraphics<T>) -> Result<()> {
+        graphics.flush_primitives()?;
+        let mut batches: Vec<(Rc<Texture2d>, Vec<ParticleVertex>)> = Vec::new();
+        let camera = graphics.camera;
+        for emitter in &self.emitters {
+            let helper = match graphics.get_graphic(&emitter.graphic) {
+                Some(helper) => helper,
+                None => continue,
+            };
+            let index = match batches
+                .iter()
+                .position(|(texture, _)| Rc::ptr_eq(texture, &helper.texture))
+            {
+                Some(index) => index,
+                None => {
+                    batches.push((helper.texture.clone(), Vec::new()));
+                    batches.len() - 1
+                }
+            };
+            let vertices = &mut batches[index].1;
+            let region = helper.region;
+
+            for particle in &emitter.particles {
+                let factor = particle.age / particle.lifetime;
+                let scale = lerp(emitter.start_scale, emitter.end_scale, factor);
+                let color = [
+                    lerp(emitter.start_color.0, emitter.end_color.0, factor),
+                    lerp(emitter.start_color.1, emitter.end_color.1, factor),
+                    lerp(emitter.start_color.2, emitter.end_color.2, factor),
+                    lerp(emitter.start_color.3, emitter.end_color.3, factor),
+                ];
+                let half_width = helper.width * scale / 2f32;
+                let half_height = helper.height * scale / 2f32;
+                let (x, y) = camera.world_to_screen(particle.x, particle.y);
+                let left = x - half_width;
+                let right = x + half_width;
+                let top = y - half_height;
+                let bottom = y + half_height;
+
+                let top_left = ParticleVertex {
+                    position: [left, top],
+                    tex_coords: [region.left, region.top],
+                    color,
+                };
+                let top_right = ParticleVertex {
+                    position: [right, top],
+                    tex_coords: [region.right, region.top],
+                    color,
+                };
+                let bottom_left = ParticleVertex {
+                    position: [left, bottom],
+                    tex_coords: [region.left, region.bottom],
+                    color,
+                };
+                let bottom_right = ParticleVertex {
+                    position: [right, bottom],
+                    tex_coords: [region.right, region.bottom],
+                    color,
+                };
+                vertices.extend_from_slice(&[
+                    top_left,
+                    top_right,
+                    bottom_left,
+                    top_right,
+                    bottom_right,
+                    bottom_left,
+                ]);
+            }
+        }
+
+        let draw_parameters = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..DrawParameters::default()
+        };
+        for (texture, vertices) in batches {
+            if vertices.is_empty() {
+                continue;
+            }
+            let vertex_buffer = VertexBuffer::new(&graphics.display, &vertices)?;
+            if let Some(frame) = &mut graphics.frame {
+                let uniform = UniformsStorage::new("tex", &*texture);
+                let uniform = uniform.add("screen_size", [graphics.width, graphics.height]);
+                frame.draw(
+                    &vertex_buffer,
+                    NoIndices(PrimitiveType::TrianglesList),
+                    &graphics.particle_program,
+                    &uniform,
+                    &draw_parameters,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParticleEmitter, ParticleSystem};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use TGraphicIndex;
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct Spark;
+
+    impl TGraphicIndex for Spark {}
+
+    /// Update the system `times` times with a frame time of `delta_time`
+    fn run(particles: &mut ParticleSystem<Spark>, times: usize, delta_time: f32) {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..times {
+            particles.update(delta_time, &[], &mut rng);
+        }
+    }
+
+    #[test]
+    fn continuous_emitters_spawn_their_rate() {
+        let mut particles = ParticleSystem::default();
+        particles
+            .add(ParticleEmitter::continuous(Spark, 0f32, 0f32, 50f32).with_lifetime(1e6, 1e6));
+
+        // 50 particles per second are one every 20 milliseconds
+        run(&mut particles, 1, 10f32);
+        assert_eq!(particles.particle_count(), 0);
+        run(&mut particles, 9, 10f32);
+        assert_eq!(particles.particle_count(), 5);
+        run(&mut particles, 1, 1000f32);
+        assert_eq!(particles.particle_count(), 55);
+    }
+
+    #[test]
+    fn continuous_emitters_stop_after_their_duration() {
+        let mut particles = ParticleSystem::default();
+        let emitter = ParticleEmitter::continuous(Spark, 0f32, 0f32, 100f32)
+            .with_duration(100f32)
+            .with_lifetime(150f32, 150f32);
+        particles.add(emitter);
+
+        run(&mut particles, 10, 10f32);
+        assert_eq!(particles.particle_count(), 10);
+        run(&mut particles, 10, 10f32);
+        assert_eq!(particles.particle_count(), 5);
+        run(&mut particles, 5, 10f32);
+        assert_eq!(particles.particle_count(), 0);
+        assert!(particles.emitters.is_empty());
+    }
+
+    #[test]
+    fn particles_are_removed_at_the_end_of_their_life() {
+        let mut particles = ParticleSystem::default();
+        particles.add(ParticleEmitter::burst(Spark, 0f32, 0f32, 5).with_lifetime(100f32, 100f32));
+
+        run(&mut particles, 1, 50f32);
+        assert_eq!(particles.particle_count(), 5);
+        run(&mut particles, 1, 99f32);
+        assert_eq!(particles.particle_count(), 5);
+        run(&mut particles, 1, 1f32);
+        assert_eq!(particles.particle_count(), 0);
+        assert!(particles.emitters.is_empty());
+    }
+
+    #[test]
+    fn no_particles_spawn_beyond_the_cap() {
+        let mut particles = ParticleSystem::default();
+        particles.max_particles = 10;
+        particles.add(ParticleEmitter::burst(Spark, 0f32, 0f32, 25).with_lifetime(100f32, 100f32));
+        particles.add(
+            ParticleEmitter::continuous(Spark, 0f32, 0f32, 1000f32).with_lifetime(100f32, 100f32),
+        );
+
+        run(&mut particles, 1, 10f32);
+        assert_eq!(particles.particle_count(), 10);
+        run(&mut particles, 5, 10f32);
+        assert_eq!(particles.particle_count(), 10);
+
+        // The burst dies, which makes room for the continuous emitter
+        run(&mut particles, 5, 10f32);
+        assert_eq!(particles.particle_count(), 10);
+        assert_eq!(particles.emitters.len(), 1);
+    }
+
+    #[test]
+    fn emitters_stop_when_their_entity_is_gone() {
+        let mut particles = ParticleSystem::default();
+        particles.add(ParticleEmitter::continuous(Spark, 0f32, 0f32, 1000f32).following(3));
+
+        run(&mut particles, 1, 10f32);
+        assert_eq!(particles.particle_count(), 0);
+        assert!(particles.emitters.is_empty());
+    }
+}
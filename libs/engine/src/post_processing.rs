@@ -0,0 +1,382 @@
+#![allow(deprecated)]
+
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::PrimitiveType;
+use glium::texture::{MipmapsOption, Texture2d, Texture2dMultisample};
+use glium::uniforms::{
+    MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction, UniformValue,
+    Uniforms,
+};
+use glium::{Display, DrawParameters, Frame, IndexBuffer, Program, Surface, VertexBuffer};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{Color, RenderTarget, Result};
+
+/// A value that is passed to a post effect's shader as a uniform
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PostParameter {
+    Float(f32),
+    Vec2([f32; 2]),
+    Color(Color),
+}
+
+/// A full-screen effect in the post-processing chain.
+/// `shader` is the name the fragment shader was registered with, see `PostProcessing::register_shader`.
+pub struct PostEffect {
+    pub shader: String,
+    pub enabled: bool,
+    pub parameters: HashMap<String, PostParameter>,
+}
+
+impl PostEffect {
+    pub const BLOOM: &'static str = "bloom";
+    pub const CRT: &'static str = "crt";
+    pub const VIGNETTE: &'static str = "vignette";
+    pub const COLOR_GRADING: &'static str = "color_grading";
+    pub const CHROMATIC_ABERRATION: &'static str = "chromatic_aberration";
+
+    pub fn new(shader: &str) -> PostEffect {
+        PostEffect {
+            shader: shader.to_owned(),
+            enabled: true,
+            parameters: HashMap::new(),
+        }
+    }
+
+    /// Bright pixels bleed into their surroundings
+    pub fn bloom() -> PostEffect {
+        PostEffect::new(PostEffect::BLOOM)
+            .with_parameter("threshold", PostParameter::Float(0.6))
+            .with_parameter("intensity", PostParameter::Float(1.5))
+            .with_parameter("radius", PostParameter::Float(2.0))
+    }
+
+    /// Scanlines and a curved screen
+    pub fn crt() -> PostEffect {
+        PostEffect::new(PostEffect::CRT)
+            .with_parameter("scanline_strength", PostParameter::Float(0.3))
+            .with_parameter("curvature", PostParameter::Float(0.05))
+    }
+
+    /// Darkens the corners of the screen
+    pub fn vignette() -> PostEffect {
+        PostEffect::new(PostEffect::VIGNETTE)
+            .with_parameter("strength", PostParameter::Float(0.6))
+            .with_parameter("radius", PostParameter::Float(0.4))
+    }
+
+    /// Brightness, contrast, saturation and tint of the whole screen
+    pub fn color_grading() -> PostEffect {
+        PostEffect::new(PostEffect::COLOR_GRADING)
+            .with_parameter("brightness", PostParameter::Float(0.0))
+            .with_parameter("contrast", PostParameter::Float(1.0))
+            .with_parameter("saturation", PostParameter::Float(1.0))
+            .with_parameter("tint", PostParameter::Color((1.0, 1.0, 1.0, 1.0)))
+    }
+
+    /// Splits the red and blue channels towards the edges of the screen, `amount` is in pixels
+    pub fn chromatic_aberration() -> PostEffect {
+        PostEffect::new(PostEffect::CHROMATIC_ABERRATION)
+            .with_parameter("amount", PostParameter::Float(0.0))
+    }
+
+    pub fn with_parameter(mut self, name: &str, value: PostParameter) -> PostEffect {
+        self.set_parameter(name, value);
+        self
+    }
+
+    pub fn set_parameter(&mut self, name: &str, value: PostParameter) {
+        self.parameters.insert(name.to_owned(), value);
+    }
+}
+
+struct PostUniforms<'a> {
+    scene: &'a Texture2d,
+    resolution: [f32; 2],
+    time: f32,
+    parameters: &'a HashMap<String, PostParameter>,
+}
+
+impl<'b> Uniforms for PostUniforms<'b> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut visit: F) {
+        let sampler = SamplerBehavior {
+            wrap_function: (
+                SamplerWrapFunction::Clamp,
+                SamplerWrapFunction::Clamp,
+                SamplerWrapFunction::Clamp,
+            ),
+            minify_filter: MinifySamplerFilter::Linear,
+            magnify_filter: MagnifySamplerFilter::Linear,
+            ..SamplerBehavior::default()
+        };
+        visit("scene", UniformValue::Texture2d(self.scene, Some(sampler)));
+        visit("resolution", UniformValue::Vec2(self.resolution));
+        visit("time", UniformValue::Float(self.time));
+        for (name, value) in self.parameters {
+            let value = match *value {
+                PostParameter::Float(value) => UniformValue::Float(value),
+                PostParameter::Vec2(value) => UniformValue::Vec2(value),
+                PostParameter::Color((r, g, b, a)) => UniformValue::Vec4([r, g, b, a]),
+            };
+            visit(name, value);
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+
+implement_vertex!(Vertex, position, tex_coords);
+
+/// A chain of full-screen effects that are applied to the scene before it is shown.
+/// When at least one effect is enabled, the scene is rendered to an offscreen texture,
+/// which is then passed through every enabled effect in order.
+///
+/// Effect shaders are fragment shaders receiving `in vec2 v_tex_coords`, and the uniforms
+/// `sampler2D scene`, `vec2 resolution`, `float time` (in seconds) and the effect's parameters.
+pub struct PostProcessing {
+    pub chain: Vec<PostEffect>,
+    shaders: HashMap<String, Program>,
+    vertex_buffer: VertexBuffer<Vertex>,
+    index_buffer: IndexBuffer<u8>,
+    targets: Vec<Rc<Texture2d>>,
+    /// The amount of samples per pixel the scene is rendered with, 0 turns anti-aliasing off
+    samples: u16,
+    /// The scene before it is resolved into the first target, if anti-aliasing is on
+    multisampled: Option<Rc<Texture2dMultisample>>,
+    /// Seconds since the engine started, see `set_time`
+    time: f32,
+}
+
+impl PostProcessing {
+    /// The scene is rendered with the given amount of samples per pixel, like the window would be
+    pub fn new(display: &Display, samples: u16) -> Result<PostProcessing> {
+        let vertex_buffer = VertexBuffer::new(
+            display,
+            &[
+                Vertex {
+                    position: [-1.0, -1.0],
+                    tex_coords: [0.0, 0.0],
+                },
+                Vertex {
+                    position: [1.0, -1.0],
+                    tex_coords: [1.0, 0.0],
+                },
+                Vertex {
+                    position: [-1.0, 1.0],
+                    tex_coords: [0.0, 1.0],
+                },
+                Vertex {
+                    position: [1.0, 1.0],
+                    tex_coords: [1.0, 1.0],
+                },
+            ],
+        )?;
+        let index_buffer =
+            IndexBuffer::<u8>::new(display, PrimitiveType::TriangleStrip, &[0, 1, 2, 3])?;
+
+        let mut post_processing = PostProcessing {
+            chain: Vec::new(),
+            shaders: HashMap::new(),
+            vertex_buffer,
+            index_buffer,
+            targets: Vec::new(),
+            samples,
+            multisampled: None,
+            time: 0f32,
+        };
+        post_processing.register_shader(
+            display,
+            PostEffect::BLOOM,
+            include_str!("../assets/post/bloom.frag"),
+        )?;
+        post_processing.register_shader(
+            display,
+            PostEffect::CRT,
+            include_str!("../assets/post/crt.frag"),
+        )?;
+        post_processing.register_shader(
+            display,
+            PostEffect::VIGNETTE,
+            include_str!("../assets/post/vignette.frag"),
+        )?;
+        post_processing.register_shader(
+            display,
+            PostEffect::COLOR_GRADING,
+            include_str!("../assets/post/color_grading.frag"),
+        )?;
+        post_processing.register_shader(
+            display,
+            PostEffect::CHROMATIC_ABERRATION,
+            include_str!("../assets/post/chromatic_aberration.frag"),
+        )?;
+        Ok(post_processing)
+    }
+
+    /// Compile a fragment shader that can be used by a `PostEffect` with the same name.
+    /// Registering a shader with an existing name replaces it.
+    pub fn register_shader(
+        &mut self,
+        display: &Display,
+        name: &str,
+        fragment_shader: &str,
+    ) -> Result<()> {
+        let program = Program::from_source(
+            display,
+            include_str!("../assets/post/post.vert"),
+            fragment_shader,
+            None,
+        )?;
+        self.shaders.insert(name.to_owned(), program);
+        Ok(())
+    }
+
+    pub fn push(&mut self, effect: PostEffect) {
+        self.chain.push(effect);
+    }
+
+    /// Get the first effect in the chain that uses the given shader
+    pub fn effect_mut(&mut self, shader: &str) -> Option<&mut PostEffect> {
+        self.chain.iter_mut().find(|e| e.shader == shader)
+    }
+
+    pub fn remove(&mut self, shader: &str) {
+        self.chain.retain(|e| e.shader != shader);
+    }
+
+    /// The offscreen texture the scene was rendered to, before any effect was applied.
+    /// Only available while post-processing is active.
+    pub fn scene_texture(&self) -> Option<&Texture2d> {
+        self.targets.first().map(|t| &**t)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.chain.iter().any(|e| e.enabled)
+    }
+
+    /// Start a new frame. If any effect is enabled, the frame is rendered to an offscreen texture.
+    pub fn begin(&mut self, display: &Display) -> Result<RenderTarget> {
+        if !self.is_active() {
+            return Ok(RenderTarget::Frame(display.draw()));
+        }
+        let dimensions = display.get_framebuffer_dimensions();
+        let matches = self
+            .targets
+            .first()
+            .map(|t| t.dimensions() == dimensions)
+            .unwrap_or(false);
+        if !matches {
+            self.targets.clear();
+            // The scene, and two textures for the effects in between to render to
+            for _ in 0..3 {
+                let texture = Texture2d::empty_with_mipmaps(
+                    display,
+                    MipmapsOption::NoMipmap,
+                    dimensions.0,
+                    dimensions.1,
+                )?;
+                SimpleFrameBuffer::new(display, &texture)?;
+                self.targets.push(Rc::new(texture));
+            }
+            self.multisampled = None;
+            if self.samples > 0 {
+                let texture = Texture2dMultisample::empty(
+                    display,
+                    dimensions.0,
+                    dimensions.1,
+                    u32::from(self.samples),
+                )?;
+                SimpleFrameBuffer::new(display, &texture)?;
+                self.multisampled = Some(Rc::new(texture));
+            }
+        }
+        Ok(RenderTarget::Texture {
+            frame: display.draw(),
+            display: display.clone(),
+            texture: self.targets[0].clone(),
+            multisampled: self.multisampled.clone(),
+        })
+    }
+
+    /// The `time` uniform of the effects, in seconds
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// Run the enabled effects on the rendered scene, and return the window's frame with the result.
+    pub fn finish(&self, display: &Display, target: RenderTarget) -> Result<Frame> {
+        let (mut frame, scene) = target.into_parts();
+        let scene = match scene {
+            Some(scene) => scene,
+            None => return Ok(frame),
+        };
+
+        let effects = self
+            .chain
+            .iter()
+            .filter(|e| e.enabled)
+            .filter_map(|e| self.shaders.get(&e.shader).map(|program| (e, program)))
+            .collect::<Vec<_>>();
+        let dimensions = scene.dimensions();
+
+        let mut source = scene;
+        for (index, (effect, program)) in effects.iter().enumerate() {
+            let uniforms = PostUniforms {
+                scene: &source,
+                resolution: [dimensions.0 as f32, dimensions.1 as f32],
+                time: self.time,
+                parameters: &effect.parameters,
+            };
+            let is_last = index + 1 == effects.len();
+            let result = if is_last {
+                frame.draw(
+                    &self.vertex_buffer,
+                    &self.index_buffer,
+                    program,
+                    &uniforms,
+                    &DrawParameters::default(),
+                )
+            } else {
+                let destination = self.targets[1..]
+                    .iter()
+                    .find(|t| !Rc::ptr_eq(t, &source))
+                    .expect("There are always two intermediate post processing targets")
+                    .clone();
+                let result = SimpleFrameBuffer::new(display, &*destination)?.draw(
+                    &self.vertex_buffer,
+                    &self.index_buffer,
+                    program,
+                    &uniforms,
+                    &DrawParameters::default(),
+                );
+                source = destination;
+                result
+            };
+            if let Err(e) = result {
+                // The frame has to be finished, even if we can not draw to it
+                frame.finish()?;
+                return Err(e.into());
+            }
+        }
+        if effects.is_empty() {
+            // Only effects with unknown shaders are enabled, show the scene as is
+            frame.draw(
+                &self.vertex_buffer,
+                &self.index_buffer,
+                &self.shaders[PostEffect::COLOR_GRADING],
+                &PostUniforms {
+                    scene: &source,
+                    resolution: [dimensions.0 as f32, dimensions.1 as f32],
+                    time: self.time,
+                    parameters: &PostEffect::color_grading().parameters,
+                },
+                &DrawParameters::default(),
+            )?;
+        }
+        Ok(frame)
+    }
+}
@@ -0,0 +1,175 @@
+#![allow(deprecated)]
+
+use std::f32::consts::PI;
+
+use super::Color;
+
+#[derive(Copy, Clone)]
+pub struct ColorVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+implement_vertex!(ColorVertex, position, color);
+
+/// Coloured triangles that are collected during a frame and drawn with a single draw call.
+/// All positions are in screen coordinates.
+#[derive(Default)]
+pub struct PrimitiveBatch {
+    vertices: Vec<ColorVertex>,
+}
+
+impl PrimitiveBatch {
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &[ColorVertex] {
+        &self.vertices
+    }
+
+    /// Remove all triangles, keeping the allocated memory for the next frame
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn triangle(&mut self, a: (f32, f32), b: (f32, f32), c: (f32, f32), color: Color) {
+        let color = [color.0, color.1, color.2, color.3];
+        for &(x, y) in &[a, b, c] {
+            self.vertices.push(ColorVertex {
+                position: [x, y],
+                color,
+            });
+        }
+    }
+
+    /// A quadrilateral with its corners given in order around its edge
+    pub fn quad(
+        &mut self,
+        a: (f32, f32),
+        b: (f32, f32),
+        c: (f32, f32),
+        d: (f32, f32),
+        color: Color,
+    ) {
+        self.triangle(a, b, c, color);
+        self.triangle(a, c, d, color);
+    }
+
+    pub fn rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.quad(
+            (x, y),
+            (x + width, y),
+            (x + width, y + height),
+            (x, y + height),
+            color,
+        );
+    }
+
+    /// The outline lies inside the rectangle, the sides do not overlap so translucent outlines have an even colour
+    pub fn rectangle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        let thickness = thickness.min(width / 2f32).min(height / 2f32);
+        let inner_height = height - thickness * 2f32;
+        self.rectangle(x, y, width, thickness, color);
+        self.rectangle(x, y + height - thickness, width, thickness, color);
+        self.rectangle(x, y + thickness, thickness, inner_height, color);
+        self.rectangle(
+            x + width - thickness,
+            y + thickness,
+            thickness,
+            inner_height,
+            color,
+        );
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), thickness: f32, color: Color) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0f32 {
+            return;
+        }
+        let normal = (
+            -dy / length * thickness / 2f32,
+            dx / length * thickness / 2f32,
+        );
+        self.quad(
+            (from.0 + normal.0, from.1 + normal.1),
+            (to.0 + normal.0, to.1 + normal.1),
+            (to.0 - normal.0, to.1 - normal.1),
+            (from.0 - normal.0, from.1 - normal.1),
+            color,
+        );
+    }
+
+    /// A filled convex polygon. Concave polygons are not drawn correctly.
+    pub fn polygon(&mut self, points: &[(f32, f32)], color: Color) {
+        for index in 2..points.len() {
+            self.triangle(points[0], points[index - 1], points[index], color);
+        }
+    }
+
+    /// Lines between consecutive points, and from the last point back to the first
+    pub fn polygon_outline(&mut self, points: &[(f32, f32)], thickness: f32, color: Color) {
+        for index in 0..points.len() {
+            let next = (index + 1) % points.len();
+            self.line(points[index], points[next], thickness, color);
+        }
+    }
+
+    pub fn circle(&mut self, center: (f32, f32), radius: f32, color: Color) {
+        let segments = segments(radius, 2f32 * PI);
+        let mut previous = point_on_circle(center, radius, 0f32);
+        for index in 1..=segments {
+            let point = point_on_circle(center, radius, 2f32 * PI * index as f32 / segments as f32);
+            self.triangle(center, previous, point, color);
+            previous = point;
+        }
+    }
+
+    /// Part of a circle outline, from `start_angle` to `end_angle` in radians, with 0 being to the right
+    pub fn arc(
+        &mut self,
+        center: (f32, f32),
+        radius: f32,
+        (start_angle, end_angle): (f32, f32),
+        thickness: f32,
+        color: Color,
+    ) {
+        let inner = (radius - thickness / 2f32).max(0f32);
+        let outer = radius + thickness / 2f32;
+        let sweep = end_angle - start_angle;
+        let segments = segments(outer, sweep.abs());
+        for index in 0..segments {
+            let from = start_angle + sweep * index as f32 / segments as f32;
+            let to = start_angle + sweep * (index + 1) as f32 / segments as f32;
+            self.quad(
+                point_on_circle(center, inner, from),
+                point_on_circle(center, outer, from),
+                point_on_circle(center, outer, to),
+                point_on_circle(center, inner, to),
+                color,
+            );
+        }
+    }
+}
+
+fn point_on_circle(center: (f32, f32), radius: f32, angle: f32) -> (f32, f32) {
+    (
+        center.0 + radius * angle.cos(),
+        center.1 + radius * angle.sin(),
+    )
+}
+
+/// The amount of segments needed for a round looking arc. Larger circles need more segments.
+fn segments(radius: f32, angle: f32) -> usize {
+    let full_circle = (radius.max(0f32).sqrt() * 4f32).max(12f32).min(128f32);
+    ((full_circle * angle / (2f32 * PI)).ceil() as usize).max(1)
+}
@@ -0,0 +1,240 @@
+use glium::framebuffer::{MultiOutputFrameBuffer, SimpleFrameBuffer};
+use glium::index::IndicesSource;
+use glium::texture::{Texture2d, Texture2dMultisample};
+use glium::uniforms::{MagnifySamplerFilter, Uniforms};
+use glium::vertex::MultiVerticesSource;
+use glium::{BlitTarget, Display, DrawError, DrawParameters, Frame, Program, Rect, Surface};
+use std::rc::Rc;
+
+/// The surface everything in a frame is drawn on.
+/// This is either the window itself, or an offscreen texture that is later drawn to the window,
+/// e.g. by the post-processing chain.
+/// The texture must be usable as a framebuffer, which is checked by whoever creates it.
+/// With anti-aliasing, drawing goes to the multisampled texture, which is resolved into `texture`
+/// when rendering stops.
+pub enum RenderTarget {
+    Frame(Frame),
+    Texture {
+        frame: Frame,
+        display: Display,
+        texture: Rc<Texture2d>,
+        multisampled: Option<Rc<Texture2dMultisample>>,
+    },
+}
+
+impl RenderTarget {
+    /// Stop rendering, returning the window's frame and the offscreen texture, if any
+    pub fn into_parts(self) -> (Frame, Option<Rc<Texture2d>>) {
+        match self {
+            RenderTarget::Frame(frame) => (frame, None),
+            RenderTarget::Texture {
+                frame,
+                display,
+                texture,
+                multisampled,
+            } => {
+                if multisampled.is_some() {
+                    // Resolve the samples into the texture the effects read from
+                    let (width, height) = texture.dimensions();
+                    let target = framebuffer(&display, &texture, &None);
+                    framebuffer(&display, &texture, &multisampled).blit_whole_color_to(
+                        &target,
+                        &BlitTarget {
+                            left: 0,
+                            bottom: 0,
+                            width: width as i32,
+                            height: height as i32,
+                        },
+                        MagnifySamplerFilter::Nearest,
+                    );
+                }
+                (frame, Some(texture))
+            }
+        }
+    }
+}
+
+fn framebuffer<'a>(
+    display: &Display,
+    texture: &'a Texture2d,
+    multisampled: &'a Option<Rc<Texture2dMultisample>>,
+) -> SimpleFrameBuffer<'a> {
+    match multisampled {
+        Some(multisampled) => SimpleFrameBuffer::new(display, &**multisampled),
+        None => SimpleFrameBuffer::new(display, texture),
+    }
+    .expect("Render target textures are validated when they are created")
+}
+
+impl Surface for RenderTarget {
+    fn clear(
+        &mut self,
+        rect: Option<&Rect>,
+        color: Option<(f32, f32, f32, f32)>,
+        color_srgb: bool,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) {
+        match self {
+            RenderTarget::Frame(frame) => frame.clear(rect, color, color_srgb, depth, stencil),
+            RenderTarget::Texture {
+                display,
+                texture,
+                multisampled,
+                ..
+            } => framebuffer(display, texture, multisampled)
+                .clear(rect, color, color_srgb, depth, stencil),
+        }
+    }
+
+    fn get_dimensions(&self) -> (u32, u32) {
+        match self {
+            RenderTarget::Frame(frame) => frame.get_dimensions(),
+            RenderTarget::Texture { texture, .. } => texture.dimensions(),
+        }
+    }
+
+    fn get_depth_buffer_bits(&self) -> Option<u16> {
+        match self {
+            RenderTarget::Frame(frame) => frame.get_depth_buffer_bits(),
+            RenderTarget::Texture { .. } => None,
+        }
+    }
+
+    fn get_stencil_buffer_bits(&self) -> Option<u16> {
+        match self {
+            RenderTarget::Frame(frame) => frame.get_stencil_buffer_bits(),
+            RenderTarget::Texture { .. } => None,
+        }
+    }
+
+    fn draw<'a, 'b, V, I, U>(
+        &mut self,
+        vertices: V,
+        indices: I,
+        program: &Program,
+        uniforms: &U,
+        draw_parameters: &DrawParameters,
+    ) -> ::std::result::Result<(), DrawError>
+    where
+        V: MultiVerticesSource<'b>,
+        I: Into<IndicesSource<'a>>,
+        U: Uniforms,
+    {
+        match self {
+            RenderTarget::Frame(frame) => {
+                frame.draw(vertices, indices, program, uniforms, draw_parameters)
+            }
+            RenderTarget::Texture {
+                display,
+                texture,
+                multisampled,
+                ..
+            } => framebuffer(display, texture, multisampled).draw(
+                vertices,
+                indices,
+                program,
+                uniforms,
+                draw_parameters,
+            ),
+        }
+    }
+
+    fn blit_from_frame(
+        &self,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+    ) {
+        match self {
+            RenderTarget::Frame(frame) => frame.blit_from_frame(source_rect, target_rect, filter),
+            RenderTarget::Texture {
+                display,
+                texture,
+                multisampled,
+                ..
+            } => framebuffer(display, texture, multisampled).blit_from_frame(
+                source_rect,
+                target_rect,
+                filter,
+            ),
+        }
+    }
+
+    fn blit_from_simple_framebuffer(
+        &self,
+        source: &SimpleFrameBuffer,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+    ) {
+        match self {
+            RenderTarget::Frame(frame) => {
+                frame.blit_from_simple_framebuffer(source, source_rect, target_rect, filter)
+            }
+            RenderTarget::Texture {
+                display,
+                texture,
+                multisampled,
+                ..
+            } => framebuffer(display, texture, multisampled).blit_from_simple_framebuffer(
+                source,
+                source_rect,
+                target_rect,
+                filter,
+            ),
+        }
+    }
+
+    fn blit_from_multioutput_framebuffer(
+        &self,
+        source: &MultiOutputFrameBuffer,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+    ) {
+        match self {
+            RenderTarget::Frame(frame) => {
+                frame.blit_from_multioutput_framebuffer(source, source_rect, target_rect, filter)
+            }
+            RenderTarget::Texture {
+                display,
+                texture,
+                multisampled,
+                ..
+            } => framebuffer(display, texture, multisampled).blit_from_multioutput_framebuffer(
+                source,
+                source_rect,
+                target_rect,
+                filter,
+            ),
+        }
+    }
+
+    fn blit_color<S>(
+        &self,
+        source_rect: &Rect,
+        target: &S,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+    ) where
+        S: Surface,
+    {
+        match self {
+            RenderTarget::Frame(frame) => {
+                frame.blit_color(source_rect, target, target_rect, filter)
+            }
+            RenderTarget::Texture {
+                display,
+                texture,
+                multisampled,
+                ..
+            } => framebuffer(display, texture, multisampled).blit_color(
+                source_rect,
+                target,
+                target_rect,
+                filter,
+            ),
+        }
+    }
+}
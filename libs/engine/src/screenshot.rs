@@ -0,0 +1,103 @@
+use __time::OffsetDateTime;
+use glium::texture::RawImage2d;
+use image::{imageops, RgbaImage};
+use std::path::{Path, PathBuf};
+
+use super::error::Error;
+use super::Result;
+
+/// Convert pixels that were read back from OpenGL, which start at the bottom row, into an image
+pub fn image_from_raw(raw: RawImage2d<u8>) -> Result<RgbaImage> {
+    let (width, height) = (raw.width, raw.height);
+    let length = raw.data.len();
+    let image = RgbaImage::from_raw(width, height, raw.data.into_owned()).ok_or_else(|| {
+        Error::readback(&format!(
+            "{} bytes is not a {}x{} RGBA image",
+            length, width, height
+        ))
+    })?;
+    Ok(imageops::flip_vertical(&image))
+}
+
+/// The average difference per colour channel between two images, from 0 (identical) to 1.
+/// Returns `None` if the images have different dimensions.
+pub fn compare_images(first: &RgbaImage, second: &RgbaImage) -> Option<f32> {
+    if first.dimensions() != second.dimensions() {
+        return None;
+    }
+    let channels = first.len();
+    if channels == 0 {
+        return Some(0f32);
+    }
+    let difference: u64 = first
+        .iter()
+        .zip(second.iter())
+        .map(|(a, b)| (*a as i64 - *b as i64).abs() as u64)
+        .sum();
+    Some(difference as f32 / (channels as f32 * 255f32))
+}
+
+/// A new file name in `directory` for a screenshot taken now, e.g. `screenshot-20170130-211501-042.png`
+pub fn screenshot_path(directory: &Path) -> PathBuf {
+    let now = OffsetDateTime::now_utc();
+    directory.join(format!(
+        "screenshot-{}-{:03}.png",
+        now.format("%Y%m%d-%H%M%S"),
+        now.millisecond()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_images, image_from_raw};
+    use glium::texture::{ClientFormat, RawImage2d};
+    use image::{Rgba, RgbaImage};
+    use std::borrow::Cow;
+
+    fn raw(data: Vec<u8>, width: u32, height: u32) -> RawImage2d<'static, u8> {
+        RawImage2d {
+            data: Cow::Owned(data),
+            width,
+            height,
+            format: ClientFormat::U8U8U8U8,
+        }
+    }
+
+    #[test]
+    fn readback_starts_at_the_bottom_row() {
+        let image = image_from_raw(raw(vec![1, 1, 1, 1, 2, 2, 2, 2], 1, 2)).unwrap();
+        assert_eq!(*image.get_pixel(0, 0), Rgba([2, 2, 2, 2]));
+        assert_eq!(*image.get_pixel(0, 1), Rgba([1, 1, 1, 1]));
+    }
+
+    #[test]
+    fn readback_of_the_wrong_size_fails() {
+        assert!(image_from_raw(raw(vec![0; 12], 2, 2)).is_err());
+    }
+
+    #[test]
+    fn identical_images_have_no_difference() {
+        let image = RgbaImage::from_pixel(4, 3, Rgba([10, 20, 30, 255]));
+        assert_eq!(compare_images(&image, &image.clone()), Some(0f32));
+    }
+
+    #[test]
+    fn different_images_are_compared_per_channel() {
+        let black = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        let white = RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255]));
+        // Three of the four channels differ completely
+        assert_eq!(compare_images(&black, &white), Some(0.75));
+
+        let mut one_pixel = black.clone();
+        one_pixel.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let difference = compare_images(&black, &one_pixel).unwrap();
+        assert!((difference - 0.75 / 4f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn images_of_different_sizes_can_not_be_compared() {
+        let first = RgbaImage::new(2, 2);
+        let second = RgbaImage::new(2, 3);
+        assert_eq!(compare_images(&first, &second), None);
+    }
+}
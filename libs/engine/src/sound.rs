@@ -0,0 +1,154 @@
+use hound::{SampleFormat, WavReader};
+use lewton::inside_ogg::OggStreamReader;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use super::error::Error;
+use super::Result;
+
+/// Decoded audio in memory, as interleaved samples between -1 and 1.
+/// Sounds have one or two channels, the channels after the second one of a file are dropped.
+pub struct SoundData {
+    samples: Vec<f32>,
+    channels: usize,
+    sample_rate: u32,
+}
+
+impl SoundData {
+    pub fn new(samples: Vec<f32>, channels: usize, sample_rate: u32) -> Result<SoundData> {
+        if channels == 0 || sample_rate == 0 {
+            return Err(Error::audio(
+                "a sound needs at least one channel and a sample rate",
+            ));
+        }
+        let (samples, channels) = keep_two_channels(samples, channels);
+        Ok(SoundData {
+            samples,
+            channels,
+            sample_rate,
+        })
+    }
+
+    /// Load a WAV or OGG Vorbis file, depending on its extension
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<SoundData> {
+        let file = file.as_ref();
+        let extension = file
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let reader = BufReader::new(File::open(file)?);
+        match extension.as_ref().map(|e| e.as_str()) {
+            Some("wav") => SoundData::from_wav(reader),
+            Some("ogg") => SoundData::from_ogg(reader),
+            _ => Err(Error::audio(&format!(
+                "{}: only .wav and .ogg files are supported",
+                file.display()
+            ))),
+        }
+    }
+
+    pub fn from_wav<R: Read>(reader: R) -> Result<SoundData> {
+        let reader = WavReader::new(reader).map_err(|e| Error::audio(&e.to_string()))?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            SampleFormat::Float => reader
+                .into_samples::<f32>()
+                .collect::<::std::result::Result<Vec<_>, _>>(),
+            SampleFormat::Int => {
+                let scale = 1f32 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|sample| sample.map(|s| s as f32 * scale))
+                    .collect::<::std::result::Result<Vec<_>, _>>()
+            }
+        }
+        .map_err(|e| Error::audio(&e.to_string()))?;
+        SoundData::new(samples, spec.channels as usize, spec.sample_rate)
+    }
+
+    pub fn from_ogg<R: Read + Seek>(reader: R) -> Result<SoundData> {
+        let mut reader = OggStreamReader::new(reader).map_err(|e| Error::audio(&e.to_string()))?;
+        let mut samples = Vec::new();
+        while let Some(packet) = reader
+            .read_dec_packet_itl()
+            .map_err(|e| Error::audio(&e.to_string()))?
+        {
+            samples.extend(packet.into_iter().map(|s| f32::from(s) / 32768f32));
+        }
+        SoundData::new(
+            samples,
+            reader.ident_hdr.audio_channels as usize,
+            reader.ident_hdr.audio_sample_rate,
+        )
+    }
+
+    /// The interleaved samples of all channels
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The amount of samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// The length of the sound in milliseconds, when played at its own sample rate
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 * 1000f32 / self.sample_rate as f32
+    }
+
+    /// The left and right sample at a frame. Mono sounds play the same sample on both sides.
+    pub fn frame(&self, index: usize) -> (f32, f32) {
+        if self.channels == 1 {
+            let sample = self.samples[index];
+            (sample, sample)
+        } else {
+            (self.samples[index * 2], self.samples[index * 2 + 1])
+        }
+    }
+
+    /// The sample between two frames, interpolated linearly.
+    /// When `looping`, the last frame is interpolated towards the first one, otherwise towards silence.
+    pub fn sample_at(&self, position: f64, looping: bool) -> (f32, f32) {
+        let frames = self.frames();
+        let index = position as usize;
+        if index >= frames {
+            return (0f32, 0f32);
+        }
+        let fraction = (position - index as f64) as f32;
+        let (left, right) = self.frame(index);
+        let (next_left, next_right) = if index + 1 < frames {
+            self.frame(index + 1)
+        } else if looping {
+            self.frame(0)
+        } else {
+            (0f32, 0f32)
+        };
+        (
+            left + (next_left - left) * fraction,
+            right + (next_right - right) * fraction,
+        )
+    }
+}
+
+/// Drop every channel after the second one
+fn keep_two_channels(samples: Vec<f32>, channels: usize) -> (Vec<f32>, usize) {
+    if channels <= 2 {
+        return (samples, channels);
+    }
+    let samples = samples
+        .chunks(channels)
+        .filter(|frame| frame.len() == channels)
+        .flat_map(|frame| frame[..2].to_vec())
+        .collect();
+    (samples, 2)
+}
@@ -1,77 +1,377 @@
-use super::{Color, Result};
-use glium::{Display, Frame};
+use super::error::Error;
+use super::{Color, LruCache, Result};
+use glium::{Display, Surface};
 use glium_text::{draw, FontTexture, TextDisplay, TextSystem};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
 use std::rc::Rc;
 
+/// The name of the font that is built into the engine
+pub const DEFAULT_FONT: &str = "default";
+/// The height of a capital letter relative to the font size. glium_text measures glyphs in this unit.
+const CAP_HEIGHT: f32 = 0.72;
+/// The distance from the top of a line to its baseline, relative to the font size
+const ASCENT: f32 = 0.9;
+/// The amount of laid out lines that are kept for `TextGraphics::draw_at`
+pub const DEFAULT_TEXT_CACHE_SIZE: usize = 256;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextAlign {
+    /// `x` is the left edge of every line
+    Left,
+    /// `x` is the center of every line
+    Center,
+    /// `x` is the right edge of every line
+    Right,
+}
+
+/// How a text is drawn. The default is the built-in font at 16 pixels, white and left-aligned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    /// The name the font was loaded with, see `TextGraphics::load_font`
+    pub font: String,
+    /// The font size in pixels
+    pub size: u32,
+    pub color: Color,
+    pub align: TextAlign,
+    /// Break lines between words so they are no wider than this many pixels
+    pub wrap_width: Option<f32>,
+    /// The distance between two lines, relative to the font size
+    pub line_spacing: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> TextStyle {
+        TextStyle {
+            font: DEFAULT_FONT.to_owned(),
+            size: 16,
+            color: (1.0, 1.0, 1.0, 1.0),
+            align: TextAlign::Left,
+            wrap_width: None,
+            line_spacing: 1.2,
+        }
+    }
+}
+
+impl TextStyle {
+    pub fn with_font(mut self, font: &str) -> TextStyle {
+        self.font = font.to_owned();
+        self
+    }
+
+    pub fn with_size(mut self, size: u32) -> TextStyle {
+        self.size = size;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> TextStyle {
+        self.color = color;
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> TextStyle {
+        self.align = align;
+        self
+    }
+
+    pub fn wrapped(mut self, width: f32) -> TextStyle {
+        self.wrap_width = Some(width);
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> TextStyle {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.size as f32 * self.line_spacing
+    }
+}
+
+/// A font rasterized at a single size
+struct Font {
+    texture: Rc<FontTexture>,
+    /// The width of every character that has been measured so far, in pixels
+    advances: HashMap<char, f32>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct TextKey {
+    font: String,
+    size: u32,
+    text: String,
+}
+
 pub struct TextGraphics {
     pub system: TextSystem,
-    pub font: Rc<FontTexture>,
-    pub cache: HashMap<String, TextDisplay<Rc<FontTexture>>>,
-    pub unused_keys: Vec<String>,
+    display: Display,
+    font_sources: HashMap<String, Vec<u8>>,
+    fonts: HashMap<(String, u32), Font>,
+    cache: LruCache<TextKey, TextDisplay<Rc<FontTexture>>>,
+}
+
+/// Text that has been laid out once, and can be drawn again without any lookups.
+/// Create it with `TextGraphics::create_text`. Its size is known up front, which helps with layout.
+pub struct RetainedText {
+    text: String,
+    style: TextStyle,
+    /// Every line with its offset from the alignment point
+    lines: Vec<(TextDisplay<Rc<FontTexture>>, f32, f32)>,
+    size: (f32, f32),
+}
+
+impl RetainedText {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn style(&self) -> &TextStyle {
+        &self.style
+    }
+
+    /// The width and height of the text in pixels
+    pub fn size(&self) -> (f32, f32) {
+        self.size
+    }
+
+    /// Change the colour, which does not need a new layout
+    pub fn set_color(&mut self, color: Color) {
+        self.style.color = color;
+    }
 }
 
-#[allow(dead_code)]
 impl TextGraphics {
     pub fn new(display: &Display) -> Result<TextGraphics> {
         let system = TextSystem::new(display);
-        let font = Rc::new(FontTexture::new(
-            display,
-            &include_bytes!("../assets/arial.ttf")[..],
-            16,
-        )?);
-        Ok(TextGraphics {
+        let mut text_graphics = TextGraphics {
             system,
-            font,
-            cache: HashMap::new(),
-            unused_keys: Vec::new(),
-        })
+            display: display.clone(),
+            font_sources: HashMap::new(),
+            fonts: HashMap::new(),
+            cache: LruCache::new(DEFAULT_TEXT_CACHE_SIZE),
+        };
+        text_graphics.load_font_bytes(DEFAULT_FONT, include_bytes!("../assets/arial.ttf").to_vec());
+        Ok(text_graphics)
+    }
+
+    /// Load a TrueType font from a file, to be used by a `TextStyle` with the same name
+    pub fn load_font(&mut self, name: &str, file: &str) -> Result<()> {
+        let mut bytes = Vec::new();
+        File::open(file)?.read_to_end(&mut bytes)?;
+        self.load_font_bytes(name, bytes);
+        Ok(())
+    }
+
+    /// Load a TrueType font from memory. Loading a font with an existing name replaces it.
+    pub fn load_font_bytes(&mut self, name: &str, bytes: Vec<u8>) {
+        self.fonts.retain(|(font, _), _| font != name);
+        self.cache.retain(|key, _| key.font != name);
+        self.font_sources.insert(name.to_owned(), bytes);
     }
 
-    pub fn frame_end(&mut self) {
-        for key in &self.unused_keys {
-            self.cache.remove(key);
+    /// Change the amount of laid out lines that are kept for `draw_at`.
+    /// Text that is drawn every frame should use a `RetainedText` instead of a larger cache.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache.set_capacity(capacity);
+    }
+
+    pub fn cached_lines(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// The font is rasterized the first time it is used at a size
+    fn font(&mut self, name: &str, size: u32) -> Result<&mut Font> {
+        let key = (name.to_owned(), size);
+        if !self.fonts.contains_key(&key) {
+            let source = self
+                .font_sources
+                .get(name)
+                .ok_or_else(|| Error::font_not_found(name))?;
+            let texture = FontTexture::new(&self.display, &source[..], size)?;
+            self.fonts.insert(
+                key.clone(),
+                Font {
+                    texture: Rc::new(texture),
+                    advances: HashMap::new(),
+                },
+            );
         }
-        self.unused_keys.clear();
-        for key in self.cache.keys() {
-            self.unused_keys.push(key.clone());
+        Ok(self.fonts.get_mut(&key).unwrap())
+    }
+
+    fn line_width(&mut self, line: &str, style: &TextStyle) -> Result<f32> {
+        let em_pixels = style.size as f32 * CAP_HEIGHT;
+        self.font(&style.font, style.size)?;
+        let system = &self.system;
+        let font = self
+            .fonts
+            .get_mut(&(style.font.clone(), style.size))
+            .unwrap();
+        let mut width = 0f32;
+        for character in line.chars() {
+            // glium_text has no kerning, so a line is exactly as wide as its characters together
+            let texture = &font.texture;
+            let advance = *font.advances.entry(character).or_insert_with(|| {
+                TextDisplay::new(system, texture.clone(), &character.to_string()).get_width()
+                    * em_pixels
+            });
+            width += advance;
+        }
+        Ok(width)
+    }
+
+    /// Split the text into lines, at line breaks and wherever a line would become wider than `wrap_width`.
+    /// Returns every line with its width in pixels.
+    pub fn layout(&mut self, text: &str, style: &TextStyle) -> Result<Vec<(String, f32)>> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let wrap_width = match style.wrap_width {
+                Some(wrap_width) => wrap_width,
+                None => {
+                    let width = self.line_width(paragraph, style)?;
+                    lines.push((paragraph.to_owned(), width));
+                    continue;
+                }
+            };
+            let mut line = String::new();
+            let mut line_width = 0f32;
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() {
+                    word.to_owned()
+                } else {
+                    format!("{} {}", line, word)
+                };
+                let candidate_width = self.line_width(&candidate, style)?;
+                if candidate_width > wrap_width && !line.is_empty() {
+                    // Words that are wider than the wrap width get a line of their own
+                    lines.push((line, line_width));
+                    line_width = self.line_width(word, style)?;
+                    line = word.to_owned();
+                } else {
+                    line = candidate;
+                    line_width = candidate_width;
+                }
+            }
+            lines.push((line, line_width));
         }
+        Ok(lines)
     }
 
-    pub fn draw_at(
+    /// The width and height of the text in pixels, if it were drawn with the given style
+    pub fn measure(&mut self, text: &str, style: &TextStyle) -> Result<(f32, f32)> {
+        let lines = self.layout(text, style)?;
+        let width = lines.iter().map(|(_, width)| *width).fold(0f32, f32::max);
+        Ok((width, lines.len() as f32 * style.line_height()))
+    }
+
+    /// Lay out the lines of the text relative to the alignment point, as `(line, x, y)`
+    fn place_lines(&mut self, text: &str, style: &TextStyle) -> Result<Vec<(String, f32, f32)>> {
+        let lines = self.layout(text, style)?;
+        Ok(lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, (line, width))| {
+                let x = match style.align {
+                    TextAlign::Left => 0f32,
+                    TextAlign::Center => -width / 2f32,
+                    TextAlign::Right => -width,
+                };
+                (line, x, index as f32 * style.line_height())
+            })
+            .collect())
+    }
+
+    /// Draw text with its first line's top at `y`. `x` is where the lines are aligned to.
+    /// Laid out lines are kept in a cache of limited size, where the least recently drawn lines are removed first.
+    pub fn draw_at<S: Surface>(
         &mut self,
-        frame: &mut Frame,
-        name: String,
-        (screen_width, screen_height): (f32, f32),
+        frame: &mut S,
+        text: &str,
+        screen_size: (f32, f32),
         (x, y): (f32, f32),
-        color: Color,
+        style: &TextStyle,
     ) -> Result<()> {
-        self.unused_keys.retain(|k| *k != name);
-
-        let horizontal_scale = 0.025f32;
-        let vertical_scale = 0.05f32;
-
-        const VERTICAL_OFFSET: f32 = 12f32;
-
-        let x = (x / screen_width) * 2f32 - 1f32;
-        let y = 1f32 - ((y + VERTICAL_OFFSET) / screen_height) * 2f32;
-        // let x = 0.5f32;
-        // let y = 0.5f32;
-        let matrix = [
-            [horizontal_scale, 0.0, 0.0, 0.0],
-            [0.0, vertical_scale, 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [x, y, 0.0, 1.0],
-        ];
-
-        if let Some(ref text) = self.cache.get(&name) {
-            self.unused_keys.retain(|s| *s == name);
-            draw(text, &self.system, frame, matrix, color);
-            return Ok(());
+        for (line, left, top) in self.place_lines(text, style)? {
+            if line.is_empty() {
+                continue;
+            }
+            let matrix = line_matrix(screen_size, (x + left, y + top), style.size);
+            let key = TextKey {
+                font: style.font.clone(),
+                size: style.size,
+                text: line,
+            };
+            if let Some(display) = self.cache.get(&key) {
+                draw(display, &self.system, frame, matrix, style.color);
+                continue;
+            }
+            let texture = self.font(&style.font, style.size)?.texture.clone();
+            let display = TextDisplay::new(&self.system, texture, &key.text);
+            draw(&display, &self.system, frame, matrix, style.color);
+            self.cache.insert(key, display);
         }
-        let text = TextDisplay::new(&self.system, self.font.clone(), &name);
-        draw(&text, &self.system, frame, matrix, color);
-        self.cache.insert(name, text);
         Ok(())
     }
+
+    /// Lay out text once, to be drawn with `draw_retained` as often as needed
+    pub fn create_text(&mut self, text: &str, style: &TextStyle) -> Result<RetainedText> {
+        let size = self.measure(text, style)?;
+        let texture = self.font(&style.font, style.size)?.texture.clone();
+        let lines = self
+            .place_lines(text, style)?
+            .into_iter()
+            .filter(|(line, _, _)| !line.is_empty())
+            .map(|(line, x, y)| (TextDisplay::new(&self.system, texture.clone(), &line), x, y))
+            .collect();
+        Ok(RetainedText {
+            text: text.to_owned(),
+            style: style.clone(),
+            lines,
+            size,
+        })
+    }
+
+    /// Change the text of a retained text. Nothing happens if the text is the same.
+    pub fn update_text(&mut self, retained: &mut RetainedText, text: &str) -> Result<()> {
+        if retained.text != text {
+            *retained = self.create_text(text, &retained.style)?;
+        }
+        Ok(())
+    }
+
+    pub fn draw_retained<S: Surface>(
+        &self,
+        frame: &mut S,
+        retained: &RetainedText,
+        screen_size: (f32, f32),
+        (x, y): (f32, f32),
+    ) {
+        for (display, left, top) in &retained.lines {
+            let matrix = line_matrix(screen_size, (x + left, y + top), retained.style.size);
+            draw(display, &self.system, frame, matrix, retained.style.color);
+        }
+    }
+}
+
+/// The matrix that draws a line of glium_text with its top-left corner at the given pixel position
+fn line_matrix(
+    (screen_width, screen_height): (f32, f32),
+    (left, top): (f32, f32),
+    size: u32,
+) -> [[f32; 4]; 4] {
+    let em_pixels = size as f32 * CAP_HEIGHT;
+    let baseline = top + size as f32 * ASCENT;
+    [
+        [em_pixels * 2f32 / screen_width, 0.0, 0.0, 0.0],
+        [0.0, em_pixels * 2f32 / screen_height, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [
+            (left / screen_width) * 2f32 - 1f32,
+            1f32 - (baseline / screen_height) * 2f32,
+            0.0,
+            1.0,
+        ],
+    ]
 }
@@ -0,0 +1,93 @@
+/// Something the player typed, in the order it was typed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextEvent {
+    Character(char),
+    Backspace,
+    /// Enter was pressed
+    Submit,
+}
+
+/// The text typed during the current frame, with the entity that has claimed the text focus.
+/// While an entity has the focus, `GameState` reports all actions as released,
+/// so typing a name does not also move the ship.
+#[derive(Default)]
+pub struct TextInput {
+    events: Vec<TextEvent>,
+    focus: Option<u64>,
+}
+
+impl TextInput {
+    pub fn frame_start(&mut self) {
+        self.events.clear();
+    }
+
+    /// Record a character from the window. Control characters other than backspace and enter are ignored.
+    pub fn push_character(&mut self, character: char) {
+        let event = match character {
+            '\u{8}' | '\u{7f}' => TextEvent::Backspace,
+            '\r' | '\n' => TextEvent::Submit,
+            c if c.is_control() => return,
+            c => TextEvent::Character(c),
+        };
+        self.events.push(event);
+    }
+
+    pub fn events(&self) -> &[TextEvent] {
+        &self.events
+    }
+
+    /// The characters typed this frame, without backspaces
+    pub fn characters(&self) -> String {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                TextEvent::Character(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn submitted_this_frame(&self) -> bool {
+        self.events.contains(&TextEvent::Submit)
+    }
+
+    /// Apply the typing of this frame to a text, keeping it at most `max_length` characters long.
+    /// Returns true when Enter was pressed.
+    pub fn apply_to(&self, text: &mut String, max_length: usize) -> bool {
+        let mut submitted = false;
+        for event in &self.events {
+            match event {
+                TextEvent::Character(c) => {
+                    if text.chars().count() < max_length {
+                        text.push(*c);
+                    }
+                }
+                TextEvent::Backspace => {
+                    text.pop();
+                }
+                TextEvent::Submit => submitted = true,
+            }
+        }
+        submitted
+    }
+
+    /// Take the text focus for an entity, away from any other entity that had it
+    pub fn claim_focus(&mut self, entity_id: u64) {
+        self.focus = Some(entity_id);
+    }
+
+    /// Give up the text focus, if the entity has it
+    pub fn release_focus(&mut self, entity_id: u64) {
+        if self.focus == Some(entity_id) {
+            self.focus = None;
+        }
+    }
+
+    pub fn focus(&self) -> Option<u64> {
+        self.focus
+    }
+
+    pub fn has_focus(&self) -> bool {
+        self.focus.is_some()
+    }
+}
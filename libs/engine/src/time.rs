@@ -1,12 +1,83 @@
-use __time::OffsetDateTime;
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub fn get() -> u64 {
-    (OffsetDateTime::now_utc() - OffsetDateTime::unix_epoch()).whole_milliseconds() as u64
+/// A source of time in milliseconds, counted from an arbitrary start
+pub trait Clock {
+    fn now(&self) -> f64;
+
+    /// Wait until the clock reaches `deadline`. Sleeping is not precise,
+    /// so the last `spin_threshold` milliseconds are spent spinning.
+    fn wait_until(&self, deadline: f64, spin_threshold: f64) {
+        let remaining = deadline - self.now();
+        if remaining > spin_threshold.max(0f64) {
+            thread::sleep(Duration::from_secs_f64(
+                (remaining - spin_threshold.max(0f64)) / 1000f64,
+            ));
+        }
+        while self.now() < deadline {
+            thread::yield_now();
+        }
+    }
+}
+
+/// Real time that never goes backwards, counted from the creation of the clock
+pub struct MonotonicClock {
+    start: Instant,
+}
+
+impl Default for MonotonicClock {
+    fn default() -> MonotonicClock {
+        MonotonicClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000f64
+    }
+}
+
+/// A clock that only moves when it's told to or waited on, so tests can step the engine by exact frame times.
+/// Keep an `Rc` of it after handing it to the engine, to advance it from the test.
+#[derive(Default)]
+pub struct MockClock {
+    now: Cell<f64>,
+}
+
+impl MockClock {
+    pub fn new(now: f64) -> MockClock {
+        MockClock {
+            now: Cell::new(now),
+        }
+    }
+
+    pub fn advance(&self, milliseconds: f64) {
+        self.now.set(self.now.get() + milliseconds);
+    }
+
+    pub fn set(&self, now: f64) {
+        self.now.set(now);
+    }
 }
 
-pub fn has_elapsed(time: &mut u64, interval_in_ms: u64) -> bool {
-    let compare_time = get();
-    if *time + interval_in_ms < compare_time {
+impl Clock for MockClock {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+
+    /// Waiting takes no time, the clock moves to the deadline at once
+    fn wait_until(&self, deadline: f64, _: f64) {
+        if deadline > self.now.get() {
+            self.now.set(deadline);
+        }
+    }
+}
+
+pub fn has_elapsed(clock: &dyn Clock, time: &mut f64, interval_in_ms: f64) -> bool {
+    if *time + interval_in_ms < clock.now() {
         *time += interval_in_ms;
         true
     } else {
@@ -14,9 +85,68 @@ pub fn has_elapsed(time: &mut u64, interval_in_ms: u64) -> bool {
     }
 }
 
-pub fn since(time: &mut u64) -> u64 {
-    let time_now = get();
-    let diff = time_now - *time;
+/// The milliseconds since `time`, which is moved to now. Never negative, even when a clock is replaced.
+pub fn since(clock: &dyn Clock, time: &mut f64) -> f64 {
+    let time_now = clock.now();
+    let diff = (time_now - *time).max(0f64);
     *time = time_now;
     diff
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{has_elapsed, since, Clock, MockClock, MonotonicClock};
+
+    #[test]
+    fn mock_clocks_only_move_when_told() {
+        let clock = MockClock::new(10f64);
+        assert_eq!(clock.now(), 10f64);
+        clock.advance(5.5);
+        assert_eq!(clock.now(), 15.5);
+        clock.set(3f64);
+        assert_eq!(clock.now(), 3f64);
+        clock.wait_until(20f64, 2f64);
+        assert_eq!(clock.now(), 20f64);
+        clock.wait_until(10f64, 2f64);
+        assert_eq!(clock.now(), 20f64);
+    }
+
+    #[test]
+    fn since_moves_the_time_to_now() {
+        let clock = MockClock::new(100f64);
+        let mut time = 40f64;
+        assert_eq!(since(&clock, &mut time), 60f64);
+        assert_eq!(time, 100f64);
+        clock.advance(16.5);
+        assert_eq!(since(&clock, &mut time), 16.5);
+        assert_eq!(since(&clock, &mut time), 0f64);
+    }
+
+    #[test]
+    fn since_is_never_negative() {
+        let clock = MockClock::new(10f64);
+        let mut time = 50f64;
+        assert_eq!(since(&clock, &mut time), 0f64);
+        assert_eq!(time, 10f64);
+    }
+
+    #[test]
+    fn has_elapsed_counts_whole_intervals() {
+        let clock = MockClock::new(0f64);
+        let mut time = 0f64;
+        assert!(!has_elapsed(&clock, &mut time, 100f64));
+        clock.advance(250f64);
+        assert!(has_elapsed(&clock, &mut time, 100f64));
+        assert!(has_elapsed(&clock, &mut time, 100f64));
+        assert!(!has_elapsed(&clock, &mut time, 100f64));
+        assert_eq!(time, 200f64);
+    }
+
+    #[test]
+    fn monotonic_clocks_wait_until_the_deadline() {
+        let clock = MonotonicClock::default();
+        let deadline = clock.now() + 3f64;
+        clock.wait_until(deadline, 1f64);
+        assert!(clock.now() >= deadline);
+    }
+}
@@ -0,0 +1,585 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use super::{
+    Arguments, Camera, Color, EngineGraphics, KeyboardState, MouseButton, MouseState, NineSlice,
+    Result, TGraphicIndex, TextAlign, TextStyle, VirtualKeyCode,
+};
+
+/// The width of widgets that are created outside of a list or panel
+const DEFAULT_WIDTH: f32 = 200f32;
+/// How many key presses it takes to move a slider from one end to the other
+const SLIDER_STEPS: f32 = 20f32;
+const SLIDER_TRACK_HEIGHT: f32 = 4f32;
+const SLIDER_HANDLE_WIDTH: f32 = 8f32;
+const FOCUS_THICKNESS: f32 = 2f32;
+
+/// The sizes and colours of the widgets
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiStyle {
+    pub text: TextStyle,
+    pub widget_height: f32,
+    /// The space between two widgets in a list
+    pub spacing: f32,
+    /// The space between the edge of a panel and its widgets
+    pub padding: f32,
+    pub panel_color: Color,
+    pub widget_color: Color,
+    pub hovered_color: Color,
+    pub pressed_color: Color,
+    /// The colour of a ticked toggle and the filled part of a slider
+    pub fill_color: Color,
+    /// The outline of the widget that has the keyboard focus
+    pub focus_color: Color,
+}
+
+impl Default for UiStyle {
+    fn default() -> UiStyle {
+        UiStyle {
+            text: TextStyle::default().with_size(18),
+            widget_height: 32f32,
+            spacing: 8f32,
+            padding: 16f32,
+            panel_color: (0.05, 0.05, 0.12, 0.85),
+            widget_color: (0.2, 0.2, 0.35, 0.9),
+            hovered_color: (0.3, 0.3, 0.5, 0.9),
+            pressed_color: (0.12, 0.12, 0.22, 0.9),
+            fill_color: (0.4, 0.7, 1.0, 1.0),
+            focus_color: (1.0, 0.85, 0.3, 1.0),
+        }
+    }
+}
+
+/// Graphics for the widgets. Without them, the widgets are drawn as plain rectangles.
+/// The graphics are multiplied with the colours of the `UiStyle`, so white graphics work best.
+pub struct UiSkin<T: TGraphicIndex> {
+    pub panel: Option<NineSlice<T>>,
+    pub button: Option<NineSlice<T>>,
+}
+
+impl<T: TGraphicIndex> Default for UiSkin<T> {
+    fn default() -> UiSkin<T> {
+        UiSkin {
+            panel: None,
+            button: None,
+        }
+    }
+}
+
+type Rectangle = (f32, f32, f32, f32);
+
+#[derive(Copy, Clone, Default)]
+struct WidgetState {
+    focused: bool,
+    hovered: bool,
+    pressed: bool,
+}
+
+enum Command {
+    Panel(Rectangle),
+    /// The arguments of the text, already formatted
+    Label(Rectangle, String, Vec<(String, String)>),
+    Button(Rectangle, String, WidgetState),
+    Toggle(Rectangle, String, bool, WidgetState),
+    /// The fraction is how far the value is between the minimum and maximum
+    Slider(Rectangle, String, f32, WidgetState),
+}
+
+impl Command {
+    fn rectangle(&self) -> Rectangle {
+        match self {
+            Command::Panel(rectangle)
+            | Command::Label(rectangle, _, _)
+            | Command::Button(rectangle, _, _)
+            | Command::Toggle(rectangle, _, _, _)
+            | Command::Slider(rectangle, _, _, _) => *rectangle,
+        }
+    }
+}
+
+/// Where the next widget of a list goes
+struct Layout {
+    x: f32,
+    y: f32,
+    width: f32,
+    /// The command of the panel around the list, whose height is known when the panel ends
+    panel: Option<usize>,
+}
+
+/// An immediate mode UI. Entities create their widgets in `update` through `GameState::ui`, every frame,
+/// and get back whether the widget was used. The engine draws the widgets in screen coordinates,
+/// above the world and the post-processing.
+///
+/// Widgets are identified by the id string they are created with, which has to be unique for the frame.
+/// Their texts are looked up in the localization, so they can be keys as well as plain text.
+///
+/// The keyboard moves the focus with the arrow keys and Tab, activates with Enter or Space,
+/// and changes sliders with Left and Right. The mouse focuses the widget under the cursor.
+pub struct Ui {
+    pub style: UiStyle,
+    commands: Vec<Command>,
+    /// The panels and widgets of the last frame, for entities updated before this frame's widgets exist
+    previous_areas: Vec<Rectangle>,
+    layouts: Vec<Layout>,
+    /// The widgets that can be focused, in the order they were created this frame
+    focus_order: Vec<u64>,
+    previous_focus_order: Vec<u64>,
+    focused: Option<u64>,
+    /// The widget the mouse button was pressed on
+    active: Option<u64>,
+    activate: bool,
+    adjust: f32,
+    pointer: (f32, f32),
+    pointer_moved: bool,
+    pointer_down: bool,
+    pointer_pressed: bool,
+    pointer_released: bool,
+}
+
+impl Default for Ui {
+    fn default() -> Ui {
+        Ui {
+            style: UiStyle::default(),
+            commands: Vec::new(),
+            previous_areas: Vec::new(),
+            layouts: Vec::new(),
+            focus_order: Vec::new(),
+            previous_focus_order: Vec::new(),
+            focused: None,
+            active: None,
+            activate: false,
+            adjust: 0f32,
+            pointer: (-1f32, -1f32),
+            pointer_moved: false,
+            pointer_down: false,
+            pointer_pressed: false,
+            pointer_released: false,
+        }
+    }
+}
+
+impl Ui {
+    /// Forget the widgets of the last frame and read the input for this one.
+    /// The engine calls this before the entities are updated, without the keyboard while an entity is typing.
+    pub fn begin_frame(&mut self, keyboard: Option<&KeyboardState>, mouse: &MouseState) {
+        self.previous_areas = self.commands.drain(..).map(|c| c.rectangle()).collect();
+        self.layouts.clear();
+        self.previous_focus_order = mem::replace(&mut self.focus_order, Vec::new());
+        if let Some(focused) = self.focused {
+            if !self.previous_focus_order.contains(&focused) {
+                self.focused = None;
+            }
+        }
+
+        self.pointer = if mouse.is_inside() {
+            mouse.position()
+        } else {
+            (-1f32, -1f32)
+        };
+        self.pointer_moved = mouse.moved_this_frame();
+        self.pointer_down = mouse.is_button_down(MouseButton::Left);
+        self.pointer_pressed = mouse.is_pressed_this_frame(MouseButton::Left);
+        self.pointer_released = mouse.is_released_this_frame(MouseButton::Left);
+        if !self.pointer_down && !self.pointer_released {
+            self.active = None;
+        }
+
+        self.activate = false;
+        self.adjust = 0f32;
+        let keyboard = match keyboard {
+            Some(keyboard) => keyboard,
+            None => return,
+        };
+        if keyboard.is_pressed_this_frame(VirtualKeyCode::Down)
+            || keyboard.is_pressed_this_frame(VirtualKeyCode::Tab)
+        {
+            self.move_focus(1);
+        }
+        if keyboard.is_pressed_this_frame(VirtualKeyCode::Up) {
+            self.move_focus(-1);
+        }
+        self.activate = keyboard.is_pressed_this_frame(VirtualKeyCode::Return)
+            || keyboard.is_pressed_this_frame(VirtualKeyCode::Space);
+        if keyboard.is_pressed_this_frame(VirtualKeyCode::Left) {
+            self.adjust -= 1f32;
+        }
+        if keyboard.is_pressed_this_frame(VirtualKeyCode::Right) {
+            self.adjust += 1f32;
+        }
+    }
+
+    /// Move the focus through the widgets of the last frame, wrapping around at the ends
+    fn move_focus(&mut self, step: isize) {
+        let count = self.previous_focus_order.len() as isize;
+        if count == 0 {
+            return;
+        }
+        let index = match self.focused {
+            Some(focused) => {
+                let current = self
+                    .previous_focus_order
+                    .iter()
+                    .position(|id| *id == focused)
+                    .unwrap_or(0) as isize;
+                (current + step).rem_euclid(count)
+            }
+            None if step > 0 => 0,
+            None => count - 1,
+        };
+        self.focused = Some(self.previous_focus_order[index as usize]);
+    }
+
+    /// Whether a widget has the keyboard focus, so keys like Space are used by the UI
+    pub fn has_focus(&self) -> bool {
+        self.focused.is_some()
+    }
+
+    /// Whether the mouse is over a panel or widget, or dragging one, so clicks should not go to the game
+    pub fn wants_pointer(&self) -> bool {
+        let (pointer_x, pointer_y) = self.pointer;
+        self.active.is_some()
+            || self
+                .commands
+                .iter()
+                .map(Command::rectangle)
+                .chain(self.previous_areas.iter().cloned())
+                .any(|(x, y, width, height)| {
+                    pointer_x >= x
+                        && pointer_x < x + width
+                        && pointer_y >= y
+                        && pointer_y < y + height
+                })
+    }
+
+    /// Give the keyboard focus to a widget, e.g. the first button of a menu when it opens
+    pub fn focus(&mut self, id: &str) {
+        self.focused = Some(widget_id(id));
+    }
+
+    /// The height of a panel with the given amount of widgets, to position it before creating it
+    pub fn panel_height(&self, widgets: usize) -> f32 {
+        let widgets = widgets as f32;
+        self.style.padding * 2f32
+            + widgets * self.style.widget_height
+            + (widgets - 1f32).max(0f32) * self.style.spacing
+    }
+
+    /// Place the following widgets below each other, starting at the given screen position
+    pub fn begin_list(&mut self, x: f32, y: f32, width: f32) {
+        self.layouts.push(Layout {
+            x,
+            y,
+            width,
+            panel: None,
+        });
+    }
+
+    pub fn end_list(&mut self) {
+        self.layouts.pop();
+    }
+
+    /// A list with a background. Its height grows with the widgets in it.
+    pub fn begin_panel(&mut self, x: f32, y: f32, width: f32) {
+        self.commands.push(Command::Panel((x, y, width, 0f32)));
+        let padding = self.style.padding;
+        self.layouts.push(Layout {
+            x: x + padding,
+            y: y + padding,
+            width: width - padding * 2f32,
+            panel: Some(self.commands.len() - 1),
+        });
+    }
+
+    /// Returns the size of the panel
+    pub fn end_panel(&mut self) -> (f32, f32) {
+        let layout = match self.layouts.pop() {
+            Some(layout) => layout,
+            None => return (0f32, 0f32),
+        };
+        let (padding, spacing) = (self.style.padding, self.style.spacing);
+        if let Some(Command::Panel((_, top, width, height))) =
+            layout.panel.and_then(|index| self.commands.get_mut(index))
+        {
+            *height = (layout.y - spacing + padding - *top).max(padding * 2f32);
+            return (*width, *height);
+        }
+        (0f32, 0f32)
+    }
+
+    fn next_rectangle(&mut self) -> Rectangle {
+        if self.layouts.is_empty() {
+            self.begin_list(0f32, 0f32, DEFAULT_WIDTH);
+        }
+        let (height, spacing) = (self.style.widget_height, self.style.spacing);
+        let layout = self.layouts.last_mut().unwrap();
+        let rectangle = (layout.x, layout.y, layout.width, height);
+        layout.y += height + spacing;
+        rectangle
+    }
+
+    /// Handle focus and mouse for a widget. Returns its state and whether it was clicked or activated.
+    fn interact(&mut self, id: u64, (x, y, width, height): Rectangle) -> (WidgetState, bool) {
+        self.focus_order.push(id);
+        let (pointer_x, pointer_y) = self.pointer;
+        let hovered =
+            pointer_x >= x && pointer_x < x + width && pointer_y >= y && pointer_y < y + height;
+        if hovered && (self.pointer_moved || self.pointer_pressed) {
+            self.focused = Some(id);
+        }
+        if hovered && self.pointer_pressed {
+            self.active = Some(id);
+        }
+        let focused = self.focused == Some(id);
+        let active = self.active == Some(id);
+        let clicked = (focused && self.activate) || (active && hovered && self.pointer_released);
+        let state = WidgetState {
+            focused,
+            hovered,
+            pressed: active && self.pointer_down,
+        };
+        (state, clicked)
+    }
+
+    pub fn label(&mut self, text: &str) {
+        self.label_with(text, &[]);
+    }
+
+    /// A label with `{name}` arguments in its text, see `Localization::format`
+    pub fn label_with(&mut self, text: &str, arguments: &Arguments) {
+        let rectangle = self.next_rectangle();
+        let arguments = arguments
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), value.to_string()))
+            .collect();
+        self.commands
+            .push(Command::Label(rectangle, text.to_owned(), arguments));
+    }
+
+    /// Returns true when the button was clicked or activated with the keyboard
+    pub fn button(&mut self, id: &str, text: &str) -> bool {
+        let rectangle = self.next_rectangle();
+        let (state, clicked) = self.interact(widget_id(id), rectangle);
+        self.commands
+            .push(Command::Button(rectangle, text.to_owned(), state));
+        clicked
+    }
+
+    /// A check box. Returns true when the value was changed.
+    pub fn toggle(&mut self, id: &str, text: &str, value: &mut bool) -> bool {
+        let rectangle = self.next_rectangle();
+        let (state, clicked) = self.interact(widget_id(id), rectangle);
+        if clicked {
+            *value = !*value;
+        }
+        self.commands
+            .push(Command::Toggle(rectangle, text.to_owned(), *value, state));
+        clicked
+    }
+
+    /// A value between `min` and `max`, changed by dragging or with Left and Right.
+    /// Returns true when the value was changed.
+    pub fn slider(&mut self, id: &str, text: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let rectangle = self.next_rectangle();
+        let id = widget_id(id);
+        let (state, _) = self.interact(id, rectangle);
+        let previous = *value;
+        if self.active == Some(id) && self.pointer_down {
+            let (x, _, width, _) = rectangle;
+            let fraction = ((self.pointer.0 - x) / width).max(0f32).min(1f32);
+            *value = min + fraction * (max - min);
+        } else if state.focused && self.adjust != 0f32 {
+            *value += self.adjust * (max - min) / SLIDER_STEPS;
+        }
+        *value = value.max(min.min(max)).min(max.max(min));
+        let fraction = if max != min {
+            (*value - min) / (max - min)
+        } else {
+            0f32
+        };
+        self.commands
+            .push(Command::Slider(rectangle, text.to_owned(), fraction, state));
+        *value != previous
+    }
+
+    /// Draw the widgets of this frame in screen coordinates
+    pub fn draw<T: TGraphicIndex>(
+        &self,
+        graphics: &mut EngineGraphics<T>,
+        skin: &UiSkin<T>,
+    ) -> Result<()> {
+        if self.commands.is_empty() {
+            return Ok(());
+        }
+        let camera = mem::replace(&mut graphics.camera, Camera::default());
+        let result = self.draw_commands(graphics, skin);
+        graphics.camera = camera;
+        result
+    }
+
+    fn draw_commands<T: TGraphicIndex>(
+        &self,
+        graphics: &mut EngineGraphics<T>,
+        skin: &UiSkin<T>,
+    ) -> Result<()> {
+        let style = &self.style;
+        for command in &self.commands {
+            match command {
+                Command::Panel(rectangle) => {
+                    draw_background(graphics, skin.panel.as_ref(), *rectangle, style.panel_color)?;
+                }
+                Command::Label(rectangle, text, arguments) => {
+                    self.draw_label(graphics, *rectangle, text, arguments, TextAlign::Left)?;
+                }
+                Command::Button(rectangle, text, state) => {
+                    draw_background(
+                        graphics,
+                        skin.button.as_ref(),
+                        *rectangle,
+                        self.widget_color(*state),
+                    )?;
+                    self.draw_label(graphics, *rectangle, text, &[], TextAlign::Center)?;
+                    self.draw_focus(graphics, *rectangle, *state)?;
+                }
+                Command::Toggle((x, y, width, height), text, value, state) => {
+                    let size = height * 0.7;
+                    let (box_x, box_y) = (*x, y + (height - size) / 2f32);
+                    let color = self.widget_color(*state);
+                    draw_background(
+                        graphics,
+                        skin.button.as_ref(),
+                        (box_x, box_y, size, size),
+                        color,
+                    )?;
+                    if *value {
+                        let inset = size * 0.25;
+                        graphics.draw_rectangle(
+                            box_x + inset,
+                            box_y + inset,
+                            size - inset * 2f32,
+                            size - inset * 2f32,
+                            style.fill_color,
+                        )?;
+                    }
+                    let text_x = x + size + style.spacing;
+                    self.draw_label(
+                        graphics,
+                        (text_x, *y, width - size - style.spacing, *height),
+                        text,
+                        &[],
+                        TextAlign::Left,
+                    )?;
+                    self.draw_focus(graphics, (box_x, box_y, size, size), *state)?;
+                }
+                Command::Slider((x, y, width, height), text, fraction, state) => {
+                    let track_y = y + height - SLIDER_TRACK_HEIGHT * 2f32;
+                    graphics.draw_rectangle(
+                        *x,
+                        track_y,
+                        *width,
+                        SLIDER_TRACK_HEIGHT,
+                        self.widget_color(*state),
+                    )?;
+                    graphics.draw_rectangle(
+                        *x,
+                        track_y,
+                        width * fraction,
+                        SLIDER_TRACK_HEIGHT,
+                        style.fill_color,
+                    )?;
+                    let handle_x = x + width * fraction - SLIDER_HANDLE_WIDTH / 2f32;
+                    let handle_height = SLIDER_TRACK_HEIGHT * 3f32;
+                    graphics.draw_rectangle(
+                        handle_x,
+                        track_y + (SLIDER_TRACK_HEIGHT - handle_height) / 2f32,
+                        SLIDER_HANDLE_WIDTH,
+                        handle_height,
+                        style.text.color,
+                    )?;
+                    let label_height = height - SLIDER_TRACK_HEIGHT * 3f32;
+                    self.draw_label(
+                        graphics,
+                        (*x, *y, *width, label_height),
+                        text,
+                        &[],
+                        TextAlign::Left,
+                    )?;
+                    self.draw_focus(graphics, (*x, *y, *width, *height), *state)?;
+                }
+            }
+        }
+        graphics.flush_primitives()
+    }
+
+    fn widget_color(&self, state: WidgetState) -> Color {
+        if state.pressed {
+            self.style.pressed_color
+        } else if state.hovered || state.focused {
+            self.style.hovered_color
+        } else {
+            self.style.widget_color
+        }
+    }
+
+    /// Draw a localized text, vertically centered in the rectangle
+    fn draw_label<T: TGraphicIndex>(
+        &self,
+        graphics: &mut EngineGraphics<T>,
+        (x, y, width, height): Rectangle,
+        text: &str,
+        arguments: &[(String, String)],
+        align: TextAlign,
+    ) -> Result<()> {
+        let arguments = arguments
+            .iter()
+            .map(|(name, value)| (name.as_str(), value as &dyn Display))
+            .collect::<Vec<_>>();
+        let text = graphics.localization.format(text, &arguments);
+        let style = self.style.text.clone().with_align(align);
+        let x = match align {
+            TextAlign::Left => x,
+            TextAlign::Center => x + width / 2f32,
+            TextAlign::Right => x + width,
+        };
+        let y = y + (height - style.size as f32) / 2f32;
+        graphics.draw_text(&text, x, y, &style)
+    }
+
+    fn draw_focus<T: TGraphicIndex>(
+        &self,
+        graphics: &mut EngineGraphics<T>,
+        (x, y, width, height): Rectangle,
+        state: WidgetState,
+    ) -> Result<()> {
+        if !state.focused {
+            return Ok(());
+        }
+        graphics.draw_rectangle_outline(
+            x,
+            y,
+            width,
+            height,
+            FOCUS_THICKNESS,
+            self.style.focus_color,
+        )
+    }
+}
+
+fn draw_background<T: TGraphicIndex>(
+    graphics: &mut EngineGraphics<T>,
+    slice: Option<&NineSlice<T>>,
+    (x, y, width, height): Rectangle,
+    color: Color,
+) -> Result<()> {
+    match slice {
+        Some(slice) => graphics.draw_nine_slice(slice, (x, y, width, height), color),
+        None => graphics.draw_rectangle(x, y, width, height, color),
+    }
+}
+
+fn widget_id(id: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    hasher.finish()
+}
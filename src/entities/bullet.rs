@@ -1,3 +1,4 @@
+use super::effects;
 use engine::*;
 use std::f32::consts::PI;
 use GraphicsEnum;
@@ -5,10 +6,23 @@ use GraphicsEnum;
 pub const WIDTH: f32 = 16.0;
 pub const HEIGHT: f32 = 16.0;
 
+pub const SHEET: &str = "assets/M484BulletCollection1.png";
+pub const ENEMY_WIDTH: f32 = 18.0;
+pub const ENEMY_HEIGHT: f32 = 18.0;
+
+/// The red star on the bullet sheet, growing and shrinking
+pub fn enemy_animation() -> Animation {
+    Animation::new(AnimationMode::PingPong)
+        .with_frame(SpriteRegion::new(135, 4, 18, 18), 80f32)
+        .with_frame(SpriteRegion::new(153, 4, 18, 18), 80f32)
+        .with_frame(SpriteRegion::new(173, 4, 18, 18), 80f32)
+}
+
 pub struct Bullet {
     start_x: f32,
     start_y: f32,
     is_player_bullet: bool,
+    animation: AnimationCursor,
 }
 
 impl Bullet {
@@ -17,6 +31,7 @@ impl Bullet {
             start_x: x,
             start_y: y,
             is_player_bullet,
+            animation: AnimationCursor::new(&enemy_animation()),
         }
     }
 }
@@ -66,6 +81,7 @@ impl EntityTrait<GraphicsEnum> for Bullet {
             }
         } else {
             state.x -= 0.5f32 * game_state.delta_time;
+            self.animation.update(game_state.delta_time);
 
             if state.x + state.hitbox.right < 0f32 {
                 state.active = false;
@@ -81,11 +97,24 @@ impl EntityTrait<GraphicsEnum> for Bullet {
         _: &mut EntityState,
     ) -> Vec<EntityEvent<GraphicsEnum>> {
         state.active = false;
-        Vec::new()
+        vec![
+            effects::hit_sparks(state.x, state.y),
+            effects::hit_sound(state.x, state.y),
+        ]
     }
 
     fn draw(&self, state: &EntityState, graphics: &mut EngineGraphics<GraphicsEnum>) -> Result<()> {
-        let angle = if self.is_player_bullet { 0f32 } else { PI };
-        graphics.draw(GraphicsEnum::Bullet, state.x, state.y, angle, 1f32)
+        if self.is_player_bullet {
+            graphics.draw(GraphicsEnum::Bullet, state.x, state.y, 0f32, 1f32)
+        } else {
+            graphics.draw_animation(
+                GraphicsEnum::EnemyBullet,
+                &self.animation,
+                state.x,
+                state.y,
+                PI,
+                1f32,
+            )
+        }
     }
 }
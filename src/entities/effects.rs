@@ -0,0 +1,73 @@
+use engine::*;
+use std::f32::consts::PI;
+use GraphicsEnum;
+
+pub const PARTICLE_REGION: (u32, u32, u32, u32) = (279, 157, 11, 11);
+pub const PARTICLE_WIDTH: f32 = 8f32;
+pub const PARTICLE_HEIGHT: f32 = 8f32;
+
+pub const SHOT_SOUND: &str = "shot";
+pub const HIT_SOUND: &str = "hit";
+pub const EXPLOSION_SOUND: &str = "explosion";
+/// The sounds the effects play, with their files
+pub const SOUNDS: [(&str, &str); 3] = [
+    (SHOT_SOUND, "assets/sounds/shot.wav"),
+    (HIT_SOUND, "assets/sounds/hit.wav"),
+    (EXPLOSION_SOUND, "assets/sounds/explosion.wav"),
+];
+
+/// The explosion of a destroyed ship
+pub fn explosion(x: f32, y: f32) -> EntityEvent<GraphicsEnum> {
+    let emitter = ParticleEmitter::burst(GraphicsEnum::Particle, x, y, 60)
+        .with_lifetime(300f32, 800f32)
+        .with_speed(0.02f32, 0.25f32)
+        .with_colors((1.0, 0.9, 0.3, 1.0), (1.0, 0.1, 0.0, 0.0))
+        .with_scale(1.5f32, 0.3f32);
+    EntityEvent::SpawnEmitter(emitter)
+}
+
+pub fn explosion_sound(x: f32, y: f32) -> EntityEvent<GraphicsEnum> {
+    EntityEvent::PlaySound(PlaySound::new(EXPLOSION_SOUND).at(x, y))
+}
+
+/// The explosion of the player's ship, which turns the music down so it can not be missed
+pub fn player_explosion_sound(x: f32, y: f32) -> EntityEvent<GraphicsEnum> {
+    let request = PlaySound::new(EXPLOSION_SOUND).at(x, y).ducking_music();
+    EntityEvent::PlaySound(request)
+}
+
+/// The sparks of a bullet hitting something
+pub fn hit_sparks(x: f32, y: f32) -> EntityEvent<GraphicsEnum> {
+    let emitter = ParticleEmitter::burst(GraphicsEnum::Particle, x, y, 10)
+        .with_lifetime(100f32, 250f32)
+        .with_speed(0.1f32, 0.3f32)
+        .with_colors((1.0, 1.0, 0.8, 1.0), (1.0, 0.8, 0.2, 0.0))
+        .with_scale(0.6f32, 0.2f32);
+    EntityEvent::SpawnEmitter(emitter)
+}
+
+pub fn hit_sound(x: f32, y: f32) -> EntityEvent<GraphicsEnum> {
+    let request = PlaySound::new(HIT_SOUND).with_volume(0.6f32).at(x, y);
+    EntityEvent::PlaySound(request)
+}
+
+/// A ship firing, following the entity with the given id.
+/// Quiet because the player fires ten times a second.
+pub fn shot_sound(entity_id: u64) -> EntityEvent<GraphicsEnum> {
+    let request = PlaySound::new(SHOT_SOUND)
+        .with_volume(0.3f32)
+        .following(entity_id);
+    EntityEvent::PlaySound(request)
+}
+
+/// The exhaust of a ship flying to the right, following the entity with the given id
+pub fn thruster(entity_id: u64, offset_x: f32) -> EntityEvent<GraphicsEnum> {
+    let emitter = ParticleEmitter::continuous(GraphicsEnum::Particle, offset_x, 0f32, 120f32)
+        .following(entity_id)
+        .with_direction(PI, 0.25f32)
+        .with_lifetime(150f32, 300f32)
+        .with_speed(0.1f32, 0.2f32)
+        .with_colors((0.6, 0.8, 1.0, 0.9), (0.2, 0.3, 1.0, 0.0))
+        .with_scale(1f32, 0.4f32);
+    EntityEvent::SpawnEmitter(emitter)
+}
@@ -1,5 +1,5 @@
 use engine::*;
-use entities::Bullet;
+use entities::{effects, Bullet};
 use std::f32::consts::PI;
 use GraphicsEnum;
 
@@ -10,10 +10,13 @@ pub struct Enemy1 {
     diagonal_direction: f32,
     bullet_target_time: f32,
     bullet_recharge_counter: f32,
+    flash_time: f32,
 }
 
 const ONETHIRD: f32 = 1f32 / 3f32;
 const TWOTHIRD: f32 = ONETHIRD * 2f32;
+const TINT: Color = (1.0, 0.55, 0.55, 1.0);
+const FLASH_DURATION: f32 = 100f32;
 
 impl Enemy1 {
     pub fn new(x: f32, y: f32, factor: f32) -> Enemy1 {
@@ -25,6 +28,7 @@ impl Enemy1 {
             diagonal_direction: 0f32,
             bullet_target_time,
             bullet_recharge_counter: bullet_target_time / 2f32,
+            flash_time: 0f32,
         }
     }
 }
@@ -71,6 +75,8 @@ impl EntityTrait<GraphicsEnum> for Enemy1 {
             state.y += self.diagonal_direction * 0.2f32 * game_state.delta_time;
         }
 
+        self.flash_time = (self.flash_time - game_state.delta_time).max(0f32);
+
         let mut result = Vec::new();
 
         self.bullet_recharge_counter += game_state.delta_time;
@@ -82,12 +88,17 @@ impl EntityTrait<GraphicsEnum> for Enemy1 {
         result
     }
     fn draw(&self, state: &EntityState, graphics: &mut EngineGraphics<GraphicsEnum>) -> Result<()> {
-        graphics.draw(
+        let effects = SpriteEffects {
+            flash: (1.0, 1.0, 1.0, self.flash_time / FLASH_DURATION),
+            ..SpriteEffects::tinted(TINT)
+        };
+        graphics.draw_with(
             GraphicsEnum::Player,
             state.x,
             state.y,
             PI / 2f32 * 3f32,
             1f32,
+            &effects,
         )
     }
     fn collided(
@@ -98,9 +109,13 @@ impl EntityTrait<GraphicsEnum> for Enemy1 {
     ) -> Vec<EntityEvent<GraphicsEnum>> {
         if self.health <= 1f32 {
             self_state.active = false;
-        } else {
-            self.health -= 1f32;
+            return vec![
+                effects::explosion(self_state.x, self_state.y),
+                effects::explosion_sound(self_state.x, self_state.y),
+            ];
         }
+        self.health -= 1f32;
+        self.flash_time = FLASH_DURATION;
 
         Vec::new()
     }
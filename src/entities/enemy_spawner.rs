@@ -1,4 +1,4 @@
-use super::{Enemy1, Enemy2}; //, Enemy3};
+use super::{music, Enemy1, Enemy2}; //, Enemy3};
 use engine::*;
 use rand::{prelude::StdRng, Rng, SeedableRng};
 use GraphicsEnum;
@@ -8,6 +8,7 @@ pub struct EnemySpawner {
     pub target_time_counter: f32,
     pub enemy_amount_to_spawn: u32,
     pub rng: StdRng,
+    pub music_started: bool,
 }
 
 impl EnemySpawner {
@@ -17,6 +18,7 @@ impl EnemySpawner {
             target_time_counter: 3000f32,
             enemy_amount_to_spawn: 3,
             rng: StdRng::seed_from_u64(0),
+            music_started: false,
         }
     }
 
@@ -37,6 +39,10 @@ impl EntityTrait<GraphicsEnum> for EnemySpawner {
     ) -> Vec<EntityEvent<GraphicsEnum>> {
         self.time_counter += game_state.delta_time;
         let mut result = Vec::new();
+        if !self.music_started {
+            self.music_started = true;
+            result.push(music::stage());
+        }
         while self.time_counter > self.target_time_counter {
             self.time_counter -= self.target_time_counter;
             let height = (game_state.screen_height) * self.rng.gen::<f32>();
@@ -63,6 +69,15 @@ impl EntityTrait<GraphicsEnum> for EnemySpawner {
             self.enemy_amount_to_spawn += 1;
             self.generate_time_target();
         }
+        // Clicks on the menu are not meant for the game
+        if cfg!(debug_assertions)
+            && game_state.mouse.is_pressed_this_frame(MouseButton::Left)
+            && !game_state.ui.wants_pointer()
+        {
+            let (x, y) = game_state.mouse.world_position();
+            let enemy = Enemy1::new(x, y, self.enemy_amount_to_spawn as f32);
+            result.push(EntityEvent::SpawnEntity(Box::new(enemy)));
+        }
         result
     }
 }
@@ -1,6 +1,8 @@
 pub mod bullet;
+pub mod effects;
 pub mod enemies;
 pub mod enemy_spawner;
+pub mod music;
 pub mod player;
 pub mod you_lost;
 
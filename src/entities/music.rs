@@ -0,0 +1,19 @@
+use engine::*;
+use GraphicsEnum;
+
+pub const STAGE_MUSIC: &str = "stage";
+pub const GAME_OVER_MUSIC: &str = "game_over";
+/// The tracks with their files and the frame their loop starts at, after the intro
+pub const TRACKS: [(&str, &str, u64); 2] = [
+    (STAGE_MUSIC, "assets/music/stage.wav", 17_640),
+    (GAME_OVER_MUSIC, "assets/music/game_over.wav", 0),
+];
+
+pub fn stage() -> EntityEvent<GraphicsEnum> {
+    EntityEvent::Music(MusicCommand::Play(PlayMusic::new(STAGE_MUSIC)))
+}
+
+pub fn game_over() -> EntityEvent<GraphicsEnum> {
+    let request = PlayMusic::new(GAME_OVER_MUSIC).with_crossfade(1500f32);
+    EntityEvent::Music(MusicCommand::Play(request))
+}
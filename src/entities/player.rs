@@ -1,4 +1,4 @@
-use super::{Bullet, YouLost};
+use super::{effects, music, Bullet, YouLost};
 use engine::*;
 use std::f32::consts::PI;
 use GraphicsEnum;
@@ -10,12 +10,20 @@ pub const WIDTH: f32 = 1306f32 / 20f32;
 pub const HEIGHT: f32 = 1656f32 / 20f32;
 const HORIZONTAL_SPEED: f32 = 0.3f32;
 const VERTICAL_SPEED: f32 = 0.4f32;
+const FLASH_DURATION: f32 = 150f32;
+const HIT_ABERRATION: f32 = 12f32;
+pub const HUD_FONT: &str = "hud";
+pub const HUD_FONT_FILE: &str = "assets/fonts/hud.fnt";
+pub const MOVE_X_AXIS: &str = "move_x";
+pub const MOVE_Y_AXIS: &str = "move_y";
 
 pub struct Player {
     pub last_bullet_time: f32,
     pub last_bullet_position_index: u8,
     pub health: u64,
     pub max_health: u64,
+    pub flash_time: f32,
+    pub has_thruster: bool,
 }
 
 impl Player {
@@ -25,6 +33,8 @@ impl Player {
             last_bullet_position_index: 0,
             health: 10,
             max_health: 10,
+            flash_time: 0f32,
+            has_thruster: false,
         }
     }
 }
@@ -63,21 +73,8 @@ impl EntityTrait<GraphicsEnum> for Player {
         game_state: &mut GameState,
         state: &mut EntityState,
     ) -> Vec<EntityEvent<GraphicsEnum>> {
-        let mut x = 0f32;
-        let mut y = 0f32;
-
-        if game_state.keyboard.is_keydown(VirtualKeyCode::A) {
-            x -= 1f32;
-        }
-        if game_state.keyboard.is_keydown(VirtualKeyCode::D) {
-            x += 1f32;
-        }
-        if game_state.keyboard.is_keydown(VirtualKeyCode::S) {
-            y += 1f32;
-        }
-        if game_state.keyboard.is_keydown(VirtualKeyCode::W) {
-            y -= 1f32;
-        }
+        let x = game_state.axis(MOVE_X_AXIS);
+        let y = game_state.axis(MOVE_Y_AXIS);
 
         state.x += x * HORIZONTAL_SPEED * game_state.delta_time;
         state.y += y * VERTICAL_SPEED * game_state.delta_time;
@@ -93,7 +90,14 @@ impl EntityTrait<GraphicsEnum> for Player {
             game_state.screen_height - state.hitbox.bottom,
         );
 
+        self.flash_time = (self.flash_time - game_state.delta_time).max(0f32);
+
         let mut result = Vec::new();
+        if !self.has_thruster {
+            self.has_thruster = true;
+            result.push(effects::thruster(state.id, -state.hitbox.left));
+        }
+
         if self.last_bullet_time < game_state.delta_time {
             self.last_bullet_time = PLAYER_FIRE_INTERVAL;
             let position = PLAYER_FIRE_POINTS[self.last_bullet_position_index as usize];
@@ -101,6 +105,7 @@ impl EntityTrait<GraphicsEnum> for Player {
 
             let bullet = Bullet::new(state.x + position[0], state.y + position[1], true);
             result.push(EntityEvent::SpawnEntity(Box::new(bullet)));
+            result.push(effects::shot_sound(state.id));
         } else {
             self.last_bullet_time -= game_state.delta_time;
         }
@@ -109,7 +114,26 @@ impl EntityTrait<GraphicsEnum> for Player {
     }
 
     fn draw(&self, state: &EntityState, graphics: &mut EngineGraphics<GraphicsEnum>) -> Result<()> {
-        graphics.draw(GraphicsEnum::Player, state.x, state.y, PI / 2f32, 1f32)?;
+        if let Some(effect) = graphics
+            .post_processing
+            .effect_mut(PostEffect::CHROMATIC_ABERRATION)
+        {
+            let amount = HIT_ABERRATION * self.flash_time / FLASH_DURATION;
+            effect.set_parameter("amount", PostParameter::Float(amount));
+        }
+
+        let effects = SpriteEffects {
+            flash: (1.0, 1.0, 1.0, self.flash_time / FLASH_DURATION),
+            ..SpriteEffects::default()
+        };
+        graphics.draw_with(
+            GraphicsEnum::Player,
+            state.x,
+            state.y,
+            PI / 2f32,
+            1f32,
+            &effects,
+        )?;
 
         let health_factor = (self.health as f32) / (self.max_health as f32);
         let healthbar_offset: (f32, f32) = (-state.hitbox.left * 0.75, -(state.hitbox.top + 25f32));
@@ -129,12 +153,33 @@ impl EntityTrait<GraphicsEnum> for Player {
             healthbar_size.1,
             COLOR_RED,
         )?;
+        graphics.draw_rectangle_outline(
+            state.x + healthbar_offset.0 - 1f32,
+            state.y + healthbar_offset.1 - 1f32,
+            healthbar_size.0 + 2f32,
+            healthbar_size.1 + 2f32,
+            1f32,
+            COLOR_WHITE,
+        )?;
+
+        let health_style = BitmapTextStyle::default()
+            .with_scale(0.75)
+            .with_color(COLOR_WHITE)
+            .monospaced_digits();
+        graphics.draw_bitmap_text(
+            HUD_FONT,
+            &format!("{}/{}", self.health, self.max_health),
+            state.x + healthbar_offset.0 + healthbar_size.0 + 6f32,
+            state.y + healthbar_offset.1 - 4f32,
+            &health_style,
+        )?;
 
-        graphics.draw_text_at(
-            "Hero".to_owned(),
+        graphics.draw_localized_text(
+            "player.name",
+            &[],
             state.x + healthbar_offset.0,
             state.y + healthbar_offset.1 - 15f32,
-            COLOR_WHITE,
+            &TextStyle::default().with_color(COLOR_WHITE),
         )?;
         Ok(())
     }
@@ -148,12 +193,18 @@ impl EntityTrait<GraphicsEnum> for Player {
         if self.health > 0 {
             self.health -= 1;
         }
+        self.flash_time = FLASH_DURATION;
         other_state.active = false;
 
         if self.health == 0 {
             self_state.active = false;
             let you_lost = Box::new(YouLost::new().unwrap());
-            vec![EntityEvent::SpawnEntity(you_lost)]
+            vec![
+                effects::explosion(self_state.x, self_state.y),
+                effects::player_explosion_sound(self_state.x, self_state.y),
+                music::game_over(),
+                EntityEvent::SpawnEntity(you_lost),
+            ]
         } else {
             Vec::new()
         }
@@ -6,6 +6,8 @@ pub struct YouLost {}
 
 pub const WIDTH: f32 = 128f32;
 pub const HEIGHT: f32 = 64f32;
+const PANEL_WIDTH: f32 = 400f32;
+pub const RESTART_ACTION: &str = "restart";
 
 impl YouLost {
     pub fn new() -> Result<YouLost> {
@@ -13,8 +15,6 @@ impl YouLost {
     }
 }
 
-const COLOR_WHITE: Color = (1.0, 1.0, 1.0, 1.0);
-
 impl EntityTrait<GraphicsEnum> for YouLost {
     fn identifying_string(&self) -> String {
         "You lost!".to_owned()
@@ -23,12 +23,7 @@ impl EntityTrait<GraphicsEnum> for YouLost {
     fn draw(&self, _: &EntityState, graphics: &mut EngineGraphics<GraphicsEnum>) -> Result<()> {
         let x = graphics.width / 2f32;
         let y = graphics.height / 2f32;
-        graphics.draw(GraphicsEnum::YouLost, x, y, 0.0f32, 1.0f32)?;
-
-        let x = graphics.width / 2f32 - 100f32;
-        let y = graphics.height / 2f32 + 50f32;
-
-        graphics.draw_text_at("Press space to restart".to_owned(), x, y, COLOR_WHITE)
+        graphics.draw(GraphicsEnum::YouLost, x, y, 0.0f32, 1.0f32)
     }
 
     fn update(
@@ -36,15 +31,27 @@ impl EntityTrait<GraphicsEnum> for YouLost {
         game_state: &mut GameState,
         state: &mut EntityState,
     ) -> Vec<EntityEvent<GraphicsEnum>> {
-        if game_state
-            .keyboard
-            .is_pressed_this_frame(VirtualKeyCode::Space)
-        {
+        let x = (game_state.screen_width - PANEL_WIDTH) / 2f32;
+        let y = (game_state.screen_height + HEIGHT) / 2f32 + 10f32;
+        game_state.ui.begin_panel(x, y, PANEL_WIDTH);
+        let key = game_state
+            .input
+            .bindings(RESTART_ACTION)
+            .first()
+            .map(|binding| binding.name())
+            .unwrap_or_default();
+        game_state
+            .ui
+            .label_with("you_lost.restart", &[("key", &key)]);
+        let restart_clicked = game_state.ui.button("restart", "you_lost.restart_button");
+        game_state.ui.end_panel();
+
+        if restart_clicked || game_state.action_pressed_this_frame(RESTART_ACTION) {
             state.active = false;
 
             let mut response = get_initial_state()
                 .into_iter()
-                .map(|s| EntityEvent::SpawnEntity(s))
+                .map(EntityEvent::SpawnEntity)
                 .collect::<Vec<_>>();
             response.insert(0, EntityEvent::ClearAllEntities);
             response
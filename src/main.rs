@@ -3,16 +3,24 @@ extern crate rand;
 
 mod entities;
 
-use engine::{Engine, EntityTrait};
+use std::path::Path;
+
+use engine::{
+    Engine, EntityTrait, InputBinding, Localization, NineSlice, PostEffect, SheetTransparency,
+    SpriteRegion, Starfield, VSync,
+};
 
 #[derive(PartialEq, Eq, Hash)]
 pub enum GraphicsEnum {
     Bullet,
+    EnemyBullet,
+    Particle,
     Player,
     Enemy1,
     Enemy2,
     Enemy3,
     YouLost,
+    UiPanel,
 }
 
 fn get_initial_state() -> Vec<Box<dyn EntityTrait<GraphicsEnum>>> {
@@ -23,8 +31,38 @@ fn get_initial_state() -> Vec<Box<dyn EntityTrait<GraphicsEnum>>> {
 
 impl engine::TGraphicIndex for GraphicsEnum {}
 
+/// The bindings players can change, created with the defaults when it does not exist
+const INPUT_CONFIG_FILE: &str = "input.cfg";
+
+fn bind_default_input(engine: &mut Engine<GraphicsEnum>) {
+    use engine::VirtualKeyCode::*;
+    let input = &mut engine.input;
+    input.bind(entities::you_lost::RESTART_ACTION, InputBinding::Key(Space));
+    for (negative, positive) in &[(A, D), (Left, Right)] {
+        input.bind_axis(
+            entities::player::MOVE_X_AXIS,
+            InputBinding::Key(*negative),
+            InputBinding::Key(*positive),
+        );
+    }
+    for (negative, positive) in &[(W, S), (Up, Down)] {
+        input.bind_axis(
+            entities::player::MOVE_Y_AXIS,
+            InputBinding::Key(*negative),
+            InputBinding::Key(*positive),
+        );
+    }
+}
+
 fn main() {
-    let mut engine = Engine::<GraphicsEnum>::new(1800f32, 600f32).unwrap();
+    let mut engine = Engine::<GraphicsEnum>::builder()
+        .with_title("Janfeb")
+        .with_size(1800f32, 600f32)
+        .with_vsync(VSync::Adaptive)
+        .with_multisampling(4)
+        .with_clear_color((0.01, 0.01, 0.04, 1.0))
+        .build()
+        .unwrap();
 
     engine
         .graphics
@@ -35,6 +73,31 @@ fn main() {
             entities::bullet::HEIGHT,
         )
         .unwrap();
+    engine
+        .graphics
+        .load_sprite_sheet(entities::bullet::SHEET, SheetTransparency::BlackToAlpha)
+        .unwrap();
+    engine
+        .graphics
+        .load_animation(
+            GraphicsEnum::EnemyBullet,
+            entities::bullet::SHEET,
+            &entities::bullet::enemy_animation(),
+            entities::bullet::ENEMY_WIDTH,
+            entities::bullet::ENEMY_HEIGHT,
+        )
+        .unwrap();
+    let (x, y, width, height) = entities::effects::PARTICLE_REGION;
+    engine
+        .graphics
+        .load_graphic_region(
+            GraphicsEnum::Particle,
+            entities::bullet::SHEET,
+            SpriteRegion::new(x, y, width, height),
+            entities::effects::PARTICLE_WIDTH,
+            entities::effects::PARTICLE_HEIGHT,
+        )
+        .unwrap();
     engine
         .graphics
         .load_graphic(
@@ -54,9 +117,68 @@ fn main() {
         )
         .unwrap();
 
+    engine
+        .graphics
+        .load_graphic(GraphicsEnum::UiPanel, "assets/ui/panel.png", 24f32, 24f32)
+        .unwrap();
+    engine.ui_skin.panel = Some(NineSlice::new(GraphicsEnum::UiPanel, 8f32));
+    engine.ui_skin.button = Some(NineSlice::new(GraphicsEnum::UiPanel, 8f32));
+
+    engine
+        .graphics
+        .load_bitmap_font(entities::player::HUD_FONT, entities::player::HUD_FONT_FILE)
+        .unwrap();
+
+    for (name, file) in &entities::effects::SOUNDS {
+        engine.audio.load_sound(name, file).unwrap();
+    }
+    for (name, file, loop_start) in &entities::music::TRACKS {
+        engine.audio.load_music(name, file).unwrap();
+        engine
+            .audio
+            .set_loop_points(name, *loop_start, None)
+            .unwrap();
+    }
+
+    engine
+        .graphics
+        .localization
+        .load_directory("assets/lang")
+        .unwrap();
+    if let Some(locale) = Localization::system_locale() {
+        engine.graphics.localization.set_locale(&locale);
+    }
+
+    bind_default_input(&mut engine);
+    if Path::new(INPUT_CONFIG_FILE).exists() {
+        engine.input.load_file(INPUT_CONFIG_FILE).unwrap();
+    } else {
+        engine.input.save_file(INPUT_CONFIG_FILE).unwrap();
+    }
+
+    engine.auto_pause = true;
+
+    engine.background.starfield = Some(Starfield::new(250, 0x5eed).with_velocity(-0.3, 0.0));
+
+    engine.graphics.post_processing.push(PostEffect::bloom());
+    engine
+        .graphics
+        .post_processing
+        .push(PostEffect::chromatic_aberration());
+    engine.graphics.post_processing.push(PostEffect::vignette());
+
     for entity in get_initial_state().into_iter() {
         engine.register_entity(entity);
     }
 
     engine.run();
+    for error in engine.take_errors() {
+        eprintln!("{:?}", error);
+    }
+    for error in engine.audio.take_errors() {
+        eprintln!("{:?}", error);
+    }
+    if let Some(Err(error)) = &engine.last_screenshot {
+        eprintln!("{:?}", error);
+    }
 }